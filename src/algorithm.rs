@@ -1,36 +1,337 @@
+pub mod bench;
+pub mod book;
 pub mod choose;
+pub mod difficulty;
 pub mod eval;
+pub mod handicap;
+pub mod params;
+pub mod perft;
+pub mod puzzle;
+pub mod record;
+pub mod review;
 pub mod score;
+pub mod strength;
+pub mod tablebase;
+pub mod tune;
+pub mod variant;
 
+use book::OpeningBook;
 use choose::Chooser;
 use cozy_chess::{Board, Move};
-use eval::Evaluator;
-use score::ScoreFunction;
-use std::rc::Rc;
+use eval::{Evaluator, ProgressCallback, SearchContext};
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use score::{Score, ScoreFunction};
+use std::{
+    future::Future,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+};
+use tablebase::Tablebase;
+
+// what a player worked out on its opponent's time
+#[derive(Default)]
+struct Ponder {
+    // the reply the player expects and the position it leads to
+    expected: Mutex<Option<(Move, Board)>>,
+    // held for as long as a blocking ponder search runs
+    eval: Mutex<Option<Vec<(Move, Score)>>>,
+    // replaced after each of the player's moves, so stopping one ponder never
+    // carries over to the next
+    stop: Mutex<Arc<AtomicBool>>,
+}
 
 #[derive(Clone)]
 pub struct ComputerPlayer {
-    algorithm: Rc<dyn Evaluator>,
-    score_fn: Rc<dyn ScoreFunction>,
-    chooser: Rc<dyn Chooser>,
+    algorithm: Arc<dyn Evaluator>,
+    score_fn: Arc<dyn ScoreFunction>,
+    chooser: Arc<dyn Chooser>,
+    book: Option<Arc<OpeningBook>>,
+    tablebase: Option<Arc<dyn Tablebase>>,
+    seed: u64,
+    // shared between clones, so a copy taken to pick a move still advances
+    // the same sequence
+    rng: Arc<Mutex<ChaCha8Rng>>,
+    // also shared between clones, so any copy can stop a search in progress.
+    // each search gets a new flag, so a stopped search stays stopped
+    stop: Arc<Mutex<Arc<AtomicBool>>>,
+    on_progress: Option<ProgressCallback>,
+    ponder: Arc<Ponder>,
+}
+
+// the first of the highest scoring moves, as a greedy player would pick
+fn best_move(eval: &[(Move, Score)]) -> Option<Move> {
+    eval.iter()
+        .fold(None, |best: Option<&(Move, Score)>, eval| match best {
+            Some(best) if best.1 >= eval.1 => Some(best),
+            _ => Some(eval),
+        })
+        .map(|(mov, _)| *mov)
 }
 
 impl ComputerPlayer {
     pub fn new(
-        algorithm: Rc<dyn Evaluator>,
-        score_fn: Rc<dyn ScoreFunction>,
-        chooser: Rc<dyn Chooser>,
+        algorithm: Arc<dyn Evaluator>,
+        score_fn: Arc<dyn ScoreFunction>,
+        chooser: Arc<dyn Chooser>,
     ) -> Self {
         Self {
             algorithm,
             score_fn,
             chooser,
+            book: None,
+            tablebase: None,
+            seed: 0,
+            rng: Arc::new(Mutex::new(ChaCha8Rng::seed_from_u64(0))),
+            stop: Arc::default(),
+            on_progress: None,
+            ponder: Arc::default(),
         }
+        .with_seed(rand::random())
     }
 
-    pub fn get_move(&self, board: Board) -> Option<Move> {
-        let eval = self.algorithm.eval_moves(board, self.score_fn.clone());
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.reseed(seed);
+        self
+    }
+
+    pub fn with_progress(mut self, on_progress: ProgressCallback) -> Self {
+        self.on_progress = Some(on_progress);
+        self
+    }
+
+    /// Stops the current search and any pondering. `get_move` then plays the
+    /// best move from the deepest search it finished, while `get_move_async`
+    /// gives up without a move.
+    pub fn stop(&self) {
+        self.stop
+            .lock()
+            .expect("stop lock should not be poisoned")
+            .store(true, Ordering::Relaxed);
+        self.forget_ponder();
+    }
+
+    // a fresh stop flag for a search that is about to start
+    fn start_search(&self) -> Arc<AtomicBool> {
+        let stop = Arc::new(AtomicBool::new(false));
+        *self.stop.lock().expect("stop lock should not be poisoned") = stop.clone();
+        stop
+    }
+
+    pub fn score_fn(&self) -> Arc<dyn ScoreFunction> {
+        self.score_fn.clone()
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Restarts the random choices, so the same seed and the same opponent
+    /// moves replay a game exactly.
+    pub fn reseed(&mut self, seed: u64) {
+        self.seed = seed;
+        *self.rng.lock().expect("rng lock should not be poisoned") =
+            ChaCha8Rng::seed_from_u64(seed);
+    }
+
+    /// Thinks on the opponent's time. `board` is the position after this
+    /// player's move: the search picks the opponent's most likely reply and
+    /// then searches the position after it. The next `get_move` uses the
+    /// result straight away if the opponent played that reply, and throws it
+    /// away otherwise.
+    ///
+    /// This blocks until both searches finish, so call it on a clone from
+    /// another thread. `get_move` waits for it on a ponder hit and stops it on
+    /// a miss.
+    pub fn ponder(&self, board: Board) {
+        let stop = self.ponder_stop();
+        let mut eval = self
+            .ponder
+            .eval
+            .lock()
+            .expect("ponder lock should not be poisoned");
+
+        // a move or a new game may have come in before the lock was taken
+        if stop.load(Ordering::Relaxed) {
+            return;
+        }
+        *eval = None;
+
+        let context = SearchContext::new().with_stop(stop.clone());
+
+        let replies = self
+            .algorithm
+            .search(board.clone(), self.score_fn.clone(), &context);
+        let Some(expected) = self.expect_reply(board, &replies, &stop) else {
+            return;
+        };
+
+        let result = self
+            .algorithm
+            .search(expected, self.score_fn.clone(), &context);
+        if !context.is_stopped() {
+            *eval = Some(result);
+        }
+    }
+
+    /// `ponder` for a single thread, such as a web page's. Deepening searches
+    /// run one depth at a time with `pause` awaited in between, so whatever
+    /// else shares the thread gets a turn. A move that comes in while this is
+    /// still running stops it rather than waiting for it.
+    pub async fn ponder_async<F: Future<Output = ()>>(
+        &self,
+        board: Board,
+        mut pause: impl FnMut() -> F,
+    ) {
+        let stop = self.ponder_stop();
+        if stop.load(Ordering::Relaxed) {
+            return;
+        }
+        *self
+            .ponder
+            .eval
+            .lock()
+            .expect("ponder lock should not be poisoned") = None;
+
+        let replies = self.search_by_depth(&board, &stop, None, &mut pause).await;
+        let Some(expected) = self.expect_reply(board, &replies, &stop) else {
+            return;
+        };
+
+        pause().await;
+        let result = self
+            .search_by_depth(&expected, &stop, None, &mut pause)
+            .await;
+
+        if !stop.load(Ordering::Relaxed) {
+            *self
+                .ponder
+                .eval
+                .lock()
+                .expect("ponder lock should not be poisoned") = Some(result);
+        }
+    }
+
+    fn ponder_stop(&self) -> Arc<AtomicBool> {
+        self.ponder
+            .stop
+            .lock()
+            .expect("ponder lock should not be poisoned")
+            .clone()
+    }
+
+    // records the opponent's most likely reply, returning the position after
+    // it if there is anything there worth pondering
+    fn expect_reply(
+        &self,
+        board: Board,
+        replies: &[(Move, Score)],
+        stop: &AtomicBool,
+    ) -> Option<Board> {
+        let reply = best_move(replies)?;
+
+        let mut expected = board;
+        expected.play(reply);
+
+        // the book and tablebase answer straight away anyway
+        let in_book = self
+            .book
+            .as_ref()
+            .is_some_and(|book| !book.moves(&expected).is_empty());
+        let in_tablebase = self
+            .tablebase
+            .as_ref()
+            .is_some_and(|tablebase| tablebase.best_move(&expected).is_some());
+
+        if stop.load(Ordering::Relaxed) || in_book || in_tablebase {
+            return None;
+        }
+
+        *self
+            .ponder
+            .expected
+            .lock()
+            .expect("ponder lock should not be poisoned") = Some((reply, expected.clone()));
+
+        Some(expected)
+    }
+
+    /// The reply the player is pondering on, if any.
+    pub fn ponder_move(&self) -> Option<Move> {
+        self.ponder
+            .expected
+            .lock()
+            .expect("ponder lock should not be poisoned")
+            .as_ref()
+            .map(|(reply, _)| *reply)
+    }
 
+    // the pondered scores if `board` is the position that was pondered on
+    fn take_ponder(&self, board: &Board) -> Option<Vec<(Move, Score)>> {
+        let expected = self
+            .ponder
+            .expected
+            .lock()
+            .expect("ponder lock should not be poisoned")
+            .take();
+        let hit = matches!(&expected, Some((_, expected)) if expected == board);
+
+        let stop = self.ponder_stop();
+        if !hit {
+            stop.store(true, Ordering::Relaxed);
+        }
+
+        let eval = self
+            .ponder
+            .eval
+            .lock()
+            .expect("ponder lock should not be poisoned")
+            .take();
+
+        // a hit on a ponder that hasn't finished is searched again
+        if eval.is_none() {
+            stop.store(true, Ordering::Relaxed);
+        }
+
+        eval.filter(|_| hit)
+    }
+
+    // stops any pondering, and makes sure its result is never used
+    fn forget_ponder(&self) {
+        self.ponder_stop().store(true, Ordering::Relaxed);
+        *self
+            .ponder
+            .expected
+            .lock()
+            .expect("ponder lock should not be poisoned") = None;
+    }
+
+    // pondering is only worth starting once the player has moved
+    fn allow_ponder(&self) {
+        *self
+            .ponder
+            .stop
+            .lock()
+            .expect("ponder lock should not be poisoned") = Arc::default();
+    }
+
+    // a move from the book or the tablebase, which needs no search
+    fn instant_move(&self, board: &Board) -> Option<Move> {
+        let mut rng = self.rng.lock().expect("rng lock should not be poisoned");
+
+        self.book
+            .as_ref()
+            .and_then(|book| book.choose_move(board, &mut *rng))
+            .or_else(|| {
+                self.tablebase
+                    .as_ref()
+                    .and_then(|tablebase| tablebase.best_move(board))
+            })
+    }
+
+    fn choose(&self, eval: Vec<(Move, Score)>) -> Option<Move> {
         let mut moves = Vec::with_capacity(eval.len());
         let mut weights = Vec::with_capacity(eval.len());
 
@@ -39,20 +340,494 @@ impl ComputerPlayer {
             weights.push(score);
         }
 
-        let choice = self.chooser.choose(&moves, &weights);
+        let mut rng = self.rng.lock().expect("rng lock should not be poisoned");
+        let choice = self.chooser.choose(&moves, &weights, &mut *rng);
 
-        choice.map(|a| a.clone())
+        choice.copied()
     }
 
-    pub fn change_algorithm(&mut self, algorithm: Rc<dyn Evaluator>) {
+    pub fn get_move(&self, board: Board) -> Option<Move> {
+        let stop = self.start_search();
+        let pondered = self.take_ponder(&board);
+
+        let mov = match self.instant_move(&board) {
+            Some(mov) => Some(mov),
+            None => {
+                let eval = pondered.unwrap_or_else(|| {
+                    let mut context = SearchContext::new().with_stop(stop);
+                    if let Some(on_progress) = &self.on_progress {
+                        context = context.with_progress(on_progress.clone());
+                    }
+
+                    self.algorithm
+                        .search(board, self.score_fn.clone(), &context)
+                });
+
+                self.choose(eval)
+            }
+        };
+
+        self.allow_ponder();
+        mov
+    }
+
+    /// `get_move` for a single thread, such as a web page's. Deepening
+    /// searches run one depth at a time with `pause` awaited in between, so
+    /// progress can be shown and `stop` can be called while the search runs.
+    /// A stopped search gives no move.
+    pub async fn get_move_async<F: Future<Output = ()>>(
+        &self,
+        board: Board,
+        mut pause: impl FnMut() -> F,
+    ) -> Option<Move> {
+        let stop = self.start_search();
+        let pondered = self.take_ponder(&board);
+
+        let mov = match self.instant_move(&board) {
+            Some(mov) => Some(mov),
+            None => {
+                let eval = match pondered {
+                    Some(eval) => eval,
+                    None => {
+                        self.search_by_depth(&board, &stop, self.on_progress.clone(), &mut pause)
+                            .await
+                    }
+                };
+
+                match stop.load(Ordering::Relaxed) {
+                    true => return None,
+                    false => self.choose(eval),
+                }
+            }
+        };
+
+        self.allow_ponder();
+        mov
+    }
+
+    // runs the search one depth at a time, awaiting `pause` between depths.
+    // each depth searches again from the first, so the result is the same as
+    // one search to the deepest depth that finished
+    async fn search_by_depth<F: Future<Output = ()>>(
+        &self,
+        board: &Board,
+        stop: &Arc<AtomicBool>,
+        on_progress: Option<ProgressCallback>,
+        pause: &mut impl FnMut() -> F,
+    ) -> Vec<(Move, Score)> {
+        let mut context = SearchContext::new().with_stop(stop.clone());
+
+        if stop.load(Ordering::Relaxed) {
+            return Vec::new();
+        }
+
+        let Some(depth) = self.algorithm.depth() else {
+            if let Some(on_progress) = on_progress {
+                context = context.with_progress(on_progress);
+            }
+            return self
+                .algorithm
+                .search(board.clone(), self.score_fn.clone(), &context);
+        };
+
+        let mut best = Vec::new();
+
+        for limit in 1..=depth {
+            if limit > 1 {
+                pause().await;
+                if stop.load(Ordering::Relaxed) {
+                    break;
+                }
+            }
+
+            // earlier depths were already reported
+            let reached = Arc::new(AtomicUsize::new(0));
+            let on_progress = on_progress.clone();
+            let report = reached.clone();
+            let context = context
+                .clone()
+                .with_depth_limit(Some(limit))
+                .with_progress(Arc::new(move |progress| {
+                    report.store(progress.depth, Ordering::Relaxed);
+                    if let (true, Some(on_progress)) = (progress.depth == limit, &on_progress) {
+                        on_progress(progress);
+                    }
+                }));
+
+            best = self
+                .algorithm
+                .search(board.clone(), self.score_fn.clone(), &context);
+
+            // out of nodes, so deeper searches would stop at the same depth
+            if stop.load(Ordering::Relaxed) || reached.load(Ordering::Relaxed) < limit {
+                break;
+            }
+        }
+
+        best
+    }
+
+    pub fn change_algorithm(&mut self, algorithm: Arc<dyn Evaluator>) {
+        self.forget_ponder();
         self.algorithm = algorithm;
     }
 
-    pub fn change_score_fn(&mut self, score_fn: Rc<dyn ScoreFunction>) {
+    pub fn change_score_fn(&mut self, score_fn: Arc<dyn ScoreFunction>) {
+        self.forget_ponder();
         self.score_fn = score_fn;
     }
 
-    pub fn change_chooser(&mut self, chooser: Rc<dyn Chooser>) {
+    pub fn change_chooser(&mut self, chooser: Arc<dyn Chooser>) {
         self.chooser = chooser;
     }
+
+    // a pondered reply may have been picked knowing what the old book or
+    // tables would answer
+    pub fn change_book(&mut self, book: Option<Arc<OpeningBook>>) {
+        self.forget_ponder();
+        self.book = book;
+    }
+
+    pub fn change_tablebase(&mut self, tablebase: Option<Arc<dyn Tablebase>>) {
+        self.forget_ponder();
+        self.tablebase = tablebase;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use choose::StochasticChooser;
+    use eval::NaiveEvaluator;
+    use score::PawnDifferenceScore;
+    use std::task::{Context, Poll, Waker};
+
+    fn play_moves(player: &ComputerPlayer, plies: usize) -> Vec<Move> {
+        let mut board = Board::startpos();
+        let mut moves = Vec::new();
+
+        for _ in 0..plies {
+            let mov = player.get_move(board.clone()).unwrap();
+            board.play(mov);
+            moves.push(mov);
+        }
+
+        moves
+    }
+
+    fn seeded_player(seed: u64) -> ComputerPlayer {
+        let mut player = ComputerPlayer::new(
+            Arc::new(NaiveEvaluator),
+            Arc::new(PawnDifferenceScore::default()),
+            Arc::new(StochasticChooser),
+        )
+        .with_seed(seed);
+        player.change_book(Some(Arc::new(OpeningBook::embedded())));
+        player
+    }
+
+    #[test]
+    fn test_seeded_player_repeats_games() {
+        let player = seeded_player(42);
+        let first = play_moves(&player, 12);
+
+        assert_eq!(player.seed(), 42);
+        assert_eq!(play_moves(&seeded_player(42), 12), first);
+        assert_ne!(play_moves(&seeded_player(7), 12), first);
+
+        // clones share the rng, so reseeding any of them restarts the game
+        let mut copy = player.clone();
+        copy.reseed(42);
+        assert_eq!(play_moves(&player, 12), first);
+    }
+
+    #[test]
+    fn test_tablebase_plays_pawn_endings() {
+        let tablebase = Arc::new(tablebase::SyzygyTablebase::bundled());
+        let mut player = ComputerPlayer::new(
+            Arc::new(eval::AlphaBetaNegamax::new(2)),
+            Arc::new(PawnDifferenceScore::default()),
+            Arc::new(choose::GreedyChooser),
+        );
+        player.change_tablebase(Some(tablebase.clone()));
+
+        // a shallow search can't see the pawn through, but the tables can
+        let mut board = Board::from_fen("8/8/8/8/8/k7/4P3/7K w - - 0 1", false).unwrap();
+        let mov = player.get_move(board.clone()).unwrap();
+        board.play(mov);
+
+        let probe = tablebase.probe(&board).unwrap();
+        assert_eq!(probe.wdl, tablebase::Wdl::Loss);
+    }
+
+    // counts the positions scored, to tell whether a search ran
+    #[derive(Default)]
+    struct CountingScore {
+        count: std::sync::atomic::AtomicUsize,
+    }
+
+    impl CountingScore {
+        fn count(&self) -> usize {
+            self.count.load(std::sync::atomic::Ordering::Relaxed)
+        }
+    }
+
+    impl ScoreFunction for CountingScore {
+        fn score(&self, board: &Board) -> Score {
+            self.count
+                .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            PawnDifferenceScore::default().score(board)
+        }
+    }
+
+    fn pondering_player() -> (ComputerPlayer, Arc<CountingScore>) {
+        let counter = Arc::new(CountingScore::default());
+        let player = ComputerPlayer::new(
+            Arc::new(eval::AlphaBetaNegamax::new(2)),
+            counter.clone(),
+            Arc::new(choose::GreedyChooser),
+        );
+        (player, counter)
+    }
+
+    #[test]
+    fn test_ponder_hit_skips_search() {
+        let (player, counter) = pondering_player();
+        let (fresh, _) = pondering_player();
+
+        let mut board = Board::startpos();
+        board.play(player.get_move(board.clone()).unwrap());
+
+        player.ponder(board.clone());
+        let reply = player.ponder_move().unwrap();
+        board.play(reply);
+
+        let count = counter.count();
+        let mov = player.get_move(board.clone());
+
+        assert_eq!(counter.count(), count);
+        assert_eq!(mov, fresh.get_move(board));
+        assert_eq!(player.ponder_move(), None);
+    }
+
+    #[test]
+    fn test_changing_book_or_tablebase_forgets_ponder() {
+        let change_book = |player: &mut ComputerPlayer| player.change_book(None);
+        let change_tablebase = |player: &mut ComputerPlayer| player.change_tablebase(None);
+
+        for change in [
+            &change_book as &dyn Fn(&mut ComputerPlayer),
+            &change_tablebase,
+        ] {
+            let (mut player, counter) = pondering_player();
+            let mut board = Board::startpos();
+            board.play(player.get_move(board.clone()).unwrap());
+
+            player.ponder(board.clone());
+            let reply = player.ponder_move().unwrap();
+            change(&mut player);
+            assert_eq!(player.ponder_move(), None);
+
+            // the pondered search isn't used, even on the expected reply
+            board.play(reply);
+            let count = counter.count();
+            player.get_move(board);
+            assert!(counter.count() > count);
+        }
+    }
+
+    #[test]
+    fn test_ponder_miss_searches_again() {
+        let (player, counter) = pondering_player();
+        let (fresh, _) = pondering_player();
+
+        let mut board = Board::startpos();
+        board.play(player.get_move(board.clone()).unwrap());
+
+        player.ponder(board.clone());
+        let reply = player.ponder_move().unwrap();
+        let other = perft::legal_moves(&board)
+            .into_iter()
+            .find(|mov| *mov != reply)
+            .unwrap();
+        board.play(other);
+
+        let count = counter.count();
+        let mov = player.get_move(board.clone());
+
+        assert!(counter.count() > count);
+        assert_eq!(mov, fresh.get_move(board));
+    }
+
+    // polls `future` until it finishes, for futures that are always ready to
+    // go on after a pause
+    pub(super) fn block_on<F: Future>(future: F) -> F::Output {
+        let mut future = std::pin::pin!(future);
+        let mut cx = Context::from_waker(Waker::noop());
+
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                return output;
+            }
+        }
+    }
+
+    // gives way once, like waiting for the next turn of an event loop
+    fn yield_now() -> impl Future<Output = ()> {
+        let mut yielded = false;
+        std::future::poll_fn(move |_| match yielded {
+            true => Poll::Ready(()),
+            false => {
+                yielded = true;
+                Poll::Pending
+            }
+        })
+    }
+
+    fn reporting_player(depth: usize) -> (ComputerPlayer, Arc<Mutex<Vec<usize>>>) {
+        let depths = Arc::new(Mutex::new(Vec::new()));
+        let reported = depths.clone();
+        let player = ComputerPlayer::new(
+            Arc::new(eval::PrincipalVariationSearch::new(depth)),
+            Arc::new(PawnDifferenceScore::default()),
+            Arc::new(choose::GreedyChooser),
+        )
+        .with_progress(Arc::new(move |progress| {
+            reported.lock().unwrap().push(progress.depth)
+        }));
+        (player, depths)
+    }
+
+    #[test]
+    fn test_get_move_async_matches_get_move() {
+        let (player, depths) = reporting_player(3);
+
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - 0 1",
+        ] {
+            let board = Board::from_fen(fen, false).unwrap();
+            depths.lock().unwrap().clear();
+
+            let mov = block_on(player.get_move_async(board.clone(), yield_now));
+
+            // each depth is reported once, as it finishes
+            assert_eq!(*depths.lock().unwrap(), vec![1, 2, 3]);
+            assert_eq!(mov, player.get_move(board), "{}", fen);
+        }
+    }
+
+    #[test]
+    fn test_stop_between_depths() {
+        let (player, depths) = reporting_player(4);
+
+        // the pause is where a click on new game gets handled
+        let copy = player.clone();
+        let mov = block_on(player.get_move_async(Board::startpos(), || {
+            copy.stop();
+            yield_now()
+        }));
+
+        assert_eq!(mov, None);
+        assert_eq!(*depths.lock().unwrap(), vec![1]);
+    }
+
+    #[test]
+    fn test_stopped_search_stays_stopped() {
+        let (player, depths) = reporting_player(3);
+        let mut cx = Context::from_waker(Waker::noop());
+
+        // the first search waits after depth 1, then a new search starts
+        let mut stale = std::pin::pin!(player.get_move_async(Board::startpos(), yield_now));
+        assert!(stale.as_mut().poll(&mut cx).is_pending());
+        player.stop();
+        assert!(player.get_move(Board::startpos()).is_some());
+
+        depths.lock().unwrap().clear();
+        assert_eq!(block_on(stale), None);
+        assert!(depths.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_ponder_async_hit_skips_search() {
+        let (player, counter) = pondering_player();
+        let (fresh, _) = pondering_player();
+
+        let mut board = Board::startpos();
+        board.play(player.get_move(board.clone()).unwrap());
+
+        block_on(player.ponder_async(board.clone(), yield_now));
+        board.play(player.ponder_move().unwrap());
+
+        let count = counter.count();
+        let mov = block_on(player.get_move_async(board.clone(), yield_now));
+
+        assert_eq!(counter.count(), count);
+        assert_eq!(mov, fresh.get_move(board));
+    }
+
+    #[test]
+    fn test_unfinished_ponder_is_stopped() {
+        let (player, counter) = pondering_player();
+        let (fresh, _) = pondering_player();
+        let mut cx = Context::from_waker(Waker::noop());
+
+        let mut board = Board::startpos();
+        board.play(player.get_move(board.clone()).unwrap());
+
+        // the ponder is part way through when the expected reply comes in
+        let mut ponder = std::pin::pin!(player.ponder_async(board.clone(), yield_now));
+        while player.ponder_move().is_none() {
+            assert!(ponder.as_mut().poll(&mut cx).is_pending());
+        }
+        board.play(player.ponder_move().unwrap());
+
+        let count = counter.count();
+        let mov = player.get_move(board.clone());
+        assert!(counter.count() > count);
+        assert_eq!(mov, fresh.get_move(board));
+
+        // and gives up without searching any further
+        let count = counter.count();
+        block_on(ponder);
+        assert_eq!(counter.count(), count);
+    }
+
+    #[test]
+    fn test_ponder_after_stop_does_nothing() {
+        let (player, counter) = pondering_player();
+
+        let mut board = Board::startpos();
+        board.play(player.get_move(board.clone()).unwrap());
+
+        // a new game came in before the ponder got going
+        player.stop();
+        let count = counter.count();
+        player.ponder(board.clone());
+        block_on(player.ponder_async(board, yield_now));
+
+        assert_eq!(counter.count(), count);
+        assert_eq!(player.ponder_move(), None);
+    }
+
+    #[test]
+    fn test_ponder_in_background() {
+        let (player, _) = pondering_player();
+        let (fresh, _) = pondering_player();
+
+        let mut board = Board::startpos();
+        board.play("e2e4".parse().unwrap());
+
+        let copy = player.clone();
+        let pondered = board.clone();
+        let handle = std::thread::spawn(move || copy.ponder(pondered));
+
+        // whether or not the ponder search got there first, the move is the
+        // same as a fresh search's
+        let reply = fresh.get_move(board.clone()).unwrap();
+        board.play(reply);
+        assert_eq!(player.get_move(board.clone()), fresh.get_move(board));
+
+        handle.join().unwrap();
+    }
 }
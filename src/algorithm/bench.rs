@@ -0,0 +1,282 @@
+use super::{
+    choose::{Chooser, GreedyChooser},
+    eval::{Evaluator, Progress, SearchContext},
+    score::ScoreFunction,
+};
+use cozy_chess::{Board, Move, Piece};
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+// tactical positions from Win At Chess
+pub const WIN_AT_CHESS: &str = include_str!("bench/wac.epd");
+
+/// Test position with the moves that solve it (`bm`) and the moves that
+/// fail it (`am`), written in SAN.
+#[derive(Clone, Debug)]
+pub struct EpdPosition {
+    pub id: String,
+    pub board: Board,
+    pub best_moves: Vec<Move>,
+    pub avoid_moves: Vec<Move>,
+}
+
+impl EpdPosition {
+    pub fn is_solved_by(&self, mov: &Move) -> bool {
+        (self.best_moves.is_empty() || self.best_moves.contains(mov))
+            && !self.avoid_moves.contains(mov)
+    }
+}
+
+/// Parses an EPD line such as `<fen fields> bm Qg6; id "WAC.001";`. Lines
+/// without a `bm` or `am` operation, or naming a move that isn't legal, are
+/// rejected.
+pub fn parse_epd(line: &str) -> Option<EpdPosition> {
+    let fields = line.split_whitespace().collect::<Vec<_>>();
+    if fields.len() < 4 {
+        return None;
+    }
+
+    let board = Board::from_fen(&format!("{} 0 1", fields[..4].join(" ")), false).ok()?;
+    let operations = fields[4..].join(" ");
+
+    let mut id = String::new();
+    let mut best_moves = vec![];
+    let mut avoid_moves = vec![];
+
+    for operation in operations.split(';').map(str::trim) {
+        let Some((opcode, operands)) = operation.split_once(' ') else {
+            continue;
+        };
+
+        let moves = || {
+            operands
+                .split_whitespace()
+                .map(|san| parse_san(&board, san))
+                .collect::<Option<Vec<_>>>()
+        };
+
+        match opcode {
+            "id" => id = operands.trim_matches('"').to_string(),
+            "bm" => best_moves = moves()?,
+            "am" => avoid_moves = moves()?,
+            _ => {}
+        }
+    }
+
+    match best_moves.is_empty() && avoid_moves.is_empty() {
+        true => None,
+        false => Some(EpdPosition {
+            id,
+            board,
+            best_moves,
+            avoid_moves,
+        }),
+    }
+}
+
+/// Finds the legal move written as `san`, ignoring check and annotation
+/// marks.
+pub fn parse_san(board: &Board, san: &str) -> Option<Move> {
+    let san = san.trim_end_matches(['+', '#', '!', '?']);
+
+    let mut found = None;
+    board.generate_moves(|piece_moves| {
+        found = piece_moves
+            .into_iter()
+            .find(|mov| to_san(board, mov) == san);
+        found.is_some()
+    });
+
+    found
+}
+
+/// Standard algebraic notation for a legal move, without check marks.
+pub fn to_san(board: &Board, mov: &Move) -> String {
+    let side = board.side_to_move();
+    let piece = board.piece_on(mov.from).expect("should be piece here");
+
+    // castling is stored as the king taking its own rook
+    if piece == Piece::King && board.color_on(mov.to) == Some(side) {
+        return match mov.to.file() > mov.from.file() {
+            true => "O-O".to_string(),
+            false => "O-O-O".to_string(),
+        };
+    }
+
+    let capture = board.color_on(mov.to) == Some(!side)
+        || (piece == Piece::Pawn && mov.from.file() != mov.to.file());
+
+    let mut san = match piece {
+        Piece::Pawn => match capture {
+            true => mov.from.file().to_string(),
+            false => String::new(),
+        },
+        _ => {
+            let mut san = piece_letter(piece).to_string();
+
+            let mut rivals = vec![];
+            board.generate_moves_for(board.colored_pieces(side, piece), |piece_moves| {
+                rivals.extend(
+                    piece_moves
+                        .into_iter()
+                        .filter(|other| other.to == mov.to && other.from != mov.from),
+                );
+                false
+            });
+
+            if !rivals.is_empty() {
+                let same_file = rivals
+                    .iter()
+                    .any(|other| other.from.file() == mov.from.file());
+                let same_rank = rivals
+                    .iter()
+                    .any(|other| other.from.rank() == mov.from.rank());
+
+                if !same_file {
+                    san += &mov.from.file().to_string();
+                } else if !same_rank {
+                    san += &mov.from.rank().to_string();
+                } else {
+                    san += &mov.from.to_string();
+                }
+            }
+
+            san
+        }
+    };
+
+    if capture {
+        san.push('x');
+    }
+    san += &mov.to.to_string();
+
+    if let Some(promotion) = mov.promotion {
+        san.push('=');
+        san.push(piece_letter(promotion));
+    }
+
+    san
+}
+
+fn piece_letter(piece: Piece) -> char {
+    match piece {
+        Piece::Pawn => 'P',
+        Piece::Knight => 'N',
+        Piece::Bishop => 'B',
+        Piece::Rook => 'R',
+        Piece::Queen => 'Q',
+        Piece::King => 'K',
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct BenchResult {
+    pub chosen: Option<Move>,
+    pub solved: bool,
+    // nodes the search reported visiting by the end of its last depth
+    pub nodes: usize,
+}
+
+/// Searches a test position and checks the move a greedy player would pick.
+pub fn run_position(
+    evaluator: &dyn Evaluator,
+    score_fn: Arc<dyn ScoreFunction>,
+    position: &EpdPosition,
+) -> BenchResult {
+    let nodes = Arc::new(AtomicUsize::new(0));
+    let reported = nodes.clone();
+    let context = SearchContext::new().with_progress(Arc::new(move |progress: &Progress| {
+        reported.store(progress.nodes, Ordering::Relaxed);
+    }));
+    let evals = evaluator.search(position.board.clone(), score_fn, &context);
+
+    let (moves, scores): (Vec<_>, Vec<_>) = evals.into_iter().unzip();
+    let chosen = GreedyChooser
+        .choose(&moves, &scores, &mut ChaCha8Rng::seed_from_u64(0))
+        .copied();
+
+    BenchResult {
+        chosen,
+        solved: chosen.is_some_and(|mov| position.is_solved_by(&mov)),
+        nodes: nodes.load(Ordering::Relaxed),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::algorithm::{eval::PrincipalVariationSearch, score::PawnDifferenceScore};
+
+    #[test]
+    fn test_to_san() {
+        let board = Board::from_fen(
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            false,
+        )
+        .unwrap();
+
+        for (uci, san) in [
+            ("e1h1", "O-O"),
+            ("e1a1", "O-O-O"),
+            ("d5e6", "dxe6"),
+            ("e5f7", "Nxf7"),
+            ("f3h3", "Qxh3"),
+            ("g2h3", "gxh3"),
+            ("c3b1", "Nb1"),
+            ("e2a6", "Bxa6"),
+            ("a2a4", "a4"),
+        ] {
+            let mov = uci.parse().unwrap();
+            assert_eq!(to_san(&board, &mov), san);
+            assert_eq!(parse_san(&board, san), Some(mov));
+        }
+
+        // both rooks and both knights can reach the same square
+        let board = Board::from_fen("4k3/8/8/1N3N2/8/7K/8/R6R w - - 0 1", false).unwrap();
+        assert_eq!(to_san(&board, &"a1d1".parse().unwrap()), "Rad1");
+        assert_eq!(to_san(&board, &"b5d4".parse().unwrap()), "Nbd4");
+
+        // promotions, with and without a capture
+        let board = Board::from_fen("1r2k3/2P5/8/8/8/8/8/4K3 w - - 0 1", false).unwrap();
+        assert_eq!(to_san(&board, &"c7c8q".parse().unwrap()), "c8=Q");
+        assert_eq!(parse_san(&board, "cxb8=N+"), Some("c7b8n".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_parse_epd() {
+        let position =
+            parse_epd("6k1/5ppp/8/8/8/8/5PPP/3R2K1 w - - bm Rd8#; id \"back rank\";").unwrap();
+
+        assert_eq!(position.id, "back rank");
+        assert_eq!(position.best_moves, vec!["d1d8".parse().unwrap()]);
+        assert!(position.is_solved_by(&"d1d8".parse().unwrap()));
+        assert!(!position.is_solved_by(&"d1d7".parse().unwrap()));
+
+        // illegal best moves and missing operations are rejected
+        assert!(parse_epd("6k1/5ppp/8/8/8/8/5PPP/3R2K1 w - - bm Rd9;").is_none());
+        assert!(parse_epd("6k1/5ppp/8/8/8/8/5PPP/3R2K1 w - - id \"none\";").is_none());
+    }
+
+    #[test]
+    fn test_bundled_suite_parses() {
+        let lines = WIN_AT_CHESS.lines().filter(|line| !line.trim().is_empty());
+        assert!(lines.clone().all(|line| parse_epd(line).is_some()));
+    }
+
+    #[test]
+    fn test_run_position() {
+        let position = parse_epd("6k1/5ppp/8/8/8/8/5PPP/3R2K1 w - - bm Rd8#;").unwrap();
+        let result = run_position(
+            &PrincipalVariationSearch::new(3),
+            Arc::new(PawnDifferenceScore::default()),
+            &position,
+        );
+
+        assert!(result.solved);
+        assert!(result.nodes > 0);
+    }
+}
@@ -0,0 +1,397 @@
+mod keys;
+
+use cozy_chess::{BitBoard, Board, Color, File, Move, Piece, Rank, Square};
+use keys::RANDOM64;
+use rand::{distributions::WeightedIndex, prelude::Distribution, RngCore};
+use std::fmt;
+
+const ENTRY_SIZE: usize = 16;
+const CASTLING_OFFSET: usize = 768;
+const EN_PASSANT_OFFSET: usize = 772;
+const TURN_OFFSET: usize = 780;
+
+// small book of mainline openings shipped with the app. the lines are picked
+// by hand from well known theory rather than taken from another book, and
+// `default_book_bytes` in the tests writes the file out from them
+const DEFAULT_BOOK: &[u8] = include_bytes!("book/default.bin");
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BookError {
+    // polyglot files are a flat list of 16 byte entries
+    InvalidLength(usize),
+}
+
+impl fmt::Display for BookError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BookError::InvalidLength(len) => write!(
+                f,
+                "book is {} bytes long, which is not a multiple of {}",
+                len, ENTRY_SIZE
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BookEntry {
+    pub key: u64,
+    pub mov: u16,
+    pub weight: u16,
+}
+
+impl BookEntry {
+    fn from_bytes(bytes: &[u8]) -> Self {
+        Self {
+            key: u64::from_be_bytes(bytes[0..8].try_into().unwrap()),
+            mov: u16::from_be_bytes(bytes[8..10].try_into().unwrap()),
+            weight: u16::from_be_bytes(bytes[10..12].try_into().unwrap()),
+        }
+    }
+
+    // move layout: to square in bits 0-5, from square in bits 6-11 and
+    // promotion piece in bits 12-14. castling is stored as king takes rook,
+    // which is also how cozy_chess represents it.
+    pub fn to_move(self) -> Move {
+        let to = Square::index((self.mov & 0x3f) as usize);
+        let from = Square::index(((self.mov >> 6) & 0x3f) as usize);
+        let promotion = match (self.mov >> 12) & 0x7 {
+            1 => Some(Piece::Knight),
+            2 => Some(Piece::Bishop),
+            3 => Some(Piece::Rook),
+            4 => Some(Piece::Queen),
+            _ => None,
+        };
+
+        Move {
+            from,
+            to,
+            promotion,
+        }
+    }
+}
+
+/// Opening book in the Polyglot `.bin` format.
+#[derive(Debug, Clone, Default)]
+pub struct OpeningBook {
+    // sorted by key, as in the file
+    entries: Vec<BookEntry>,
+}
+
+impl OpeningBook {
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, BookError> {
+        let chunks = bytes.chunks_exact(ENTRY_SIZE);
+
+        if !chunks.remainder().is_empty() {
+            return Err(BookError::InvalidLength(bytes.len()));
+        }
+
+        let mut entries = chunks.map(BookEntry::from_bytes).collect::<Vec<_>>();
+
+        // the format requires sorted keys, but don't rely on every writer
+        entries.sort_by_key(|entry| entry.key);
+
+        Ok(Self { entries })
+    }
+
+    pub fn embedded() -> Self {
+        Self::from_bytes(DEFAULT_BOOK).expect("embedded book should be valid")
+    }
+
+    /// Legal book moves for this position along with their weights.
+    pub fn moves(&self, board: &Board) -> Vec<(Move, u16)> {
+        let key = polyglot_key(board);
+        let start = self.entries.partition_point(|entry| entry.key < key);
+
+        self.entries[start..]
+            .iter()
+            .take_while(|entry| entry.key == key)
+            .map(|entry| (entry.to_move(), entry.weight))
+            .filter(|(mov, weight)| *weight > 0 && board.is_legal(*mov))
+            .collect()
+    }
+
+    /// Picks a book move at random, in proportion to the entry weights.
+    pub fn choose_move(&self, board: &Board, rng: &mut dyn RngCore) -> Option<Move> {
+        let moves = self.moves(board);
+
+        let dist = WeightedIndex::new(moves.iter().map(|(_, weight)| *weight)).ok()?;
+
+        moves.get(dist.sample(rng)).map(|(mov, _)| *mov)
+    }
+}
+
+pub fn polyglot_key(board: &Board) -> u64 {
+    let mut key = 0;
+
+    for square in board.occupied() {
+        let piece = board.piece_on(square).expect("should be piece here");
+        let color = board.color_on(square).expect("should be color here");
+
+        // black pawn, white pawn, black knight, white knight, ...
+        let kind = 2 * piece as usize
+            + match color {
+                Color::White => 1,
+                Color::Black => 0,
+            };
+
+        key ^= RANDOM64[64 * kind + square as usize];
+    }
+
+    for (i, color) in [Color::White, Color::Black].into_iter().enumerate() {
+        let rights = board.castle_rights(color);
+
+        if rights.short.is_some() {
+            key ^= RANDOM64[CASTLING_OFFSET + 2 * i];
+        }
+
+        if rights.long.is_some() {
+            key ^= RANDOM64[CASTLING_OFFSET + 2 * i + 1];
+        }
+    }
+
+    if let Some(file) = board.en_passant() {
+        if can_capture_en_passant(board, file) {
+            key ^= RANDOM64[EN_PASSANT_OFFSET + file as usize];
+        }
+    }
+
+    if board.side_to_move() == Color::White {
+        key ^= RANDOM64[TURN_OFFSET];
+    }
+
+    key
+}
+
+// polyglot only hashes the en passant file if a pawn could actually take
+fn can_capture_en_passant(board: &Board, file: File) -> bool {
+    let side = board.side_to_move();
+    let rank = match side {
+        Color::White => Rank::Fifth,
+        Color::Black => Rank::Fourth,
+    };
+
+    let neighbours = [file as usize as i32 - 1, file as usize as i32 + 1]
+        .into_iter()
+        .filter(|file| (0..8).contains(file))
+        .map(|file| Square::new(File::index(file as usize), rank).bitboard())
+        .fold(BitBoard::EMPTY, |a, b| a | b);
+
+    !(board.colored_pieces(side, Piece::Pawn) & neighbours).is_empty()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+    use std::collections::BTreeMap;
+
+    fn book_move(mov: Move) -> u16 {
+        let promotion = match mov.promotion {
+            Some(Piece::Knight) => 1,
+            Some(Piece::Bishop) => 2,
+            Some(Piece::Rook) => 3,
+            Some(Piece::Queen) => 4,
+            _ => 0,
+        };
+
+        (promotion << 12) | ((mov.from as u16) << 6) | mov.to as u16
+    }
+
+    // every line adds 10 to the weight of each of its moves, so moves shared
+    // by several lines are played more often
+    fn default_book_bytes() -> Vec<u8> {
+        let lines = [
+            // open games
+            "e2e4 e7e5 g1f3 b8c6 f1b5 a7a6 b5a4 g8f6 e1h1 f8e7",
+            "e2e4 e7e5 g1f3 b8c6 f1b5 g8f6 e1h1 f6e4 d2d4 e4d6",
+            "e2e4 e7e5 g1f3 b8c6 f1c4 f8c5 c2c3 g8f6 d2d4 e5d4",
+            "e2e4 e7e5 g1f3 b8c6 f1c4 g8f6 d2d3 f8e7 e1h1 e8h8",
+            "e2e4 e7e5 g1f3 b8c6 d2d4 e5d4 f3d4 g8f6 d4c6 b7c6",
+            "e2e4 e7e5 g1f3 g8f6 f3e5 d7d6 e5f3 f6e4 d2d4 d6d5",
+            // sicilian
+            "e2e4 c7c5 g1f3 d7d6 d2d4 c5d4 f3d4 g8f6 b1c3 a7a6",
+            "e2e4 c7c5 g1f3 b8c6 d2d4 c5d4 f3d4 g8f6 b1c3 e7e5",
+            "e2e4 c7c5 g1f3 e7e6 d2d4 c5d4 f3d4 b8c6 b1c3 d8c7",
+            "e2e4 c7c5 b1c3 b8c6 g2g3 g7g6 f1g2 f8g7 d2d3 d7d6",
+            // french and caro-kann
+            "e2e4 e7e6 d2d4 d7d5 b1c3 g8f6 c1g5 f8e7 e4e5 f6d7",
+            "e2e4 e7e6 d2d4 d7d5 e4e5 c7c5 c2c3 b8c6 g1f3 d8b6",
+            "e2e4 c7c6 d2d4 d7d5 b1c3 d5e4 c3e4 c8f5 e4g3 f5g6",
+            "e2e4 c7c6 d2d4 d7d5 e4e5 c8f5 g1f3 e7e6 f1e2 c6c5",
+            // queen's gambit and indian defences
+            "d2d4 d7d5 c2c4 e7e6 b1c3 g8f6 c1g5 f8e7 e2e3 e8h8",
+            "d2d4 d7d5 c2c4 c7c6 g1f3 g8f6 b1c3 d5c4 a2a4 c8f5",
+            "d2d4 d7d5 c2c4 d5c4 g1f3 g8f6 e2e3 e7e6 f1c4 c7c5",
+            "d2d4 g8f6 c2c4 e7e6 b1c3 f8b4 e2e3 e8h8 f1d3 d7d5",
+            "d2d4 g8f6 c2c4 g7g6 b1c3 f8g7 e2e4 d7d6 g1f3 e8h8",
+            "d2d4 g8f6 c2c4 e7e6 g1f3 b7b6 g2g3 c8b7 f1g2 f8e7",
+            "d2d4 d7d5 g1f3 g8f6 c1f4 e7e6 e2e3 c7c5 c2c3 b8c6",
+            // flank openings
+            "c2c4 e7e5 b1c3 g8f6 g1f3 b8c6 g2g3 d7d5 c4d5 f6d5",
+            "c2c4 g8f6 b1c3 e7e6 g1f3 d7d5 d2d4 f8e7 c1f4 e8h8",
+            "g1f3 d7d5 g2g3 g8f6 f1g2 e7e6 e1h1 f8e7 d2d3 e8h8",
+        ];
+
+        // sorted by key and then move, as polyglot expects
+        let mut weights = BTreeMap::new();
+        for line in lines {
+            let mut board = Board::startpos();
+            for mov in line.split(' ') {
+                let mov = mov.parse().unwrap();
+                assert!(board.is_legal(mov), "{} is illegal in {}", mov, line);
+
+                *weights
+                    .entry((polyglot_key(&board), book_move(mov)))
+                    .or_insert(0u16) += 10;
+                board.play(mov);
+            }
+        }
+
+        let mut bytes = Vec::new();
+        for ((key, mov), weight) in weights {
+            bytes.extend(key.to_be_bytes());
+            bytes.extend(mov.to_be_bytes());
+            bytes.extend(weight.to_be_bytes());
+            // the learn field, which nothing here uses
+            bytes.extend(0u32.to_be_bytes());
+        }
+        bytes
+    }
+
+    // set WRITE_DEFAULT_BOOK to regenerate the file, then run the tests again
+    // so the new file is compiled in
+    #[test]
+    fn test_default_book_is_generated() {
+        let bytes = default_book_bytes();
+
+        if std::env::var_os("WRITE_DEFAULT_BOOK").is_some() {
+            let path = concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/src/algorithm/book/default.bin"
+            );
+            std::fs::write(path, &bytes).unwrap();
+        }
+
+        assert_eq!(bytes, DEFAULT_BOOK);
+    }
+
+    #[test]
+    fn test_polyglot_key() {
+        // reference keys from the polyglot specification
+        let reference = [
+            (
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+                0x463b96181691fc9c,
+            ),
+            (
+                "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1",
+                0x823c9b50fd114196,
+            ),
+            (
+                "rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 2",
+                0x0756b94461c50fb0,
+            ),
+            (
+                "rnbqkbnr/ppp1pppp/8/3pP3/8/8/PPPP1PPP/RNBQKBNR b KQkq - 0 2",
+                0x662fafb965db29d4,
+            ),
+            (
+                "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+                0x22a48b5a8e47ff78,
+            ),
+            (
+                "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPPKPPP/RNBQ1BNR b kq - 1 3",
+                0x652a607ca3f242c1,
+            ),
+            (
+                "rnbq1bnr/ppp1pkpp/8/3pPp2/8/8/PPPPKPPP/RNBQ1BNR w - - 2 4",
+                0x00fdd303c946bdd9,
+            ),
+            (
+                "rnbqkbnr/p1pppppp/8/8/PpP4P/8/1P1PPPP1/RNBQKBNR b KQkq c3 0 3",
+                0x3c8123ea7b067637,
+            ),
+            (
+                "rnbqkbnr/p1pppppp/8/8/P6P/R1p5/1P1PPPP1/1NBQKBNR b Kkq - 0 4",
+                0x5c3f9b829b279560,
+            ),
+        ];
+
+        for (fen, expected) in reference {
+            let board = Board::from_fen(fen, false).unwrap();
+            assert_eq!(polyglot_key(&board), expected, "Key mismatch for {}", fen);
+        }
+    }
+
+    #[test]
+    fn test_book_entry_to_move() {
+        // e2e4
+        let entry = BookEntry {
+            key: 0,
+            mov: (12 << 6) | 28,
+            weight: 1,
+        };
+        assert_eq!(entry.to_move(), "e2e4".parse().unwrap());
+
+        // a7a8q
+        let entry = BookEntry {
+            key: 0,
+            mov: (4 << 12) | (48 << 6) | 56,
+            weight: 1,
+        };
+        assert_eq!(entry.to_move(), "a7a8q".parse().unwrap());
+
+        // white short castling, king takes rook
+        let entry = BookEntry {
+            key: 0,
+            mov: (4 << 6) | 7,
+            weight: 1,
+        };
+        assert_eq!(entry.to_move(), "e1h1".parse().unwrap());
+    }
+
+    #[test]
+    fn test_book_from_bytes() {
+        let board = Board::startpos();
+        let key = polyglot_key(&board);
+
+        let mut bytes = Vec::new();
+        for (mov, weight) in [((12u16 << 6) | 28, 3u16), ((11 << 6) | 27, 1)] {
+            bytes.extend(key.to_be_bytes());
+            bytes.extend(mov.to_be_bytes());
+            bytes.extend(weight.to_be_bytes());
+            bytes.extend(0u32.to_be_bytes());
+        }
+
+        let book = OpeningBook::from_bytes(&bytes).unwrap();
+        assert_eq!(
+            book.moves(&board),
+            vec![("e2e4".parse().unwrap(), 3), ("d2d4".parse().unwrap(), 1)]
+        );
+
+        assert_eq!(
+            OpeningBook::from_bytes(&bytes[1..]).unwrap_err(),
+            BookError::InvalidLength(31)
+        );
+    }
+
+    #[test]
+    fn test_embedded_book() {
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let book = OpeningBook::embedded();
+        let mut board = Board::startpos();
+
+        // every embedded line should stay in book for the first few moves
+        for _ in 0..4 {
+            let mov = book
+                .choose_move(&board, &mut rng)
+                .expect("should be in book");
+            board.play(mov);
+        }
+
+        // nothing to play from an empty book
+        assert_eq!(OpeningBook::default().choose_move(&board, &mut rng), None);
+    }
+}
@@ -1,16 +1,30 @@
+use super::score::Score;
 use cozy_chess::Move;
-use rand::{distributions::WeightedIndex, prelude::Distribution, thread_rng};
+use rand::{distributions::WeightedIndex, prelude::Distribution, Rng, RngCore};
+use std::sync::Arc;
 
-pub trait Chooser {
-    fn choose<'a>(&self, choices: &'a [Move], weights: &[f64]) -> Option<&'a Move>;
+pub trait Chooser: Send + Sync {
+    // all randomness comes from `rng`, so a seeded rng gives repeatable games
+    fn choose<'a>(
+        &self,
+        choices: &'a [Move],
+        weights: &[Score],
+        rng: &mut dyn RngCore,
+    ) -> Option<&'a Move>;
 }
 
 #[derive(Default, Clone)]
 pub struct StochasticChooser;
 
 impl StochasticChooser {
-    fn normalise(&self, weights: &[f64]) -> Vec<f64> {
-        let mut weights = weights.iter().map(|a| 10f64.powf(*a)).collect::<Vec<_>>();
+    fn normalise(&self, weights: &[Score]) -> Vec<f64> {
+        // shifting by the best score keeps mate scores from overflowing
+        let best = weights.iter().max().copied().unwrap_or_default();
+
+        let mut weights = weights
+            .iter()
+            .map(|a| 10f64.powf((*a - best).as_pawns()))
+            .collect::<Vec<_>>();
 
         let sum = weights.iter().sum::<f64>();
 
@@ -23,13 +37,103 @@ impl StochasticChooser {
 }
 
 impl Chooser for StochasticChooser {
-    fn choose<'a>(&self, choices: &'a [Move], weights: &[f64]) -> Option<&'a Move> {
+    fn choose<'a>(
+        &self,
+        choices: &'a [Move],
+        weights: &[Score],
+        rng: &mut dyn RngCore,
+    ) -> Option<&'a Move> {
         let weights = self.normalise(weights);
 
-        let mut rng = thread_rng();
-        let dist = WeightedIndex::new(weights).unwrap();
+        let dist = WeightedIndex::new(weights).ok()?;
 
-        choices.get(dist.sample(&mut rng))
+        choices.get(dist.sample(rng))
+    }
+}
+
+/// Picks moves with probability proportional to `exp(score / temperature)`,
+/// with the temperature in pawns. Lower temperatures play closer to the best
+/// move, and a temperature of zero always plays it.
+#[derive(Clone, Debug)]
+pub struct SoftmaxChooser {
+    temperature: f64,
+    // only the k best moves are considered, along with any tied with the kth
+    top_k: Option<usize>,
+    // only moves within this much of the best move are considered
+    margin: Option<Score>,
+}
+
+impl SoftmaxChooser {
+    pub fn new(temperature: f64) -> Self {
+        Self {
+            temperature: temperature.max(0.0),
+            top_k: None,
+            margin: None,
+        }
+    }
+
+    pub fn with_top_k(mut self, top_k: usize) -> Self {
+        self.top_k = Some(top_k.max(1));
+        self
+    }
+
+    pub fn with_margin(mut self, margin: Score) -> Self {
+        self.margin = Some(margin);
+        self
+    }
+
+    /// Probability of choosing each move, in the same order as the weights.
+    pub fn probabilities(&self, weights: &[Score]) -> Vec<f64> {
+        let Some(best) = weights.iter().max().copied() else {
+            return Vec::new();
+        };
+
+        let mut ranked = (0..weights.len()).collect::<Vec<_>>();
+        ranked.sort_by(|a, b| weights[*b].cmp(&weights[*a]));
+        let cutoff = ranked[self.top_k.unwrap_or(weights.len()).min(weights.len()) - 1];
+
+        let mut probabilities = weights
+            .iter()
+            .map(|weight| {
+                let in_top_k = *weight >= weights[cutoff];
+                let in_margin = match self.margin {
+                    Some(margin) => best - *weight <= margin,
+                    None => true,
+                };
+
+                match (in_top_k && in_margin, self.temperature > 0.0) {
+                    (false, _) => 0.0,
+                    // subtracting the best score keeps every exponent at or
+                    // below zero, so mate scores can't overflow
+                    (true, true) => ((*weight - best).as_pawns() / self.temperature).exp(),
+                    (true, false) => match *weight == best {
+                        true => 1.0,
+                        false => 0.0,
+                    },
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let sum = probabilities.iter().sum::<f64>();
+
+        for probability in &mut probabilities {
+            *probability /= sum;
+        }
+
+        probabilities
+    }
+}
+
+impl Chooser for SoftmaxChooser {
+    fn choose<'a>(
+        &self,
+        choices: &'a [Move],
+        weights: &[Score],
+        rng: &mut dyn RngCore,
+    ) -> Option<&'a Move> {
+        let dist = WeightedIndex::new(self.probabilities(weights)).ok()?;
+
+        choices.get(dist.sample(rng))
     }
 }
 
@@ -37,25 +141,90 @@ impl Chooser for StochasticChooser {
 pub struct GreedyChooser;
 
 impl Chooser for GreedyChooser {
-    fn choose<'a>(&self, choices: &'a [Move], weights: &[f64]) -> Option<&'a Move> {
-        choices
+    fn choose<'a>(
+        &self,
+        choices: &'a [Move],
+        weights: &[Score],
+        _rng: &mut dyn RngCore,
+    ) -> Option<&'a Move> {
+        // first of the best weights, so ties resolve to the earliest move
+        let (best, _) = weights.iter().enumerate().fold(
+            None,
+            |best: Option<(usize, &Score)>, (i, weight)| match best {
+                Some((_, best_weight)) if best_weight >= weight => best,
+                _ => Some((i, weight)),
+            },
+        )?;
+
+        choices.get(best)
+    }
+}
+
+/// Every so often plays a move that gives away at least `loss` compared to
+/// the best move, so beginners get mistakes to punish. The rest of the time
+/// the wrapped chooser decides. Only works with evaluators whose scores are
+/// centipawns, see `Evaluator::scores_centipawns`.
+#[derive(Clone)]
+pub struct BlunderChooser {
+    blunder_rate: f64,
+    loss: Score,
+    chooser: Arc<dyn Chooser>,
+}
+
+impl BlunderChooser {
+    pub fn new(blunder_rate: f64, loss: Score) -> Self {
+        Self {
+            blunder_rate: blunder_rate.clamp(0.0, 1.0),
+            loss,
+            chooser: Arc::new(GreedyChooser),
+        }
+    }
+
+    pub fn with_chooser(mut self, chooser: Arc<dyn Chooser>) -> Self {
+        self.chooser = chooser;
+        self
+    }
+
+    /// The mildest move that still loses at least `loss`, if there is one.
+    pub fn blunder<'a>(&self, choices: &'a [Move], weights: &[Score]) -> Option<&'a Move> {
+        let best = weights.iter().max()?;
+
+        let (blunder, _) = weights
             .iter()
-            .zip(weights.iter())
-            .max_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap())
-            .map(|(choice, _)| choice)
+            .enumerate()
+            .filter(|(_, weight)| *best - **weight >= self.loss)
+            .max_by_key(|(_, weight)| **weight)?;
+
+        choices.get(blunder)
     }
 }
 
-fn sigmoid(x: &f64) -> f64 {
-    1.0 / (1.0 + (-x).exp())
+impl Chooser for BlunderChooser {
+    fn choose<'a>(
+        &self,
+        choices: &'a [Move],
+        weights: &[Score],
+        rng: &mut dyn RngCore,
+    ) -> Option<&'a Move> {
+        if rng.gen_bool(self.blunder_rate) {
+            if let Some(blunder) = self.blunder(choices, weights) {
+                return Some(blunder);
+            }
+        }
+
+        self.chooser.choose(choices, weights, rng)
+    }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
 
     #[test]
     fn test_greedy_chooser() {
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
         let chooser = GreedyChooser::default();
 
         let choices: Vec<_> = vec![
@@ -65,20 +234,24 @@ mod test {
         .map(|a| a.parse().unwrap())
         .collect();
 
-        let weights = vec![0.0; 10];
+        let weights = vec![Score::ZERO; 10];
 
         for i in 0..10 {
             for _ in 0..100 {
                 let mut new_weights = weights.clone();
-                new_weights[i] = 1.0;
+                new_weights[i] = Score::centipawns(100);
 
-                assert_eq!(chooser.choose(&choices, &new_weights), choices.get(i));
+                assert_eq!(
+                    chooser.choose(&choices, &new_weights, &mut rng),
+                    choices.get(i)
+                );
             }
         }
     }
 
     #[test]
     fn test_stochastic_chooser() {
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
         let chooser = StochasticChooser::default();
 
         let choices = vec![
@@ -88,15 +261,139 @@ mod test {
         .map(|a| a.parse().unwrap())
         .collect::<Vec<_>>();
 
-        let weights = vec![f64::NEG_INFINITY; 10];
+        let weights = vec![-Score::INFINITY; 10];
 
         for i in 0..10 {
             for _ in 0..100 {
                 let mut new_weights = weights.clone();
-                new_weights[i] = 1.0;
+                new_weights[i] = Score::centipawns(100);
 
-                assert_eq!(chooser.choose(&choices, &new_weights), choices.get(i));
+                assert_eq!(
+                    chooser.choose(&choices, &new_weights, &mut rng),
+                    choices.get(i)
+                );
             }
         }
     }
+
+    #[test]
+    fn test_seeded_stochastic_chooser() {
+        let chooser = StochasticChooser;
+        let choices = ["a2a4", "b2b4", "c2c4", "d2d4"].map(|a| a.parse().unwrap());
+        let weights = [Score::ZERO; 4];
+
+        let picks = |seed| {
+            let mut rng = ChaCha8Rng::seed_from_u64(seed);
+            (0..20)
+                .map(|_| *chooser.choose(&choices, &weights, &mut rng).unwrap())
+                .collect::<Vec<Move>>()
+        };
+
+        // the same seed always makes the same choices, even between equal moves
+        assert_eq!(picks(3), picks(3));
+        assert_ne!(picks(3), picks(4));
+    }
+
+    #[test]
+    fn test_stochastic_chooser_with_mates() {
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let chooser = StochasticChooser::default();
+        let choices = ["a2a4", "b2b4"].map(|a| a.parse().unwrap());
+
+        let weights = [Score::mate_in(1), Score::mated_in(2)];
+
+        assert_eq!(
+            chooser.choose(&choices, &weights, &mut rng),
+            choices.first()
+        );
+        assert_eq!(chooser.choose(&[], &[], &mut rng), None);
+    }
+
+    #[test]
+    fn test_softmax_probabilities() {
+        let weights = [
+            Score::centipawns(100),
+            Score::ZERO,
+            Score::centipawns(100),
+            Score::mated_in(1),
+        ];
+
+        let probabilities = SoftmaxChooser::new(1.0).probabilities(&weights);
+        assert!((probabilities.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+        assert_eq!(probabilities[0], probabilities[2]);
+        assert!((probabilities[0] / probabilities[1] - 1f64.exp()).abs() < 1e-9);
+        assert_eq!(probabilities[3], 0.0);
+
+        // hotter means more even
+        let hot = SoftmaxChooser::new(10.0).probabilities(&weights);
+        assert!(hot[1] > probabilities[1]);
+
+        // zero temperature splits between the best moves only
+        assert_eq!(
+            SoftmaxChooser::new(0.0).probabilities(&weights),
+            vec![0.5, 0.0, 0.5, 0.0]
+        );
+
+        // the cutoffs leave out weaker moves
+        let top_k = SoftmaxChooser::new(1.0)
+            .with_top_k(1)
+            .probabilities(&weights);
+        assert_eq!(top_k[1], 0.0);
+        assert!(top_k[0] > 0.0);
+
+        let margin = SoftmaxChooser::new(1.0)
+            .with_margin(Score::centipawns(50))
+            .probabilities(&weights);
+        assert_eq!(margin, vec![0.5, 0.0, 0.5, 0.0]);
+    }
+
+    #[test]
+    fn test_softmax_chooser_with_mates() {
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let chooser = SoftmaxChooser::new(0.5).with_top_k(3);
+        let choices = ["a2a4", "b2b4", "c2c4"].map(|a| a.parse().unwrap());
+
+        let weights = [Score::mated_in(2), Score::mate_in(3), -Score::INFINITY];
+
+        for _ in 0..100 {
+            assert_eq!(chooser.choose(&choices, &weights, &mut rng), choices.get(1));
+        }
+
+        assert_eq!(chooser.choose(&[], &[], &mut rng), None);
+    }
+
+    #[test]
+    fn test_blunder_chooser() {
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let choices = ["a2a4", "b2b4", "c2c4", "d2d4"].map(|a| a.parse().unwrap());
+        let weights = [
+            Score::centipawns(50),
+            Score::centipawns(-100),
+            Score::centipawns(-400),
+            Score::mated_in(2),
+        ];
+
+        // loses at least two pawns, but no more than needed
+        let chooser = BlunderChooser::new(1.0, Score::centipawns(200));
+        for _ in 0..100 {
+            assert_eq!(chooser.choose(&choices, &weights, &mut rng), choices.get(2));
+        }
+
+        // nothing bad enough, so fall back to the best move
+        let chooser = BlunderChooser::new(1.0, Score::INFINITY);
+        assert_eq!(
+            chooser.choose(&choices, &weights, &mut rng),
+            choices.first()
+        );
+
+        let chooser = BlunderChooser::new(0.0, Score::centipawns(200));
+        for _ in 0..100 {
+            assert_eq!(
+                chooser.choose(&choices, &weights, &mut rng),
+                choices.first()
+            );
+        }
+
+        assert_eq!(chooser.choose(&[], &[], &mut rng), None);
+    }
 }
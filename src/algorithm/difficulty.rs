@@ -0,0 +1,105 @@
+use super::{
+    choose::{Chooser, SoftmaxChooser},
+    eval::{Evaluator, PrincipalVariationSearch},
+    score::Score,
+};
+use std::sync::Arc;
+
+/// Named strength levels, each pairing a search depth with how freely the
+/// chooser strays from the best move.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Difficulty {
+    Beginner,
+    Casual,
+    Intermediate,
+    Advanced,
+    Master,
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 5] = [
+        Difficulty::Beginner,
+        Difficulty::Casual,
+        Difficulty::Intermediate,
+        Difficulty::Advanced,
+        Difficulty::Master,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Difficulty::Beginner => "Beginner",
+            Difficulty::Casual => "Casual",
+            Difficulty::Intermediate => "Intermediate",
+            Difficulty::Advanced => "Advanced",
+            Difficulty::Master => "Master",
+        }
+    }
+
+    pub fn depth(&self) -> usize {
+        match self {
+            Difficulty::Beginner => 1,
+            Difficulty::Casual => 2,
+            Difficulty::Intermediate => 3,
+            Difficulty::Advanced => 5,
+            Difficulty::Master => 7,
+        }
+    }
+
+    // in pawns, see `SoftmaxChooser`
+    pub fn temperature(&self) -> f64 {
+        match self {
+            Difficulty::Beginner => 1.0,
+            Difficulty::Casual => 0.5,
+            Difficulty::Intermediate => 0.25,
+            Difficulty::Advanced => 0.1,
+            Difficulty::Master => 0.0,
+        }
+    }
+
+    pub fn evaluator(&self) -> Arc<dyn Evaluator> {
+        Arc::new(PrincipalVariationSearch::new(self.depth()))
+    }
+
+    pub fn chooser(&self) -> Arc<dyn Chooser> {
+        let chooser = SoftmaxChooser::new(self.temperature());
+
+        // beginners may play anything, stronger levels only consider moves
+        // that don't throw away too much
+        let chooser = match self {
+            Difficulty::Beginner => chooser,
+            Difficulty::Casual => chooser.with_top_k(5).with_margin(Score::centipawns(300)),
+            Difficulty::Intermediate => chooser.with_top_k(3).with_margin(Score::centipawns(150)),
+            Difficulty::Advanced => chooser.with_top_k(2).with_margin(Score::centipawns(50)),
+            Difficulty::Master => chooser.with_top_k(1),
+        };
+
+        Arc::new(chooser)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    #[test]
+    fn test_difficulty_levels_get_stronger() {
+        for pair in Difficulty::ALL.windows(2) {
+            assert!(pair[0].depth() <= pair[1].depth());
+            assert!(pair[0].temperature() > pair[1].temperature());
+        }
+    }
+
+    #[test]
+    fn test_master_always_plays_best_move() {
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let chooser = Difficulty::Master.chooser();
+        let choices = ["a2a4", "b2b4", "c2c4"].map(|a| a.parse().unwrap());
+        let weights = [Score::ZERO, Score::centipawns(1), Score::centipawns(-1)];
+
+        for _ in 0..100 {
+            assert_eq!(chooser.choose(&choices, &weights, &mut rng), choices.get(1));
+        }
+    }
+}
@@ -1,12 +1,312 @@
-use super::score::ScoreFunction;
-use cozy_chess::{Board, Color, GameStatus, Move};
-use std::rc::Rc;
+pub mod mcts;
+
+use super::score::{
+    nnue::{Accumulator, Network},
+    Score, ScoreFunction,
+};
+use cozy_chess::{Board, Color, GameStatus, Move, Piece};
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+};
+
+pub trait Evaluator: Send + Sync {
+    /// Scores every legal move from the side to move's point of view. A
+    /// stopped search returns the scores from the deepest search it finished.
+    fn search(
+        &self,
+        board: Board,
+        score_fn: Arc<dyn ScoreFunction>,
+        context: &SearchContext,
+    ) -> Vec<(Move, Score)>;
+
+    fn eval_moves(&self, board: Board, score_fn: Arc<dyn ScoreFunction>) -> Vec<(Move, Score)> {
+        self.search(board, score_fn, &SearchContext::default())
+    }
+
+    /// Deepest iteration of an iterative deepening search, so a caller can
+    /// run it one depth at a time through `SearchContext::with_depth_limit`.
+    /// Searches that don't deepen return `None`.
+    fn depth(&self) -> Option<usize> {
+        None
+    }
+
+    /// Whether the scores are evaluations in centipawns, which choosers and
+    /// reviews that measure how much a move gives away rely on. Searches that
+    /// rank moves some other way return `false`.
+    fn scores_centipawns(&self) -> bool {
+        true
+    }
+}
+
+/// Best move so far, reported each time a search finishes another depth.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Progress {
+    pub depth: usize,
+    pub nodes: usize,
+    pub best_move: Move,
+    // from the side to move's point of view
+    pub score: Score,
+}
+
+impl fmt::Display for Progress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "depth {}: {} ({}, {} nodes)",
+            self.depth, self.best_move, self.score, self.nodes
+        )
+    }
+}
+
+pub type ProgressCallback = Arc<dyn Fn(&Progress) + Send + Sync>;
+
+/// Everything a caller can tell a running search: a flag to stop it early,
+/// node and depth limits on top of the evaluator's own, and a callback for
+/// progress. Time limits work by setting the stop flag from elsewhere.
+#[derive(Clone, Default)]
+pub struct SearchContext {
+    stop: Arc<AtomicBool>,
+    node_limit: Option<usize>,
+    depth_limit: Option<usize>,
+    on_progress: Option<ProgressCallback>,
+}
+
+impl SearchContext {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // shares a stop flag, so whoever holds it can stop the search
+    pub fn with_stop(mut self, stop: Arc<AtomicBool>) -> Self {
+        self.stop = stop;
+        self
+    }
+
+    pub fn with_node_limit(mut self, node_limit: Option<usize>) -> Self {
+        self.node_limit = node_limit;
+        self
+    }
+
+    pub fn with_depth_limit(mut self, depth_limit: Option<usize>) -> Self {
+        self.depth_limit = depth_limit;
+        self
+    }
+
+    pub fn with_progress(mut self, on_progress: ProgressCallback) -> Self {
+        self.on_progress = Some(on_progress);
+        self
+    }
+
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.stop.load(Ordering::Relaxed)
+    }
+
+    pub fn node_limit(&self) -> Option<usize> {
+        self.node_limit
+    }
+
+    pub fn depth_limit(&self) -> Option<usize> {
+        self.depth_limit
+    }
+
+    /// Reports the first of the best scores.
+    pub fn report(
+        &self,
+        depth: usize,
+        nodes: usize,
+        evals: impl IntoIterator<Item = (Move, Score)>,
+    ) {
+        let Some(on_progress) = &self.on_progress else {
+            return;
+        };
+
+        let best = evals
+            .into_iter()
+            .fold(None, |best: Option<(Move, Score)>, eval| match best {
+                Some(best) if best.1 >= eval.1 => Some(best),
+                _ => Some(eval),
+            });
+
+        if let Some((best_move, score)) = best {
+            on_progress(&Progress {
+                depth,
+                nodes,
+                best_move,
+                score,
+            });
+        }
+    }
+}
+
+/// Counts the nodes visited by one search against an optional budget, and
+/// watches the search's stop flag. Node counts don't depend on how fast the
+/// machine is, so a limited search gives the same result everywhere, as long
+/// as it runs on a single thread and isn't stopped.
+struct NodeCounter<'a> {
+    nodes: AtomicUsize,
+    limit: Option<usize>,
+    stop: &'a AtomicBool,
+}
+
+impl<'a> NodeCounter<'a> {
+    // the tighter of the evaluator's and the context's limits applies
+    fn new(limit: Option<usize>, context: &'a SearchContext) -> Self {
+        let limit = match (limit, context.node_limit) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+
+        Self {
+            nodes: AtomicUsize::new(0),
+            limit,
+            stop: &context.stop,
+        }
+    }
+
+    fn count(&self) -> usize {
+        self.nodes.load(Ordering::Relaxed)
+    }
+
+    // counts a node, returning true once the budget has run out
+    fn visit(&self) -> bool {
+        self.nodes.fetch_add(1, Ordering::Relaxed);
+        self.exhausted()
+    }
+
+    fn exhausted(&self) -> bool {
+        self.stop.load(Ordering::Relaxed)
+            || matches!(self.limit, Some(limit) if self.count() > limit)
+    }
+}
+
+// searches one ply deeper at a time and keeps the deepest search that
+// finished, so running out of nodes or being stopped still leaves a result.
+// the first depth only scores leaves, which never stop early, so every move
+// always gets a score.
+fn deepen(
+    depth: usize,
+    nodes: &NodeCounter,
+    context: &SearchContext,
+    mut search: impl FnMut(usize) -> Vec<(Move, Score)>,
+) -> Vec<(Move, Score)> {
+    let depth = context.depth_limit.map_or(depth, |limit| depth.min(limit));
+
+    let mut best = search(1);
+    context.report(1, nodes.count(), best.iter().copied());
+
+    for depth in 2..=depth {
+        let result = search(depth);
+
+        if nodes.exhausted() {
+            break;
+        }
+
+        best = result;
+        context.report(depth, nodes.count(), best.iter().copied());
+    }
+
+    best
+}
+
+// scores every root move, handing them out one at a time to `threads`
+// workers. wasm can't spawn threads, so there the moves are searched in order.
+fn search_root(
+    moves: &[Move],
+    threads: usize,
+    search: impl Fn(usize, &Move) -> Score + Sync,
+) -> Vec<Score> {
+    if threads <= 1 || cfg!(target_arch = "wasm32") {
+        return moves
+            .iter()
+            .enumerate()
+            .map(|(i, mov)| search(i, mov))
+            .collect();
+    }
+
+    let next = AtomicUsize::new(0);
+    let search = &search;
+
+    let mut scores = thread::scope(|scope| {
+        let workers = (0..threads.min(moves.len()))
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut scores = vec![];
+
+                    loop {
+                        let i = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(mov) = moves.get(i) else {
+                            return scores;
+                        };
+                        scores.push((i, search(i, mov)));
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().expect("search thread should not panic"))
+            .collect::<Vec<_>>()
+    });
+
+    scores.sort_by_key(|(i, _)| *i);
+    scores.into_iter().map(|(_, score)| score).collect()
+}
+
+// a board along with the network's accumulator for it, when the score
+// function has a network
+#[derive(Clone)]
+struct Position<'a> {
+    board: Board,
+    nnue: Option<(&'a Network, Accumulator)>,
+}
+
+impl<'a> Position<'a> {
+    fn new(board: Board, network: Option<&'a Network>) -> Self {
+        let nnue = network.map(|network| (network, Accumulator::new(network, &board)));
+        Self { board, nnue }
+    }
+
+    fn play(&self, mov: Move) -> Self {
+        let mut board = self.board.clone();
+        board.play(mov);
+
+        let nnue = self.nnue.as_ref().map(|(network, accumulator)| {
+            (*network, accumulator.play(network, &self.board, &board))
+        });
+
+        Self { board, nnue }
+    }
+
+    fn null_move(&self) -> Option<Self> {
+        Some(Self {
+            board: self.board.null_move()?,
+            nnue: self.nnue.clone(),
+        })
+    }
+
+    // from the side to move's point of view
+    fn score(&self, score_fn: &Arc<dyn ScoreFunction>) -> Score {
+        let side = self.board.side_to_move();
 
-pub trait Evaluator {
-    fn eval_moves(&self, board: Board, score_fn: Rc<dyn ScoreFunction>) -> Vec<(Move, f64)>;
+        match (&self.nnue, side) {
+            (Some((network, accumulator)), _) => network.evaluate(accumulator, side),
+            (None, Color::White) => score_fn.score(&self.board),
+            (None, Color::Black) => -score_fn.score(&self.board),
+        }
+    }
 }
 
-fn get_sorted_moves(board: &Board, side: &Color) -> Vec<Move> {
+pub(crate) fn get_sorted_moves(board: &Board, side: &Color) -> Vec<Move> {
     let mut checkers = Vec::with_capacity(4);
     let mut promotions = Vec::with_capacity(4);
     let mut captures = Vec::with_capacity(20);
@@ -42,45 +342,70 @@ fn get_sorted_moves(board: &Board, side: &Color) -> Vec<Move> {
 pub struct NaiveEvaluator;
 
 impl Evaluator for NaiveEvaluator {
-    fn eval_moves(&self, board: Board, score_fn: Rc<dyn ScoreFunction>) -> Vec<(Move, f64)> {
+    fn search(
+        &self,
+        board: Board,
+        score_fn: Arc<dyn ScoreFunction>,
+        context: &SearchContext,
+    ) -> Vec<(Move, Score)> {
         let side = board.side_to_move();
         let moves = get_sorted_moves(&board, &side);
 
-        moves
+        let evals = moves
             .into_iter()
             .map(|mov| {
                 let mut temp_board = board.clone();
                 temp_board.play(mov);
                 (
                     mov,
-                    score_fn.score(&temp_board)
-                        * match side {
-                            Color::White => 1.0,
-                            Color::Black => -1.0,
-                        },
+                    match side {
+                        Color::White => score_fn.score(&temp_board),
+                        Color::Black => -score_fn.score(&temp_board),
+                    },
                 )
             })
-            .collect()
+            .collect::<Vec<_>>();
+
+        context.report(1, evals.len(), evals.iter().copied());
+        evals
     }
 }
 
 #[derive(Clone)]
 pub struct Negamax {
     depth: usize,
+    node_limit: Option<usize>,
 }
 
 impl Default for Negamax {
     fn default() -> Self {
-        Self { depth: 2 }
+        Self::new(2)
     }
 }
 
 impl Negamax {
     pub fn new(depth: usize) -> Self {
-        Self { depth }
+        Self {
+            depth,
+            node_limit: None,
+        }
     }
 
-    fn negamax(score_fn: Rc<dyn ScoreFunction>, board: Board, depth: usize, negative: bool) -> f64 {
+    pub fn with_node_limit(mut self, node_limit: Option<usize>) -> Self {
+        self.node_limit = node_limit;
+        self
+    }
+
+    fn negamax(
+        score_fn: Arc<dyn ScoreFunction>,
+        board: Board,
+        depth: usize,
+        ply: usize,
+        negative: bool,
+        nodes: &NodeCounter,
+    ) -> Score {
+        let out_of_nodes = nodes.visit();
+
         if depth == 0 {
             return match negative {
                 true => -score_fn.score(&board),
@@ -88,19 +413,16 @@ impl Negamax {
             };
         };
 
+        // the unfinished search gets thrown away, so any score will do
+        if out_of_nodes {
+            return Score::ZERO;
+        }
+
         let side = board.side_to_move();
 
         match board.status() {
-            GameStatus::Drawn => return 0.0,
-            GameStatus::Won => {
-                return match negative {
-                    true => -1.0,
-                    false => 1.0,
-                } * match side {
-                    Color::White => -1000.0,
-                    Color::Black => 1000.0,
-                };
-            }
+            GameStatus::Drawn => return Score::ZERO,
+            GameStatus::Won => return Score::mated_in(ply),
             GameStatus::Ongoing => {}
         };
 
@@ -111,15 +433,31 @@ impl Negamax {
             .map(|mov| {
                 let mut temp_board = board.clone();
                 temp_board.play(mov);
-                -Self::negamax(score_fn.clone(), temp_board, depth - 1, !negative)
+                -Self::negamax(
+                    score_fn.clone(),
+                    temp_board,
+                    depth - 1,
+                    ply + 1,
+                    !negative,
+                    nodes,
+                )
             })
-            .max_by(|a, b| a.partial_cmp(b).unwrap())
+            .max()
             .unwrap()
     }
 }
 
 impl Evaluator for Negamax {
-    fn eval_moves(&self, board: Board, score_fn: Rc<dyn ScoreFunction>) -> Vec<(Move, f64)> {
+    fn depth(&self) -> Option<usize> {
+        Some(self.depth)
+    }
+
+    fn search(
+        &self,
+        board: Board,
+        score_fn: Arc<dyn ScoreFunction>,
+        context: &SearchContext,
+    ) -> Vec<(Move, Score)> {
         let side = board.side_to_move();
         let moves = get_sorted_moves(&board, &side);
 
@@ -129,44 +467,81 @@ impl Evaluator for Negamax {
             Color::Black => false,
         };
 
-        moves
-            .into_iter()
-            .map(|mov| {
-                let mut temp_board = board.clone();
-                temp_board.play(mov);
-                (
-                    mov,
-                    -Self::negamax(score_fn.clone(), temp_board, self.depth - 1, negative),
-                )
-            })
-            .collect()
+        let nodes = NodeCounter::new(self.node_limit, context);
+
+        deepen(self.depth, &nodes, context, |depth| {
+            moves
+                .iter()
+                .map(|mov| {
+                    let mut temp_board = board.clone();
+                    temp_board.play(*mov);
+                    (
+                        *mov,
+                        -Self::negamax(
+                            score_fn.clone(),
+                            temp_board,
+                            depth - 1,
+                            1,
+                            negative,
+                            &nodes,
+                        ),
+                    )
+                })
+                .collect()
+        })
     }
 }
 
+/// Negamax with alpha-beta pruning. With more than one thread the root moves
+/// are shared out between them, each searched with a full window so the
+/// scores match a single-threaded search.
 #[derive(Clone)]
 pub struct AlphaBetaNegamax {
     depth: usize,
+    node_limit: Option<usize>,
+    threads: usize,
 }
 
 impl Default for AlphaBetaNegamax {
     fn default() -> Self {
-        Self { depth: 2 }
+        Self::new(2)
     }
 }
 
 impl AlphaBetaNegamax {
     pub fn new(depth: usize) -> Self {
-        Self { depth }
+        Self {
+            depth,
+            node_limit: None,
+            threads: 1,
+        }
+    }
+
+    pub fn with_node_limit(mut self, node_limit: Option<usize>) -> Self {
+        self.node_limit = node_limit;
+        self
     }
 
+    // the node limit is shared by all threads, so a limited search is only
+    // repeatable with one
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self
+    }
+
+    #[allow(clippy::too_many_arguments)]
     fn negamax(
-        score_fn: Rc<dyn ScoreFunction>,
+        score_fn: Arc<dyn ScoreFunction>,
         board: Board,
         depth: usize,
-        alpha: f64,
-        beta: f64,
+        ply: usize,
+        alpha: Score,
+        beta: Score,
         negative: bool,
-    ) -> f64 {
+        nodes: &NodeCounter,
+    ) -> Score {
+        let out_of_nodes = nodes.visit();
+
         if depth == 0 {
             return match negative {
                 true => -score_fn.score(&board),
@@ -174,23 +549,20 @@ impl AlphaBetaNegamax {
             };
         };
 
+        // the unfinished search gets thrown away, so any score will do
+        if out_of_nodes {
+            return Score::ZERO;
+        }
+
         let side = board.side_to_move();
 
         match board.status() {
-            GameStatus::Drawn => return 0.0,
-            GameStatus::Won => {
-                return match negative {
-                    true => -1.0,
-                    false => 1.0,
-                } * match side {
-                    Color::White => -1000.0,
-                    Color::Black => 1000.0,
-                };
-            }
+            GameStatus::Drawn => return Score::ZERO,
+            GameStatus::Won => return Score::mated_in(ply),
             GameStatus::Ongoing => {}
         };
 
-        let mut best_score = f64::NEG_INFINITY;
+        let mut best_score = -Score::INFINITY;
         let mut alpha = alpha;
 
         for mov in get_sorted_moves(&board, &side) {
@@ -201,9 +573,11 @@ impl AlphaBetaNegamax {
                 score_fn.clone(),
                 temp_board,
                 depth - 1,
+                ply + 1,
                 -beta,
                 -alpha,
                 !negative,
+                nodes,
             ));
 
             alpha = alpha.max(best_score);
@@ -218,7 +592,16 @@ impl AlphaBetaNegamax {
 }
 
 impl Evaluator for AlphaBetaNegamax {
-    fn eval_moves(&self, board: Board, score_fn: Rc<dyn ScoreFunction>) -> Vec<(Move, f64)> {
+    fn depth(&self) -> Option<usize> {
+        Some(self.depth)
+    }
+
+    fn search(
+        &self,
+        board: Board,
+        score_fn: Arc<dyn ScoreFunction>,
+        context: &SearchContext,
+    ) -> Vec<(Move, Score)> {
         let side = board.side_to_move();
         let moves = get_sorted_moves(&board, &side);
 
@@ -228,51 +611,68 @@ impl Evaluator for AlphaBetaNegamax {
             Color::Black => false,
         };
 
-        moves
-            .into_iter()
-            .map(|mov| {
+        let nodes = NodeCounter::new(self.node_limit, context);
+
+        deepen(self.depth, &nodes, context, |depth| {
+            let scores = search_root(&moves, self.threads, |_, mov| {
                 let mut temp_board = board.clone();
-                temp_board.play(mov);
-                (
-                    mov,
-                    -Self::negamax(
-                        score_fn.clone(),
-                        temp_board,
-                        self.depth - 1,
-                        f64::NEG_INFINITY,
-                        f64::INFINITY,
-                        negative,
-                    ),
+                temp_board.play(*mov);
+
+                -Self::negamax(
+                    score_fn.clone(),
+                    temp_board,
+                    depth - 1,
+                    1,
+                    -Score::INFINITY,
+                    Score::INFINITY,
+                    negative,
+                    &nodes,
                 )
-            })
-            .collect()
+            });
+
+            moves.iter().copied().zip(scores).collect()
+        })
     }
 }
 
 #[derive(Clone)]
 pub struct Negascout {
     depth: usize,
+    node_limit: Option<usize>,
 }
 
 impl Default for Negascout {
     fn default() -> Self {
-        Self { depth: 2 }
+        Self::new(2)
     }
 }
 
 impl Negascout {
     pub fn new(depth: usize) -> Self {
-        Self { depth }
+        Self {
+            depth,
+            node_limit: None,
+        }
     }
 
+    pub fn with_node_limit(mut self, node_limit: Option<usize>) -> Self {
+        self.node_limit = node_limit;
+        self
+    }
+
+    #[allow(clippy::too_many_arguments)]
     fn negascout(
-        score_fn: Rc<dyn ScoreFunction>,
+        score_fn: Arc<dyn ScoreFunction>,
         board: Board,
         depth: usize,
-        alpha: f64,
-        beta: f64,
+        ply: usize,
+        alpha: Score,
+        beta: Score,
         negative: bool,
-    ) -> f64 {
+        nodes: &NodeCounter,
+    ) -> Score {
+        let out_of_nodes = nodes.visit();
+
         if depth == 0 {
             return match negative {
                 true => -score_fn.score(&board),
@@ -280,19 +680,16 @@ impl Negascout {
             };
         };
 
+        // the unfinished search gets thrown away, so any score will do
+        if out_of_nodes {
+            return Score::ZERO;
+        }
+
         let side = board.side_to_move();
 
         match board.status() {
-            GameStatus::Drawn => return 0.0,
-            GameStatus::Won => {
-                return match negative {
-                    true => -1.0,
-                    false => 1.0,
-                } * match side {
-                    Color::White => -1000.0,
-                    Color::Black => 1000.0,
-                };
-            }
+            GameStatus::Drawn => return Score::ZERO,
+            GameStatus::Won => return Score::mated_in(ply),
             GameStatus::Ongoing => {}
         };
 
@@ -307,18 +704,22 @@ impl Negascout {
                     score_fn.clone(),
                     temp_board,
                     depth - 1,
+                    ply + 1,
                     -beta,
                     -alpha,
                     !negative,
+                    nodes,
                 )
             } else {
                 let score = -Self::negascout(
                     score_fn.clone(),
                     temp_board.clone(),
                     depth - 1,
-                    -(alpha + 1.0),
+                    ply + 1,
+                    -(alpha + Score::centipawns(1)),
                     -alpha,
                     !negative,
+                    nodes,
                 );
 
                 if alpha < score && score < beta && alpha < beta {
@@ -326,9 +727,11 @@ impl Negascout {
                         score_fn.clone(),
                         temp_board,
                         depth - 1,
+                        ply + 1,
                         -beta,
                         -score,
                         !negative,
+                        nodes,
                     )
                 } else {
                     score
@@ -346,7 +749,16 @@ impl Negascout {
 }
 
 impl Evaluator for Negascout {
-    fn eval_moves(&self, board: Board, score_fn: Rc<dyn ScoreFunction>) -> Vec<(Move, f64)> {
+    fn depth(&self) -> Option<usize> {
+        Some(self.depth)
+    }
+
+    fn search(
+        &self,
+        board: Board,
+        score_fn: Arc<dyn ScoreFunction>,
+        context: &SearchContext,
+    ) -> Vec<(Move, Score)> {
         let side = board.side_to_move();
         let moves = get_sorted_moves(&board, &side);
 
@@ -356,31 +768,331 @@ impl Evaluator for Negascout {
             Color::Black => false,
         };
 
-        moves
-            .into_iter()
-            .map(|mov| {
-                let mut temp_board = board.clone();
-                temp_board.play(mov);
-                (
-                    mov,
-                    -Self::negascout(
-                        score_fn.clone(),
-                        temp_board,
-                        self.depth - 1,
-                        f64::NEG_INFINITY,
-                        f64::INFINITY,
-                        negative,
-                    ),
+        let nodes = NodeCounter::new(self.node_limit, context);
+
+        deepen(self.depth, &nodes, context, |depth| {
+            moves
+                .iter()
+                .map(|mov| {
+                    let mut temp_board = board.clone();
+                    temp_board.play(*mov);
+                    (
+                        *mov,
+                        -Self::negascout(
+                            score_fn.clone(),
+                            temp_board,
+                            depth - 1,
+                            1,
+                            -Score::INFINITY,
+                            Score::INFINITY,
+                            negative,
+                            &nodes,
+                        ),
+                    )
+                })
+                .collect()
+        })
+    }
+}
+
+/// Principal variation search with aspiration windows at the root, null-move
+/// pruning and late-move reductions.
+///
+/// Each root move keeps its own score between iterations of the iterative
+/// deepening loop, so the aspiration window is centred on that move's previous
+/// score and every move still gets an exact evaluation for the chooser. That
+/// also means root moves don't depend on each other, so they can be split
+/// between threads.
+#[derive(Clone)]
+pub struct PrincipalVariationSearch {
+    depth: usize,
+    aspiration_window: Score,
+    null_move_pruning: bool,
+    late_move_reductions: bool,
+    node_limit: Option<usize>,
+    threads: usize,
+}
+
+impl Default for PrincipalVariationSearch {
+    fn default() -> Self {
+        Self::new(2)
+    }
+}
+
+impl PrincipalVariationSearch {
+    // width of the scout window used to test whether a move beats alpha
+    const NULL_WINDOW: Score = Score::centipawns(1);
+    const NULL_MOVE_REDUCTION: usize = 2;
+
+    pub fn new(depth: usize) -> Self {
+        Self {
+            depth,
+            aspiration_window: Score::centipawns(50),
+            null_move_pruning: true,
+            late_move_reductions: true,
+            node_limit: None,
+            threads: 1,
+        }
+    }
+
+    pub fn with_aspiration_window(mut self, aspiration_window: Score) -> Self {
+        self.aspiration_window = aspiration_window;
+        self
+    }
+
+    pub fn with_null_move_pruning(mut self, null_move_pruning: bool) -> Self {
+        self.null_move_pruning = null_move_pruning;
+        self
+    }
+
+    pub fn with_late_move_reductions(mut self, late_move_reductions: bool) -> Self {
+        self.late_move_reductions = late_move_reductions;
+        self
+    }
+
+    pub fn with_node_limit(mut self, node_limit: Option<usize>) -> Self {
+        self.node_limit = node_limit;
+        self
+    }
+
+    // as for `AlphaBetaNegamax`, a limited search is only repeatable with one
+    // thread
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self
+    }
+
+    // null moves are unsound when the side to move only has pawns left
+    fn zugzwang_prone(board: &Board) -> bool {
+        let side = board.colors(board.side_to_move());
+        let pawns_and_king = board.pieces(Piece::Pawn) | board.pieces(Piece::King);
+
+        (side & !pawns_and_king).is_empty()
+    }
+
+    fn is_quiet(board: &Board, mov: &Move) -> bool {
+        mov.promotion.is_none() && board.color_on(mov.to).is_none()
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn pvs(
+        &self,
+        score_fn: &Arc<dyn ScoreFunction>,
+        position: &Position,
+        depth: usize,
+        ply: usize,
+        alpha: Score,
+        beta: Score,
+        allow_null: bool,
+        nodes: &NodeCounter,
+    ) -> Score {
+        let board = &position.board;
+        let side = board.side_to_move();
+        let out_of_nodes = nodes.visit();
+
+        if depth == 0 {
+            return position.score(score_fn);
+        };
+
+        // the unfinished iteration gets thrown away, so any score will do
+        if out_of_nodes {
+            return Score::ZERO;
+        }
+
+        match board.status() {
+            GameStatus::Drawn => return Score::ZERO,
+            GameStatus::Won => return Score::mated_in(ply),
+            GameStatus::Ongoing => {}
+        };
+
+        let in_check = !board.checkers().is_empty();
+
+        if self.null_move_pruning
+            && allow_null
+            && !in_check
+            && depth > Self::NULL_MOVE_REDUCTION
+            && !Self::zugzwang_prone(board)
+        {
+            if let Some(null_position) = position.null_move() {
+                let score = -self.pvs(
+                    score_fn,
+                    &null_position,
+                    depth - 1 - Self::NULL_MOVE_REDUCTION,
+                    ply + 1,
+                    -beta,
+                    -beta + Self::NULL_WINDOW,
+                    false,
+                    nodes,
+                );
+
+                // the null move score is not a real line, so mates found
+                // through it are not trusted
+                if score >= beta {
+                    return beta;
+                }
+            }
+        }
+
+        let mut best_score = -Score::INFINITY;
+        let mut alpha = alpha;
+
+        for (i, mov) in get_sorted_moves(board, &side).into_iter().enumerate() {
+            let child = position.play(mov);
+
+            let score = if i == 0 {
+                -self.pvs(
+                    score_fn,
+                    &child,
+                    depth - 1,
+                    ply + 1,
+                    -beta,
+                    -alpha,
+                    true,
+                    nodes,
                 )
-            })
-            .collect()
+            } else {
+                let reduction = match self.late_move_reductions
+                    && depth >= 3
+                    && i >= 3
+                    && !in_check
+                    && child.board.checkers().is_empty()
+                    && Self::is_quiet(board, &mov)
+                {
+                    true => 1,
+                    false => 0,
+                };
+
+                let mut score = -self.pvs(
+                    score_fn,
+                    &child,
+                    depth - 1 - reduction,
+                    ply + 1,
+                    -alpha - Self::NULL_WINDOW,
+                    -alpha,
+                    true,
+                    nodes,
+                );
+
+                if score > alpha && reduction > 0 {
+                    score = -self.pvs(
+                        score_fn,
+                        &child,
+                        depth - 1,
+                        ply + 1,
+                        -alpha - Self::NULL_WINDOW,
+                        -alpha,
+                        true,
+                        nodes,
+                    );
+                }
+
+                if score > alpha && score < beta {
+                    score = -self.pvs(
+                        score_fn,
+                        &child,
+                        depth - 1,
+                        ply + 1,
+                        -beta,
+                        -alpha,
+                        true,
+                        nodes,
+                    );
+                }
+
+                score
+            };
+
+            best_score = best_score.max(score);
+            alpha = alpha.max(score);
+
+            if alpha >= beta {
+                break;
+            }
+        }
+
+        best_score
+    }
+
+    // searches a root move inside a window around its previous score,
+    // widening the window until the result falls inside it
+    fn aspiration_search(
+        &self,
+        score_fn: &Arc<dyn ScoreFunction>,
+        position: &Position,
+        depth: usize,
+        previous: Option<Score>,
+        nodes: &NodeCounter,
+    ) -> Score {
+        let Some(previous) = previous else {
+            return -self.pvs(
+                score_fn,
+                position,
+                depth,
+                1,
+                -Score::INFINITY,
+                Score::INFINITY,
+                true,
+                nodes,
+            );
+        };
+
+        let mut delta = self.aspiration_window;
+
+        loop {
+            let alpha = previous - delta;
+            let beta = previous + delta;
+
+            let score = -self.pvs(score_fn, position, depth, 1, -beta, -alpha, true, nodes);
+
+            if (alpha < score && score < beta) || nodes.exhausted() {
+                return score;
+            }
+
+            delta = match Score::centipawns(delta.as_centipawns() * 4) {
+                delta if delta >= Score::MATE => Score::INFINITY,
+                delta => delta,
+            };
+        }
+    }
+}
+
+impl Evaluator for PrincipalVariationSearch {
+    fn depth(&self) -> Option<usize> {
+        Some(self.depth)
+    }
+
+    fn search(
+        &self,
+        board: Board,
+        score_fn: Arc<dyn ScoreFunction>,
+        context: &SearchContext,
+    ) -> Vec<(Move, Score)> {
+        let side = board.side_to_move();
+        let moves = get_sorted_moves(&board, &side);
+
+        let nodes = NodeCounter::new(self.node_limit, context);
+        let position = Position::new(board, score_fn.network());
+
+        // each root move's score from the last iteration, which `deepen` only
+        // asks for another after keeping
+        let mut previous = vec![None; moves.len()];
+
+        deepen(self.depth, &nodes, context, |depth| {
+            let scores = search_root(&moves, self.threads, |i, mov| {
+                let child = position.play(*mov);
+                self.aspiration_search(&score_fn, &child, depth - 1, previous[i], &nodes)
+            });
+
+            previous = scores.iter().copied().map(Some).collect();
+            moves.iter().copied().zip(scores).collect()
+        })
     }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
-    use crate::algorithm::score::PawnDifferenceScore;
+    use crate::algorithm::score::{nnue::NnueScore, PawnDifferenceScore};
     use cozy_chess::{Board, Square};
 
     fn get_all_moves(board: &Board, side: &Color) -> Vec<Move> {
@@ -459,18 +1171,18 @@ mod test {
         let evaluator = Negamax::new(2);
 
         let board = Board::startpos();
-        let score_fn = Rc::new(PawnDifferenceScore::default());
+        let score_fn = Arc::new(PawnDifferenceScore::default());
 
         let eval = evaluator.eval_moves(board, score_fn);
 
-        let total_score: f64 = eval.into_iter().map(|(mov, score)| score).sum();
-        assert_eq!(total_score, 0.0);
+        let total_score: Score = eval.into_iter().map(|(mov, score)| score).sum();
+        assert_eq!(total_score, Score::ZERO);
     }
 
     #[test]
     fn pawn_move_test_negamax() {
         let evaluator = Negamax::new(2);
-        let score_fn = Rc::new(PawnDifferenceScore::default());
+        let score_fn = Arc::new(PawnDifferenceScore::default());
 
         let mut board = Board::startpos();
 
@@ -487,9 +1199,9 @@ mod test {
         println!("{:?}", eval);
         let mut nonzero_scores = eval
             .iter()
-            .filter_map(|(mov, score)| match score {
-                0.0 => None,
-                _ => Some(score),
+            .filter_map(|(mov, score)| match *score {
+                Score::ZERO => None,
+                score => Some(score),
             })
             .collect::<Vec<_>>();
 
@@ -498,9 +1210,15 @@ mod test {
 
         // C7C5, E7E5, G7G5, H7H6 all have score -1.0 as all present pawn captures
         // G8H6 has score -3.0 as it presents a knight capture
-        let expected: Vec<&f64> = vec![&-3.0, &-1.0, &-1.0, &-1.0, &-1.0];
-
-        nonzero_scores.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let expected: Vec<Score> = vec![
+            Score::centipawns(-300),
+            Score::centipawns(-100),
+            Score::centipawns(-100),
+            Score::centipawns(-100),
+            Score::centipawns(-100),
+        ];
+
+        nonzero_scores.sort();
         assert_eq!(nonzero_scores, expected);
     }
 
@@ -508,7 +1226,7 @@ mod test {
     fn test_alphabetanegamax_vs_negamax() {
         let expected_evaluator = Negamax::new(4);
         let evaluator = AlphaBetaNegamax::new(4);
-        let score_fn = Rc::new(PawnDifferenceScore::default());
+        let score_fn = Arc::new(PawnDifferenceScore::default());
 
         let mut board = Board::startpos();
 
@@ -538,7 +1256,7 @@ mod test {
     fn test_negascout_vs_negamax() {
         let expected_evaluator = Negamax::new(4);
         let evaluator = Negascout::new(4);
-        let score_fn = Rc::new(PawnDifferenceScore::default());
+        let score_fn = Arc::new(PawnDifferenceScore::default());
 
         let mut board = Board::startpos();
 
@@ -563,4 +1281,227 @@ mod test {
             assert_eq!(e, ee, "Zipped evals should be equal");
         }
     }
+
+    fn best_score(eval: &[(Move, Score)]) -> Score {
+        eval.iter().map(|(_, score)| *score).max().unwrap()
+    }
+
+    // mix of quiet, tactical and mating positions
+    const PVS_TEST_POSITIONS: [&str; 6] = [
+        "rnbqkbnr/pppppppp/8/8/3P4/8/PPP1PPPP/RNBQKBNR b KQkq - 0 1",
+        "r1bqkbnr/pppp1ppp/2n5/4p3/2B1P3/5Q2/PPPP1PPP/RNB1K1NR w KQkq - 4 4",
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "6k1/5ppp/8/8/8/8/5PPP/3R2K1 w - - 0 1",
+        "4k3/8/8/3q4/8/8/3R4/3RK3 w - - 0 1",
+        "8/8/8/4k3/8/8/4P3/4K3 w - - 0 1",
+    ];
+
+    #[test]
+    fn test_pvs_without_reductions_vs_alphabetanegamax() {
+        let expected_evaluator = AlphaBetaNegamax::new(3);
+        let evaluator = PrincipalVariationSearch::new(3)
+            .with_null_move_pruning(false)
+            .with_late_move_reductions(false);
+        let score_fn: Arc<dyn ScoreFunction> = Arc::new(PawnDifferenceScore::default());
+
+        for fen in PVS_TEST_POSITIONS {
+            let board = Board::from_fen(fen, false).unwrap();
+
+            let expected_eval = expected_evaluator.eval_moves(board.clone(), score_fn.clone());
+            let eval = evaluator.eval_moves(board, score_fn.clone());
+
+            assert_eq!(eval, expected_eval, "Evals differ for {}", fen);
+        }
+    }
+
+    #[test]
+    fn test_pvs_best_score_vs_alphabetanegamax() {
+        let expected_evaluator = AlphaBetaNegamax::new(4);
+        let evaluator = PrincipalVariationSearch::new(4);
+        let score_fn: Arc<dyn ScoreFunction> = Arc::new(PawnDifferenceScore::default());
+
+        for fen in PVS_TEST_POSITIONS {
+            let board = Board::from_fen(fen, false).unwrap();
+
+            let expected_eval = expected_evaluator.eval_moves(board.clone(), score_fn.clone());
+            let eval = evaluator.eval_moves(board, score_fn.clone());
+
+            assert_eq!(
+                eval.len(),
+                expected_eval.len(),
+                "Eval vs Expected (lengths)"
+            );
+            assert_eq!(
+                best_score(&eval),
+                best_score(&expected_eval),
+                "Best scores differ for {}",
+                fen
+            );
+        }
+    }
+
+    #[test]
+    fn test_pvs_finds_back_rank_mate() {
+        let evaluator = PrincipalVariationSearch::new(3);
+        let score_fn: Arc<dyn ScoreFunction> = Arc::new(PawnDifferenceScore::default());
+
+        let board = Board::from_fen("6k1/5ppp/8/8/8/8/5PPP/3R2K1 w - - 0 1", false).unwrap();
+        let eval = evaluator.eval_moves(board, score_fn);
+
+        let (mov, score) = eval.into_iter().max_by_key(|(_, score)| *score).unwrap();
+
+        assert_eq!(mov, "d1d8".parse().unwrap());
+        assert_eq!(score, Score::mate_in(1));
+    }
+
+    #[test]
+    fn test_pvs_with_nnue_vs_pawn_difference() {
+        // the reference network scores exactly like the pawn difference, so
+        // the incremental updates must not change anything
+        let evaluator = PrincipalVariationSearch::new(3);
+        let nnue: Arc<dyn ScoreFunction> = Arc::new(NnueScore::default());
+        let pawn_difference: Arc<dyn ScoreFunction> = Arc::new(PawnDifferenceScore::default());
+
+        for fen in PVS_TEST_POSITIONS {
+            let board = Board::from_fen(fen, false).unwrap();
+
+            assert_eq!(
+                evaluator.eval_moves(board.clone(), nnue.clone()),
+                evaluator.eval_moves(board, pawn_difference.clone()),
+                "Evals differ for {}",
+                fen
+            );
+        }
+    }
+
+    #[test]
+    fn test_parallel_search_vs_serial() {
+        let score_fn: Arc<dyn ScoreFunction> = Arc::new(PawnDifferenceScore::default());
+
+        let serial: Vec<Box<dyn Evaluator>> = vec![
+            Box::new(AlphaBetaNegamax::new(3)),
+            Box::new(PrincipalVariationSearch::new(3)),
+        ];
+        let parallel: Vec<Box<dyn Evaluator>> = vec![
+            Box::new(AlphaBetaNegamax::new(3).with_threads(4)),
+            Box::new(PrincipalVariationSearch::new(3).with_threads(4)),
+        ];
+
+        for fen in PVS_TEST_POSITIONS {
+            let board = Board::from_fen(fen, false).unwrap();
+
+            for (serial, parallel) in serial.iter().zip(&parallel) {
+                let expected_eval = serial.eval_moves(board.clone(), score_fn.clone());
+                let eval = parallel.eval_moves(board.clone(), score_fn.clone());
+
+                assert!(eval.iter().all(|(mov, _)| board.is_legal(*mov)));
+                assert_eq!(eval, expected_eval, "Evals differ for {}", fen);
+            }
+        }
+    }
+
+    fn limited_evaluators(depth: usize, node_limit: Option<usize>) -> Vec<Box<dyn Evaluator>> {
+        vec![
+            Box::new(Negamax::new(depth).with_node_limit(node_limit)),
+            Box::new(AlphaBetaNegamax::new(depth).with_node_limit(node_limit)),
+            Box::new(Negascout::new(depth).with_node_limit(node_limit)),
+            Box::new(PrincipalVariationSearch::new(depth).with_node_limit(node_limit)),
+        ]
+    }
+
+    #[test]
+    fn test_node_limit_keeps_finished_depth() {
+        let score_fn: Arc<dyn ScoreFunction> = Arc::new(PawnDifferenceScore::default());
+        let board = Board::from_fen(PVS_TEST_POSITIONS[1], false).unwrap();
+
+        let shallow = limited_evaluators(1, None);
+        let starved = limited_evaluators(4, Some(1));
+        let generous = limited_evaluators(3, Some(usize::MAX));
+        let unlimited = limited_evaluators(3, None);
+
+        for i in 0..shallow.len() {
+            // too few nodes for anything past the first depth
+            assert_eq!(
+                starved[i].eval_moves(board.clone(), score_fn.clone()),
+                shallow[i].eval_moves(board.clone(), score_fn.clone())
+            );
+
+            // a budget that is never reached changes nothing
+            assert_eq!(
+                generous[i].eval_moves(board.clone(), score_fn.clone()),
+                unlimited[i].eval_moves(board.clone(), score_fn.clone())
+            );
+        }
+    }
+
+    #[test]
+    fn test_stopped_search_keeps_first_depth() {
+        let score_fn: Arc<dyn ScoreFunction> = Arc::new(PawnDifferenceScore::default());
+        let board = Board::from_fen(PVS_TEST_POSITIONS[1], false).unwrap();
+
+        let context = SearchContext::new();
+        context.stop();
+
+        let shallow = limited_evaluators(1, None);
+
+        for (i, evaluator) in limited_evaluators(4, None).iter().enumerate() {
+            assert_eq!(
+                evaluator.search(board.clone(), score_fn.clone(), &context),
+                shallow[i].eval_moves(board.clone(), score_fn.clone())
+            );
+        }
+    }
+
+    #[test]
+    fn test_search_reports_progress() {
+        let score_fn: Arc<dyn ScoreFunction> = Arc::new(PawnDifferenceScore::default());
+        let board = Board::from_fen(PVS_TEST_POSITIONS[3], false).unwrap();
+
+        for evaluator in limited_evaluators(3, None) {
+            let reports = Arc::new(std::sync::Mutex::new(vec![]));
+            let on_progress = {
+                let reports = reports.clone();
+                Arc::new(move |progress: &Progress| reports.lock().unwrap().push(*progress))
+            };
+
+            let context = SearchContext::new().with_progress(on_progress);
+            let eval = evaluator.search(board.clone(), score_fn.clone(), &context);
+
+            let reports = reports.lock().unwrap();
+            let depths = reports
+                .iter()
+                .map(|progress| progress.depth)
+                .collect::<Vec<_>>();
+            assert_eq!(depths, vec![1, 2, 3]);
+
+            // the last report is the move a greedy player would pick
+            let last = reports.last().unwrap();
+            assert_eq!(last.best_move, "d1d8".parse().unwrap());
+            assert_eq!(
+                Some(&(last.best_move, last.score)),
+                eval.iter().max_by_key(|(_, score)| *score)
+            );
+            assert!(reports
+                .windows(2)
+                .all(|pair| pair[0].nodes <= pair[1].nodes));
+        }
+    }
+
+    #[test]
+    fn test_node_limit_is_deterministic() {
+        let score_fn: Arc<dyn ScoreFunction> = Arc::new(PawnDifferenceScore::default());
+        let board = Board::from_fen(PVS_TEST_POSITIONS[2], false).unwrap();
+
+        for (i, evaluator) in limited_evaluators(4, Some(5_000)).iter().enumerate() {
+            let eval = evaluator.eval_moves(board.clone(), score_fn.clone());
+            assert_eq!(eval, evaluator.eval_moves(board.clone(), score_fn.clone()));
+
+            // the result is a whole search at some depth, never a mix
+            let matches_a_depth = (1..=4).any(|depth| {
+                eval == limited_evaluators(depth, None)[i]
+                    .eval_moves(board.clone(), score_fn.clone())
+            });
+            assert!(matches_a_depth);
+        }
+    }
 }
@@ -0,0 +1,347 @@
+use super::{
+    super::score::{Score, ScoreFunction},
+    Evaluator, SearchContext,
+};
+use cozy_chess::{Board, Color, GameStatus, Move};
+use rand::{seq::SliceRandom, Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use std::{sync::Arc, time::Duration};
+
+/// Monte Carlo tree search with UCT selection.
+///
+/// Each iteration walks down the tree, adds one new node and plays a short
+/// playout from it, scoring the final position with the score function. The
+/// score given to each root move comes from how often it was visited, on a
+/// log scale, so a `SoftmaxChooser` picks moves in proportion to their visits
+/// raised to `1 / temperature` and a `GreedyChooser` takes the most visited.
+#[derive(Clone)]
+pub struct MonteCarloTreeSearch {
+    iterations: usize,
+    time_limit: Option<Duration>,
+    exploration: f64,
+    playout_depth: usize,
+    guided_playouts: bool,
+    seed: u64,
+}
+
+impl Default for MonteCarloTreeSearch {
+    fn default() -> Self {
+        Self::new(1_000)
+    }
+}
+
+impl MonteCarloTreeSearch {
+    // centipawns per factor of e in the visit count
+    const VISIT_SCALE: f64 = 100.0;
+    // iterations between progress reports
+    const REPORT_INTERVAL: usize = 1024;
+
+    pub fn new(iterations: usize) -> Self {
+        Self {
+            iterations,
+            time_limit: None,
+            exploration: 2f64.sqrt(),
+            playout_depth: 8,
+            guided_playouts: false,
+            seed: 0,
+        }
+    }
+
+    // stops after this long even if there are iterations left, which makes
+    // the result depend on the speed of the machine
+    pub fn with_time_limit(mut self, time_limit: Option<Duration>) -> Self {
+        self.time_limit = time_limit;
+        self
+    }
+
+    pub fn with_exploration(mut self, exploration: f64) -> Self {
+        self.exploration = exploration;
+        self
+    }
+
+    pub fn with_playout_depth(mut self, playout_depth: usize) -> Self {
+        self.playout_depth = playout_depth;
+        self
+    }
+
+    // guided playouts take the move the score function likes best instead of
+    // a random one
+    pub fn with_guided_playouts(mut self, guided_playouts: bool) -> Self {
+        self.guided_playouts = guided_playouts;
+        self
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    fn select(&self, tree: &[Node], node: &Node) -> usize {
+        let parent_visits = (node.visits as f64).ln();
+
+        let uct = |child: &Node| {
+            child.value / child.visits as f64
+                + self.exploration * (parent_visits / child.visits as f64).sqrt()
+        };
+
+        *node
+            .children
+            .iter()
+            .max_by(|a, b| uct(&tree[**a]).total_cmp(&uct(&tree[**b])))
+            .expect("should be children here")
+    }
+
+    // white's chance of winning from the end of a playout starting at `board`
+    fn playout(
+        &self,
+        mut board: Board,
+        score_fn: &Arc<dyn ScoreFunction>,
+        rng: &mut ChaCha8Rng,
+    ) -> f64 {
+        for _ in 0..self.playout_depth {
+            if board.status() != GameStatus::Ongoing {
+                break;
+            }
+
+            let moves = legal_moves(&board);
+            let mov = match self.guided_playouts {
+                true => best_move(&board, &moves, score_fn),
+                false => *moves.choose(rng).expect("should be moves here"),
+            };
+            board.play(mov);
+        }
+
+        match board.status() {
+            GameStatus::Won => match board.side_to_move() {
+                Color::White => 0.0,
+                Color::Black => 1.0,
+            },
+            GameStatus::Drawn => 0.5,
+            GameStatus::Ongoing => win_chance(score_fn.score(&board)),
+        }
+    }
+
+    fn root_scores(tree: &[Node]) -> Vec<(Move, Score)> {
+        let root = &tree[0];
+        let mut scores = root
+            .children
+            .iter()
+            .map(|child| {
+                let child = &tree[*child];
+                let score = Self::VISIT_SCALE * (child.visits.max(1) as f64).ln();
+
+                (
+                    child.mov.expect("only the root has no move"),
+                    Score::centipawns(score.round() as i32),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        // moves the search never reached rank below all the others
+        scores.extend(
+            root.untried
+                .iter()
+                .map(|mov| (*mov, Score::centipawns(-Self::VISIT_SCALE as i32))),
+        );
+
+        scores
+    }
+}
+
+impl Evaluator for MonteCarloTreeSearch {
+    // the context's node limit caps the number of iterations, and progress
+    // reports use the deepest line in the tree as the depth
+    fn search(
+        &self,
+        board: Board,
+        score_fn: Arc<dyn ScoreFunction>,
+        context: &SearchContext,
+    ) -> Vec<(Move, Score)> {
+        let mut rng = ChaCha8Rng::seed_from_u64(self.seed);
+        let mut tree = vec![Node::new(None, &board)];
+        let mut depth = 0;
+        let start = now();
+
+        let iterations = match context.node_limit() {
+            Some(limit) => self.iterations.min(limit),
+            None => self.iterations,
+        };
+
+        for iteration in 0..iterations {
+            if context.is_stopped()
+                || matches!(self.time_limit, Some(limit) if now().saturating_sub(start) >= limit)
+            {
+                break;
+            }
+
+            if iteration > 0 && iteration % Self::REPORT_INTERVAL == 0 {
+                context.report(depth, iteration, Self::root_scores(&tree));
+            }
+
+            let mut board = board.clone();
+            let mut path = vec![0];
+
+            // selection, following the best child until a node has moves that
+            // haven't been tried yet
+            let mut node = 0;
+            while tree[node].untried.is_empty() && !tree[node].children.is_empty() {
+                node = self.select(&tree, &tree[node]);
+                board.play(tree[node].mov.expect("only the root has no move"));
+                path.push(node);
+            }
+
+            // expansion
+            if !tree[node].untried.is_empty() {
+                let i = rng.gen_range(0..tree[node].untried.len());
+                let mov = tree[node].untried.swap_remove(i);
+                board.play(mov);
+
+                tree.push(Node::new(Some(mov), &board));
+                let child = tree.len() - 1;
+                tree[node].children.push(child);
+                path.push(child);
+            }
+
+            depth = depth.max(path.len() - 1);
+            let white_wins = self.playout(board, &score_fn, &mut rng);
+
+            // each node keeps the result for the side that moved into it
+            for node in path {
+                let node = &mut tree[node];
+                node.visits += 1;
+                node.value += match node.mover {
+                    Color::White => white_wins,
+                    Color::Black => 1.0 - white_wins,
+                };
+            }
+        }
+
+        let scores = Self::root_scores(&tree);
+        context.report(depth, tree[0].visits as usize, scores.iter().copied());
+
+        scores
+    }
+
+    // scores come from visit counts, so they can't say how much a move loses
+    fn scores_centipawns(&self) -> bool {
+        false
+    }
+}
+
+struct Node {
+    mov: Option<Move>,
+    mover: Color,
+    children: Vec<usize>,
+    untried: Vec<Move>,
+    visits: u32,
+    value: f64,
+}
+
+impl Node {
+    fn new(mov: Option<Move>, board: &Board) -> Self {
+        let untried = match board.status() {
+            GameStatus::Ongoing => legal_moves(board),
+            _ => vec![],
+        };
+
+        Self {
+            mov,
+            mover: !board.side_to_move(),
+            children: vec![],
+            untried,
+            visits: 0,
+            value: 0.0,
+        }
+    }
+}
+
+fn legal_moves(board: &Board) -> Vec<Move> {
+    let mut moves = Vec::new();
+    board.generate_moves(|piece_moves| {
+        moves.extend(piece_moves);
+        false
+    });
+    moves
+}
+
+fn best_move(board: &Board, moves: &[Move], score_fn: &Arc<dyn ScoreFunction>) -> Move {
+    let side = board.side_to_move();
+
+    *moves
+        .iter()
+        .max_by_key(|mov| {
+            let mut temp_board = board.clone();
+            temp_board.play(**mov);
+
+            match side {
+                Color::White => score_fn.score(&temp_board),
+                Color::Black => -score_fn.score(&temp_board),
+            }
+        })
+        .expect("should be moves here")
+}
+
+// logistic curve where being 400 centipawns up is about a 90% chance to win
+fn win_chance(score: Score) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-score.as_centipawns() as f64 / 400.0))
+}
+
+// wasm has no system clock in std
+#[cfg(target_arch = "wasm32")]
+fn now() -> Duration {
+    Duration::from_secs_f64(js_sys::Date::now() / 1000.0)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn now() -> Duration {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::algorithm::score::PawnDifferenceScore;
+
+    #[test]
+    fn test_mcts_scores_every_legal_move() {
+        let score_fn: Arc<dyn ScoreFunction> = Arc::new(PawnDifferenceScore::default());
+        let board = Board::startpos();
+
+        // too few iterations to try every move
+        let eval = MonteCarloTreeSearch::new(5).eval_moves(board.clone(), score_fn.clone());
+        assert_eq!(eval.len(), 20);
+
+        let eval = MonteCarloTreeSearch::new(200).eval_moves(board.clone(), score_fn.clone());
+        let mut moves = eval.iter().map(|(mov, _)| *mov).collect::<Vec<_>>();
+        let mut expected = legal_moves(&board);
+        moves.sort_by_key(|mov| mov.to_string());
+        expected.sort_by_key(|mov| mov.to_string());
+        assert_eq!(moves, expected);
+
+        // the same seed gives the same search
+        assert_eq!(
+            eval,
+            MonteCarloTreeSearch::new(200).eval_moves(board, score_fn)
+        );
+    }
+
+    #[test]
+    fn test_mcts_finds_free_queen() {
+        let score_fn: Arc<dyn ScoreFunction> = Arc::new(PawnDifferenceScore::default());
+
+        // the black queen is hanging to the rook
+        let board = Board::from_fen("4k3/8/8/3q4/8/8/8/3RK3 w - - 0 1", false).unwrap();
+
+        for evaluator in [
+            MonteCarloTreeSearch::new(2_000),
+            MonteCarloTreeSearch::new(500).with_guided_playouts(true),
+        ] {
+            let eval = evaluator.eval_moves(board.clone(), score_fn.clone());
+            let (mov, _) = eval.into_iter().max_by_key(|(_, score)| *score).unwrap();
+
+            assert_eq!(mov, "d1d5".parse().unwrap());
+        }
+    }
+}
@@ -0,0 +1,146 @@
+use cozy_chess::{Board, BoardBuilder, Color, File, Piece, Rank, Square};
+use std::fmt;
+
+/// Material the stronger side gives up before the game starts.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Handicap {
+    #[default]
+    None,
+    /// The f-pawn, and the weaker side moves first.
+    PawnAndMove,
+    /// The queenside knight.
+    Knight,
+    /// The queenside rook, along with castling on that side.
+    Rook,
+    Queen,
+}
+
+impl Handicap {
+    pub const ALL: [Handicap; 5] = [
+        Handicap::None,
+        Handicap::PawnAndMove,
+        Handicap::Knight,
+        Handicap::Rook,
+        Handicap::Queen,
+    ];
+
+    /// `start` with the odds taken out of `giver`'s army. Pieces on the
+    /// queenside go first, so this works for Chess960 starts too.
+    pub fn apply(&self, start: &Board, giver: Color) -> Board {
+        let mut builder = BoardBuilder::from_board(start);
+        let queenside = |piece| start.colored_pieces(giver, piece).into_iter().next();
+
+        let removed = match self {
+            Handicap::None => None,
+            Handicap::PawnAndMove => {
+                builder.side_to_move = !giver;
+                Some(Square::new(File::F, Rank::Second.relative_to(giver)))
+                    .filter(|square| start.colored_pieces(giver, Piece::Pawn).has(*square))
+            }
+            Handicap::Knight => queenside(Piece::Knight),
+            Handicap::Rook => {
+                let rights = builder.castle_rights_mut(giver);
+                let rook = rights
+                    .long
+                    .take()
+                    .map(|file| Square::new(file, Rank::First.relative_to(giver)));
+                rook.or_else(|| queenside(Piece::Rook))
+            }
+            Handicap::Queen => queenside(Piece::Queen),
+        };
+
+        if let Some(square) = removed {
+            *builder.square_mut(square) = None;
+        }
+
+        builder
+            .build()
+            .expect("taking away a piece should leave a valid position")
+    }
+}
+
+impl fmt::Display for Handicap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Handicap::None => write!(f, "No odds"),
+            Handicap::PawnAndMove => write!(f, "Pawn and move"),
+            Handicap::Knight => write!(f, "Knight odds"),
+            Handicap::Rook => write!(f, "Rook odds"),
+            Handicap::Queen => write!(f, "Queen odds"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::algorithm::variant::Variant;
+
+    #[test]
+    fn test_handicaps() {
+        let start = Board::startpos();
+
+        for (handicap, giver, fen) in [
+            (
+                Handicap::None,
+                Color::White,
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            ),
+            (
+                Handicap::PawnAndMove,
+                Color::White,
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPP1PP/RNBQKBNR b KQkq - 0 1",
+            ),
+            (
+                Handicap::PawnAndMove,
+                Color::Black,
+                "rnbqkbnr/ppppp1pp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            ),
+            (
+                Handicap::Knight,
+                Color::Black,
+                "r1bqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            ),
+            (
+                Handicap::Rook,
+                Color::White,
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/1NBQKBNR w Kkq - 0 1",
+            ),
+            (
+                Handicap::Queen,
+                Color::Black,
+                "rnb1kbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            ),
+        ] {
+            assert_eq!(
+                handicap.apply(&start, giver).to_string(),
+                fen,
+                "{} for {}",
+                handicap,
+                giver
+            );
+        }
+    }
+
+    #[test]
+    fn test_chess960_handicaps() {
+        // rooks on f1 and h1 with the king between them on g1
+        let start = Variant::Chess960(0).start();
+        let board = Handicap::Rook.apply(&start, Color::White);
+
+        assert_eq!(
+            format!("{:#}", board),
+            "bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNN1KR w Hhf - 0 1"
+        );
+
+        // every odds game still has moves to play
+        for number in 0..Variant::CHESS960_POSITIONS {
+            let start = Variant::Chess960(number).start();
+
+            for handicap in Handicap::ALL {
+                let board = handicap.apply(&start, Color::Black);
+                assert!(board.generate_moves(|_| true), "{} in {}", handicap, number);
+            }
+        }
+    }
+}
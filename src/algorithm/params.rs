@@ -0,0 +1,174 @@
+use cozy_chess::Piece;
+use serde::{Deserialize, Serialize};
+
+/// Weights used by the score functions, in centipawns. The positional terms
+/// are counted per occurrence, and negative weights act as penalties.
+///
+/// Missing fields fall back to their defaults when loading, so parameter
+/// files saved by older versions keep working as new terms are added.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EvalParams {
+    pub pawn: i32,
+    pub knight: i32,
+    pub bishop: i32,
+    pub rook: i32,
+    pub queen: i32,
+    // per legal move
+    pub mobility: i32,
+    pub doubled_pawn: i32,
+    pub isolated_pawn: i32,
+    pub passed_pawn: i32,
+    // per pawn in front of the king
+    pub king_shelter: i32,
+    // per enemy piece attacking the squares around the king
+    pub king_attacker: i32,
+    pub bishop_pair: i32,
+    pub rook_open_file: i32,
+    pub rook_half_open_file: i32,
+}
+
+impl Default for EvalParams {
+    fn default() -> Self {
+        Self {
+            pawn: 100,
+            knight: 300,
+            bishop: 350,
+            rook: 500,
+            queen: 900,
+            mobility: 5,
+            doubled_pawn: -15,
+            isolated_pawn: -10,
+            passed_pawn: 20,
+            king_shelter: 10,
+            king_attacker: -10,
+            bishop_pair: 30,
+            rook_open_file: 20,
+            rook_half_open_file: 10,
+        }
+    }
+}
+
+impl EvalParams {
+    pub fn piece_value(&self, piece: Piece) -> i32 {
+        match piece {
+            Piece::Pawn => self.pawn,
+            Piece::Knight => self.knight,
+            Piece::Bishop => self.bishop,
+            Piece::Rook => self.rook,
+            Piece::Queen => self.queen,
+            Piece::King => 0,
+        }
+    }
+
+    pub fn set_piece_value(&mut self, piece: Piece, value: i32) {
+        match piece {
+            Piece::Pawn => self.pawn = value,
+            Piece::Knight => self.knight = value,
+            Piece::Bishop => self.bishop = value,
+            Piece::Rook => self.rook = value,
+            Piece::Queen => self.queen = value,
+            Piece::King => {}
+        }
+    }
+
+    /// Named handles to every weight, in a fixed order, for the tuner.
+    pub fn weights_mut(&mut self) -> Vec<(&'static str, &mut i32)> {
+        vec![
+            ("pawn", &mut self.pawn),
+            ("knight", &mut self.knight),
+            ("bishop", &mut self.bishop),
+            ("rook", &mut self.rook),
+            ("queen", &mut self.queen),
+            ("mobility", &mut self.mobility),
+            ("doubled_pawn", &mut self.doubled_pawn),
+            ("isolated_pawn", &mut self.isolated_pawn),
+            ("passed_pawn", &mut self.passed_pawn),
+            ("king_shelter", &mut self.king_shelter),
+            ("king_attacker", &mut self.king_attacker),
+            ("bishop_pair", &mut self.bishop_pair),
+            ("rook_open_file", &mut self.rook_open_file),
+            ("rook_half_open_file", &mut self.rook_half_open_file),
+        ]
+    }
+
+    pub fn weight_names() -> Vec<&'static str> {
+        Self::default()
+            .weights_mut()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect()
+    }
+
+    pub fn weight(&self, name: &str) -> Option<i32> {
+        self.clone()
+            .weights_mut()
+            .into_iter()
+            .find(|(weight_name, _)| *weight_name == name)
+            .map(|(_, weight)| *weight)
+    }
+
+    /// Sets the named weight, returning false if there is no such weight.
+    pub fn set_weight(&mut self, name: &str, value: i32) -> bool {
+        match self
+            .weights_mut()
+            .into_iter()
+            .find(|(weight_name, _)| *weight_name == name)
+        {
+            Some((_, weight)) => {
+                *weight = value;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("params should serialise")
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_params_json_round_trip() {
+        let mut params = EvalParams::default();
+        params.set_piece_value(Piece::Bishop, 325);
+        params.set_piece_value(Piece::King, 10_000);
+
+        let loaded = EvalParams::from_json(&params.to_json()).unwrap();
+
+        assert_eq!(loaded, params);
+        assert_eq!(loaded.piece_value(Piece::Bishop), 325);
+        assert_eq!(loaded.piece_value(Piece::King), 0);
+    }
+
+    #[test]
+    fn test_params_partial_json() {
+        let params = EvalParams::from_json(r#"{ "queen": 950 }"#).unwrap();
+
+        assert_eq!(params.queen, 950);
+        assert_eq!(params.rook, EvalParams::default().rook);
+
+        assert!(EvalParams::from_json(r#"{ "queen": "lots" }"#).is_err());
+    }
+
+    #[test]
+    fn test_params_named_weights() {
+        let mut params = EvalParams::default();
+
+        assert!(params.set_weight("bishop_pair", 45));
+        assert!(!params.set_weight("castling", 45));
+
+        assert_eq!(params.bishop_pair, 45);
+        assert_eq!(params.weight("bishop_pair"), Some(45));
+        assert_eq!(params.weight("knight"), Some(300));
+        assert_eq!(params.weight("castling"), None);
+    }
+}
@@ -0,0 +1,228 @@
+use super::eval::get_sorted_moves;
+use cozy_chess::{Board, Move};
+use std::fmt;
+
+/// A position where the search's move ordering doesn't give back exactly the
+/// legal moves.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MoveOrderError {
+    pub fen: String,
+    pub missing: Vec<Move>,
+    pub extra: Vec<Move>,
+    pub duplicated: Vec<Move>,
+}
+
+impl fmt::Display for MoveOrderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let list = |moves: &[Move]| {
+            moves
+                .iter()
+                .map(|mov| mov.to_string())
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+
+        write!(
+            f,
+            "sorted moves differ in {}: missing [{}], extra [{}], duplicated [{}]",
+            self.fen,
+            list(&self.missing),
+            list(&self.extra),
+            list(&self.duplicated)
+        )
+    }
+}
+
+pub fn legal_moves(board: &Board) -> Vec<Move> {
+    let mut moves = Vec::new();
+    board.generate_moves(|piece_moves| {
+        moves.extend(piece_moves);
+        false
+    });
+    moves
+}
+
+/// Number of leaf nodes `depth` plies below `board`.
+pub fn perft(board: &Board, depth: usize) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+
+    let moves = legal_moves(board);
+
+    // the last ply only needs counting
+    if depth == 1 {
+        return moves.len() as u64;
+    }
+
+    moves
+        .into_iter()
+        .map(|mov| {
+            let mut board = board.clone();
+            board.play(mov);
+            perft(&board, depth - 1)
+        })
+        .sum()
+}
+
+/// Perft count below each legal move, for narrowing down a wrong total.
+pub fn divide(board: &Board, depth: usize) -> Vec<(Move, u64)> {
+    legal_moves(board)
+        .into_iter()
+        .map(|mov| {
+            let mut child = board.clone();
+            child.play(mov);
+            (mov, perft(&child, depth.saturating_sub(1)))
+        })
+        .collect()
+}
+
+/// Perft that walks the tree with the search's move ordering, checking at
+/// every node that it produces each legal move exactly once.
+pub fn sorted_perft(board: &Board, depth: usize) -> Result<u64, MoveOrderError> {
+    if depth == 0 {
+        return Ok(1);
+    }
+
+    let sorted = get_sorted_moves(board, &board.side_to_move());
+    check_sorted_moves(board, &sorted)?;
+
+    let mut nodes = 0;
+    for mov in sorted {
+        let mut board = board.clone();
+        board.play(mov);
+        nodes += sorted_perft(&board, depth - 1)?;
+    }
+
+    Ok(nodes)
+}
+
+fn check_sorted_moves(board: &Board, sorted: &[Move]) -> Result<(), MoveOrderError> {
+    let legal = legal_moves(board);
+
+    let missing = legal
+        .iter()
+        .filter(|mov| !sorted.contains(mov))
+        .copied()
+        .collect::<Vec<_>>();
+    let extra = sorted
+        .iter()
+        .filter(|mov| !legal.contains(mov))
+        .copied()
+        .collect::<Vec<_>>();
+    let mut duplicated = sorted
+        .iter()
+        .enumerate()
+        .filter(|(i, mov)| sorted[..*i].contains(mov))
+        .map(|(_, mov)| *mov)
+        .collect::<Vec<_>>();
+    duplicated.dedup();
+
+    match missing.is_empty() && extra.is_empty() && duplicated.is_empty() {
+        true => Ok(()),
+        false => Err(MoveOrderError {
+            fen: board.to_string(),
+            missing,
+            extra,
+            duplicated,
+        }),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // standard positions with known counts, from the chessprogramming wiki
+    const PERFT_POSITIONS: [(&str, &[u64]); 5] = [
+        (
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            &[20, 400, 8902],
+        ),
+        // kiwipete
+        (
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            &[48, 2039, 97862],
+        ),
+        // en passant that would uncover a check along the rank
+        (
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            &[14, 191, 2812, 43238],
+        ),
+        // promotions and castling out of a position with checks
+        (
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            &[6, 264, 9467],
+        ),
+        (
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+            &[44, 1486, 62379],
+        ),
+    ];
+
+    #[test]
+    fn test_perft() {
+        for (fen, counts) in PERFT_POSITIONS {
+            let board = Board::from_fen(fen, false).unwrap();
+
+            for (depth, count) in counts.iter().enumerate() {
+                assert_eq!(
+                    perft(&board, depth + 1),
+                    *count,
+                    "{} at depth {}",
+                    fen,
+                    depth + 1
+                );
+            }
+
+            let depth = counts.len();
+            let divided = divide(&board, depth).iter().map(|(_, n)| n).sum::<u64>();
+            assert_eq!(divided, counts[depth - 1]);
+        }
+    }
+
+    #[test]
+    fn test_sorted_perft() {
+        for (fen, counts) in PERFT_POSITIONS {
+            let board = Board::from_fen(fen, false).unwrap();
+            let depth = counts.len().min(3);
+
+            assert_eq!(
+                sorted_perft(&board, depth),
+                Ok(counts[depth - 1]),
+                "{}",
+                fen
+            );
+        }
+    }
+
+    #[test]
+    fn test_sorted_moves_edge_cases() {
+        let sorted = |fen: &str| {
+            let board = Board::from_fen(fen, false).unwrap();
+            assert_eq!(sorted_perft(&board, 2).map(|_| ()), Ok(()), "{}", fen);
+            get_sorted_moves(&board, &board.side_to_move())
+        };
+
+        // can't castle kingside through the attacked f1, queenside is fine
+        let moves = sorted("2k2r2/8/8/8/8/8/8/R3K2R w KQ - 0 1");
+        assert!(!moves.contains(&"e1h1".parse().unwrap()));
+        assert!(moves.contains(&"e1a1".parse().unwrap()));
+
+        // nor out of check
+        let moves = sorted("2k1r3/8/8/8/8/8/8/R3K2R w KQ - 0 1");
+        assert!(!moves.contains(&"e1h1".parse().unwrap()));
+        assert!(!moves.contains(&"e1a1".parse().unwrap()));
+
+        // taking en passant would leave the king in check along the rank
+        let moves = sorted("8/8/8/KPp4r/8/8/8/7k w - c6 0 1");
+        assert!(!moves.contains(&"b5c6".parse().unwrap()));
+
+        // but here it takes the pawn that is giving check
+        let moves = sorted("8/8/8/1Pp5/1K6/8/8/7k w - c6 0 1");
+        assert!(moves.contains(&"b5c6".parse().unwrap()));
+
+        // checks and captures at once, including promotions that capture
+        sorted("1r2k3/2P5/8/8/8/8/8/4K2R w K - 0 1");
+    }
+}
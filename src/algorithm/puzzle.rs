@@ -0,0 +1,329 @@
+use cozy_chess::{Board, Color, GameStatus, Move};
+use rand::{seq::SliceRandom, Rng};
+use serde::{Deserialize, Serialize};
+
+// tactics shipped with the app
+pub const BUNDLED_PUZZLES: &str = include_str!("puzzle/puzzles.csv");
+
+/// Tactic to solve, in the same layout as the Lichess puzzle database: the
+/// position before the opponent's move, then the solution in UCI starting
+/// with that move, so the player's moves are every second one after it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Puzzle {
+    pub id: String,
+    pub start: Board,
+    pub solution: Vec<Move>,
+    pub rating: i32,
+    pub themes: Vec<String>,
+}
+
+impl Puzzle {
+    /// Parses a `PuzzleId,FEN,Moves,Rating,Themes` line. Lines with illegal
+    /// moves, or that don't end on a move of the player's, are rejected.
+    pub fn parse(line: &str) -> Option<Self> {
+        let fields = line.split(',').map(str::trim).collect::<Vec<_>>();
+        if fields.len() < 4 {
+            return None;
+        }
+
+        let start = Board::from_fen(fields[1], false).ok()?;
+        let solution = fields[2]
+            .split_whitespace()
+            .map(|mov| mov.parse().ok())
+            .collect::<Option<Vec<Move>>>()?;
+
+        let mut board = start.clone();
+        for mov in &solution {
+            board.try_play(*mov).ok()?;
+        }
+
+        // the opponent moved first, so the player's last move hands the
+        // turn back to the side that started
+        if solution.is_empty() || board.side_to_move() != start.side_to_move() {
+            return None;
+        }
+
+        Some(Self {
+            id: fields[0].to_string(),
+            start,
+            solution,
+            rating: fields[3].parse().ok()?,
+            themes: fields
+                .get(4)
+                .map(|themes| themes.split_whitespace().map(String::from).collect())
+                .unwrap_or_default(),
+        })
+    }
+
+    // the opponent moves first, so the player has the other colour
+    pub fn player(&self) -> Color {
+        !self.start.side_to_move()
+    }
+}
+
+/// Every valid puzzle in a CSV file, skipping the header.
+pub fn parse_puzzles(csv: &str) -> Vec<Puzzle> {
+    csv.lines().filter_map(Puzzle::parse).collect()
+}
+
+/// Picks one of the few puzzles rated closest to `rating`, so a run of
+/// puzzles doesn't always repeat in the same order.
+pub fn pick_puzzle<'a>(
+    puzzles: &'a [Puzzle],
+    rating: i32,
+    rng: &mut impl Rng,
+) -> Option<&'a Puzzle> {
+    const CHOICES: usize = 3;
+
+    let mut nearest = puzzles.iter().collect::<Vec<_>>();
+    nearest.sort_by_key(|puzzle| (puzzle.rating - rating).abs());
+    nearest.truncate(CHOICES);
+
+    nearest.choose(rng).copied()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PuzzleStep {
+    Correct,
+    Solved,
+    Wrong,
+}
+
+/// A puzzle being played through.
+#[derive(Clone, Debug)]
+pub struct PuzzleAttempt {
+    pub puzzle: Puzzle,
+    played: usize,
+    failed: bool,
+    last_step: Option<PuzzleStep>,
+}
+
+impl PuzzleAttempt {
+    pub fn new(puzzle: Puzzle) -> Self {
+        Self {
+            puzzle,
+            played: 0,
+            failed: false,
+            last_step: None,
+        }
+    }
+
+    pub fn board(&self) -> Board {
+        let mut board = self.puzzle.start.clone();
+        for mov in &self.puzzle.solution[..self.played] {
+            board.play(*mov);
+        }
+        board
+    }
+
+    /// The opponent's next move, played by the computer. Only the solution's
+    /// replies are played, so this is `None` on the player's turn.
+    pub fn reply(&mut self) -> Option<Move> {
+        if self.players_turn() || self.is_solved() {
+            return None;
+        }
+
+        self.played += 1;
+        Some(self.puzzle.solution[self.played - 1])
+    }
+
+    /// Checks the player's move. Wrong moves aren't played, so the player can
+    /// try again, but the puzzle counts as failed. Any mate is accepted as the
+    /// last move, as there can be more than one.
+    pub fn try_move(&mut self, mov: Move) -> PuzzleStep {
+        let expected = self.puzzle.solution.get(self.played).copied();
+        let last = self.played + 1 == self.puzzle.solution.len();
+
+        let mut board = self.board();
+        let mates = board.try_play(mov).is_ok() && board.status() == GameStatus::Won;
+
+        let step = if !self.players_turn() || expected.is_none() {
+            PuzzleStep::Wrong
+        } else if Some(mov) == expected || (last && mates) {
+            self.played += 1;
+            match last {
+                true => PuzzleStep::Solved,
+                false => PuzzleStep::Correct,
+            }
+        } else {
+            self.failed = true;
+            PuzzleStep::Wrong
+        };
+
+        self.last_step = Some(step);
+        step
+    }
+
+    fn players_turn(&self) -> bool {
+        self.board().side_to_move() == self.puzzle.player()
+    }
+
+    pub fn failed(&self) -> bool {
+        self.failed
+    }
+
+    pub fn last_step(&self) -> Option<PuzzleStep> {
+        self.last_step
+    }
+
+    pub fn is_solved(&self) -> bool {
+        self.played == self.puzzle.solution.len()
+    }
+}
+
+/// The player's puzzle rating and streak, kept between visits.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PuzzleStats {
+    pub rating: i32,
+    pub streak: u32,
+    pub best_streak: u32,
+}
+
+impl Default for PuzzleStats {
+    fn default() -> Self {
+        Self {
+            rating: 1000,
+            streak: 0,
+            best_streak: 0,
+        }
+    }
+}
+
+impl PuzzleStats {
+    // rating points at stake on each puzzle
+    const K_FACTOR: f64 = 32.0;
+
+    /// Elo update against the puzzle's rating, counting the puzzle as the
+    /// opponent.
+    pub fn record(&mut self, puzzle_rating: i32, solved: bool) {
+        let expected = 1.0 / (1.0 + 10f64.powf((puzzle_rating - self.rating) as f64 / 400.0));
+        let result = match solved {
+            true => 1.0,
+            false => 0.0,
+        };
+        self.rating += (Self::K_FACTOR * (result - expected)).round() as i32;
+
+        self.streak = match solved {
+            true => self.streak + 1,
+            false => 0,
+        };
+        self.best_streak = self.best_streak.max(self.streak);
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("stats should serialise")
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    #[test]
+    fn test_bundled_puzzles() {
+        let puzzles = parse_puzzles(BUNDLED_PUZZLES);
+
+        // everything but the header parses
+        assert_eq!(puzzles.len(), BUNDLED_PUZZLES.lines().count() - 1);
+
+        for puzzle in puzzles {
+            let mut attempt = PuzzleAttempt::new(puzzle.clone());
+
+            while let Some(reply) = attempt.reply() {
+                let mov = puzzle.solution[attempt.played];
+                assert_eq!(attempt.board().side_to_move(), puzzle.player());
+                assert_ne!(reply, mov);
+                assert_ne!(attempt.try_move(mov), PuzzleStep::Wrong, "{}", puzzle.id);
+            }
+
+            assert!(attempt.is_solved(), "{}", puzzle.id);
+
+            // the mates really are mate
+            if puzzle.themes.iter().any(|theme| theme.starts_with("mate")) {
+                assert_eq!(attempt.board().status(), GameStatus::Won, "{}", puzzle.id);
+            }
+        }
+    }
+
+    #[test]
+    fn test_puzzle_attempt() {
+        let puzzle = Puzzle::parse(
+            "ladder,7k/8/8/8/8/8/R7/1R4K1 b - - 0 1,h8g8 b1b7 g8h8 a2a8,1000,mateIn2",
+        )
+        .unwrap();
+        assert_eq!(puzzle.player(), Color::White);
+
+        let mut attempt = PuzzleAttempt::new(puzzle);
+
+        // nothing is played out of turn
+        assert_eq!(attempt.try_move("b1b7".parse().unwrap()), PuzzleStep::Wrong);
+        assert!(!attempt.failed());
+
+        assert_eq!(attempt.reply(), Some("h8g8".parse().unwrap()));
+        assert_eq!(attempt.reply(), None);
+
+        assert_eq!(attempt.try_move("b1b8".parse().unwrap()), PuzzleStep::Wrong);
+        assert!(attempt.failed());
+        assert_eq!(
+            attempt.try_move("b1b7".parse().unwrap()),
+            PuzzleStep::Correct
+        );
+
+        // any mate finishes the puzzle
+        assert_eq!(attempt.reply(), Some("g8h8".parse().unwrap()));
+        assert_eq!(
+            attempt.try_move("a2a8".parse().unwrap()),
+            PuzzleStep::Solved
+        );
+        assert_eq!(attempt.reply(), None);
+        assert_eq!(attempt.last_step(), Some(PuzzleStep::Solved));
+    }
+
+    #[test]
+    fn test_parse_rejects_bad_lines() {
+        // illegal move, a line ending on the opponent's move and the header
+        assert!(Puzzle::parse("x,7k/8/8/8/8/8/R7/1R4K1 b - - 0 1,h8h1 b1b7,1000").is_none());
+        assert!(Puzzle::parse("x,7k/8/8/8/8/8/R7/1R4K1 b - - 0 1,h8g8,1000").is_none());
+        assert!(Puzzle::parse("PuzzleId,FEN,Moves,Rating,Themes").is_none());
+    }
+
+    #[test]
+    fn test_pick_puzzle() {
+        let puzzles = parse_puzzles(BUNDLED_PUZZLES);
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+
+        for _ in 0..10 {
+            let puzzle = pick_puzzle(&puzzles, 500, &mut rng).unwrap();
+            assert!(puzzle.rating <= 700, "{}", puzzle.id);
+        }
+
+        assert_eq!(pick_puzzle(&[], 1000, &mut rng), None);
+    }
+
+    #[test]
+    fn test_puzzle_stats() {
+        let mut stats = PuzzleStats::default();
+
+        // an even puzzle is worth half the K factor either way
+        stats.record(1000, true);
+        stats.record(1000, true);
+        assert_eq!((stats.rating, stats.streak), (1031, 2));
+
+        stats.record(1000, false);
+        assert_eq!((stats.streak, stats.best_streak), (0, 2));
+        assert!(stats.rating < 1031);
+
+        assert_eq!(PuzzleStats::from_json(&stats.to_json()).unwrap(), stats);
+        assert_eq!(
+            PuzzleStats::from_json("{}").unwrap(),
+            PuzzleStats::default()
+        );
+    }
+}
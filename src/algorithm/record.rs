@@ -0,0 +1,246 @@
+use super::{handicap::Handicap, variant::Variant};
+use cozy_chess::{Board, Color, Move, Piece};
+use std::fmt;
+
+/// Everything needed to replay a game: the start position, the moves and the
+/// seed the computer player used for its random choices.
+#[derive(Clone, Debug)]
+pub struct GameRecord {
+    pub seed: u64,
+    pub variant: Variant,
+    pub handicap: Handicap,
+    pub start: Board,
+    pub moves: Vec<Move>,
+}
+
+impl GameRecord {
+    pub fn new(start: Board, seed: u64) -> Self {
+        Self {
+            seed,
+            variant: Variant::Standard,
+            handicap: Handicap::None,
+            start,
+            moves: Vec::new(),
+        }
+    }
+
+    pub fn with_variant(mut self, variant: Variant) -> Self {
+        self.variant = variant;
+        self
+    }
+
+    // the odds are already taken out of `start`, this only records them
+    pub fn with_handicap(mut self, handicap: Handicap) -> Self {
+        self.handicap = handicap;
+        self
+    }
+
+    pub fn push(&mut self, mov: Move) {
+        self.moves.push(mov);
+    }
+
+    /// Position after every recorded move.
+    pub fn board(&self) -> Board {
+        let mut board = self.start.clone();
+
+        for mov in &self.moves {
+            board.play(*mov);
+        }
+
+        board
+    }
+}
+
+impl GameRecord {
+    /// Pieces each side has lost, indexed by colour, in the order they were
+    /// taken. Pieces missing from the start position, as in odds games, were
+    /// never captured and aren't listed.
+    pub fn captured(&self) -> [Vec<Piece>; Color::NUM] {
+        let mut captured = [vec![], vec![]];
+        let mut board = self.start.clone();
+
+        for mov in &self.moves {
+            let side = board.side_to_move();
+
+            match board.color_on(mov.to) {
+                // castling moves the king onto its own rook, so only the
+                // other side's pieces count
+                Some(color) if color != side => captured[color as usize]
+                    .push(board.piece_on(mov.to).expect("should be piece here")),
+                Some(_) => {}
+                // en passant is the only capture onto an empty square
+                None => {
+                    if board.piece_on(mov.from) == Some(Piece::Pawn)
+                        && mov.from.file() != mov.to.file()
+                    {
+                        captured[!side as usize].push(Piece::Pawn);
+                    }
+                }
+            }
+
+            board.play(*mov);
+        }
+
+        captured
+    }
+
+    /// How many points of material `color` is ahead by in the current
+    /// position, counting pawns as 1, minor pieces as 3, rooks as 5 and
+    /// queens as 9. Promotions and odds are included, unlike `captured`.
+    pub fn material_lead(&self, color: Color) -> i32 {
+        let board = self.board();
+        let material = |color| {
+            Piece::ALL
+                .into_iter()
+                .map(|piece| points(piece) * board.colored_pieces(color, piece).len() as i32)
+                .sum::<i32>()
+        };
+
+        material(color) - material(!color)
+    }
+}
+
+fn points(piece: Piece) -> i32 {
+    match piece {
+        Piece::Pawn => 1,
+        Piece::Knight | Piece::Bishop => 3,
+        Piece::Rook => 5,
+        Piece::Queen => 9,
+        Piece::King => 0,
+    }
+}
+
+impl fmt::Display for GameRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "[Seed \"{}\"]", self.seed)?;
+        if self.variant.is_chess960() {
+            writeln!(f, "[Variant \"Chess960\"]")?;
+        }
+        if self.handicap != Handicap::None {
+            writeln!(f, "[Handicap \"{}\"]", self.handicap)?;
+        }
+        writeln!(f, "[FEN \"{}\"]", self.variant.fen(&self.start))?;
+
+        let moves = self
+            .moves
+            .iter()
+            .map(|mov| mov.to_string())
+            .collect::<Vec<_>>();
+
+        write!(f, "{}", moves.join(" "))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use cozy_chess::Color;
+
+    #[test]
+    fn test_game_record() {
+        let mut record = GameRecord::new(Board::startpos(), 1234);
+
+        for mov in ["e2e4", "e7e5", "g1f3"] {
+            record.push(mov.parse().unwrap());
+        }
+
+        assert_eq!(
+            record.board(),
+            Board::from_fen(
+                "rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2",
+                false
+            )
+            .unwrap()
+        );
+
+        assert_eq!(
+            record.to_string(),
+            "[Seed \"1234\"]\n[FEN \"rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1\"]\ne2e4 e7e5 g1f3"
+        );
+    }
+
+    #[test]
+    fn test_chess960_record() {
+        let variant = Variant::Chess960(0);
+        let mut record = GameRecord::new(variant.start(), 1).with_variant(variant);
+
+        for mov in ["d1e3", "d8e6"] {
+            record.push(mov.parse().unwrap());
+        }
+
+        assert_eq!(
+            record.to_string(),
+            "[Seed \"1\"]\n[Variant \"Chess960\"]\n[FEN \"bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w HFhf - 0 1\"]\nd1e3 d8e6"
+        );
+    }
+
+    #[test]
+    fn test_handicap_record() {
+        let start = Handicap::Knight.apply(&Board::startpos(), Color::Black);
+        let record = GameRecord::new(start, 5).with_handicap(Handicap::Knight);
+
+        assert_eq!(
+            record.to_string(),
+            "[Seed \"5\"]\n[Handicap \"Knight odds\"]\n[FEN \"r1bqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1\"]\n"
+        );
+    }
+
+    fn play(fen: &str, moves: &[&str]) -> GameRecord {
+        let mut record = GameRecord::new(Board::from_fen(fen, false).unwrap(), 0);
+        for mov in moves {
+            record.push(mov.parse().unwrap());
+        }
+        record
+    }
+
+    #[test]
+    fn test_captured_pieces() {
+        // a pawn each, then white castles and takes en passant
+        let record = play(
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            &[
+                "e2e4", "d7d5", "e4d5", "d8d5", "g1f3", "d5e5", "f1e2", "e5e4", "e1h1", "f7f5",
+                "d2d3", "e4e2", "d1e2", "a7a6", "b1c3", "f5f4", "g2g4", "f4g3",
+            ],
+        );
+
+        assert_eq!(
+            record.captured(),
+            [
+                vec![Piece::Pawn, Piece::Bishop, Piece::Pawn],
+                vec![Piece::Pawn, Piece::Queen]
+            ]
+        );
+        assert_eq!(record.material_lead(Color::White), 5);
+        assert_eq!(record.material_lead(Color::Black), -5);
+    }
+
+    #[test]
+    fn test_captures_with_promotions() {
+        // white promotes to a second queen, which black then takes, and black
+        // promotes taking a rook
+        let record = play(
+            "r3k3/1P6/8/8/8/8/6p1/4K2R w - - 0 1",
+            &["b7b8q", "a8b8", "e1f2", "g2h1q"],
+        );
+
+        assert_eq!(record.captured(), [vec![Piece::Queen, Piece::Rook], vec![]]);
+
+        // white has only the king left against a rook and a queen
+        assert_eq!(record.material_lead(Color::Black), 14);
+
+        // underpromotions count as the piece they become
+        let record = play("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1", &["b7b8n"]);
+        assert!(record.captured().iter().all(Vec::is_empty));
+        assert_eq!(record.material_lead(Color::White), 3);
+    }
+
+    #[test]
+    fn test_captures_in_odds_game() {
+        let start = Handicap::Queen.apply(&Board::startpos(), Color::Black);
+        let record = GameRecord::new(start, 0);
+
+        assert!(record.captured().iter().all(Vec::is_empty));
+        assert_eq!(record.material_lead(Color::White), 9);
+    }
+}
@@ -0,0 +1,277 @@
+use super::{
+    eval::Evaluator,
+    record::GameRecord,
+    score::{Score, ScoreFunction},
+};
+use cozy_chess::{Board, Color, Move};
+use std::{fmt, future::Future, sync::Arc};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum MoveClass {
+    Best,
+    Good,
+    Inaccuracy,
+    Mistake,
+    Blunder,
+}
+
+impl MoveClass {
+    pub const ALL: [MoveClass; 5] = [
+        MoveClass::Best,
+        MoveClass::Good,
+        MoveClass::Inaccuracy,
+        MoveClass::Mistake,
+        MoveClass::Blunder,
+    ];
+
+    /// Class of a move that scored `loss` centipawns worse than the best one.
+    pub fn from_loss(loss: i32) -> Self {
+        match loss {
+            i32::MIN..=0 => MoveClass::Best,
+            1..=49 => MoveClass::Good,
+            50..=99 => MoveClass::Inaccuracy,
+            100..=299 => MoveClass::Mistake,
+            _ => MoveClass::Blunder,
+        }
+    }
+}
+
+impl fmt::Display for MoveClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MoveClass::Best => write!(f, "Best"),
+            MoveClass::Good => write!(f, "Good"),
+            MoveClass::Inaccuracy => write!(f, "Inaccuracy"),
+            MoveClass::Mistake => write!(f, "Mistake"),
+            MoveClass::Blunder => write!(f, "Blunder"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MoveReview {
+    // half-moves from the start of the game, so the move played is `moves[ply]`
+    pub ply: usize,
+    pub mover: Color,
+    pub played: Move,
+    pub best: Move,
+    // evaluation after the move, from white's point of view
+    pub eval: Score,
+    // how much worse the played move scored than the best, for the mover
+    pub loss: i32,
+    pub class: MoveClass,
+}
+
+/// Scores every position of a game with `evaluator` and classifies each move
+/// by how far it fell short of the best one. Evaluators whose scores aren't
+/// centipawns, like Monte Carlo tree search, give no review at all.
+pub fn review_game(
+    record: &GameRecord,
+    evaluator: &dyn Evaluator,
+    score_fn: Arc<dyn ScoreFunction>,
+) -> Vec<MoveReview> {
+    let mut board = record.start.clone();
+    let mut reviews = Vec::with_capacity(record.moves.len());
+
+    if !evaluator.scores_centipawns() {
+        return reviews;
+    }
+
+    for (ply, played) in record.moves.iter().enumerate() {
+        let Some(review) = review_move(&board, ply, *played, evaluator, &score_fn) else {
+            break;
+        };
+
+        board.play(*played);
+        reviews.push(review);
+    }
+
+    reviews
+}
+
+/// Same as `review_game`, but awaits `pause` before each position after the
+/// first, so a page searching on its only thread can handle input and repaint
+/// while a long game is reviewed.
+pub async fn review_game_async<F: Future<Output = ()>>(
+    record: &GameRecord,
+    evaluator: &dyn Evaluator,
+    score_fn: Arc<dyn ScoreFunction>,
+    mut pause: impl FnMut() -> F,
+) -> Vec<MoveReview> {
+    let mut board = record.start.clone();
+    let mut reviews = Vec::with_capacity(record.moves.len());
+
+    if !evaluator.scores_centipawns() {
+        return reviews;
+    }
+
+    for (ply, played) in record.moves.iter().enumerate() {
+        if ply > 0 {
+            pause().await;
+        }
+
+        let Some(review) = review_move(&board, ply, *played, evaluator, &score_fn) else {
+            break;
+        };
+
+        board.play(*played);
+        reviews.push(review);
+    }
+
+    reviews
+}
+
+// searches `board` and compares `played` with the best move, or gives None
+// when there are no moves to compare
+fn review_move(
+    board: &Board,
+    ply: usize,
+    played: Move,
+    evaluator: &dyn Evaluator,
+    score_fn: &Arc<dyn ScoreFunction>,
+) -> Option<MoveReview> {
+    let mover = board.side_to_move();
+    let evals = evaluator.eval_moves(board.clone(), score_fn.clone());
+
+    // the first of the best moves, as a greedy player would pick
+    let (best, best_score) = evals.iter().fold(None, |best, (mov, score)| match best {
+        Some((_, best_score)) if best_score >= *score => best,
+        _ => Some((*mov, *score)),
+    })?;
+
+    // searches that leave moves out get the played one scored directly
+    let played_score = evals
+        .iter()
+        .find(|(mov, _)| *mov == played)
+        .map(|(_, score)| *score)
+        .unwrap_or_else(|| {
+            let mut after = board.clone();
+            after.play(played);
+            match mover {
+                Color::White => score_fn.score(&after),
+                Color::Black => -score_fn.score(&after),
+            }
+        });
+
+    let loss = (best_score - played_score).as_centipawns();
+
+    Some(MoveReview {
+        ply,
+        mover,
+        played,
+        best,
+        eval: match mover {
+            Color::White => played_score,
+            Color::Black => -played_score,
+        },
+        loss,
+        class: MoveClass::from_loss(loss),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::algorithm::{
+        eval::{mcts::MonteCarloTreeSearch, AlphaBetaNegamax},
+        score::PawnDifferenceScore,
+        test::block_on,
+    };
+
+    #[test]
+    fn test_move_class_from_loss() {
+        for (loss, class) in [
+            (0, MoveClass::Best),
+            (1, MoveClass::Good),
+            (49, MoveClass::Good),
+            (50, MoveClass::Inaccuracy),
+            (100, MoveClass::Mistake),
+            (299, MoveClass::Mistake),
+            (300, MoveClass::Blunder),
+            (Score::MATE.as_centipawns(), MoveClass::Blunder),
+        ] {
+            assert_eq!(MoveClass::from_loss(loss), class, "{}", loss);
+        }
+    }
+
+    #[test]
+    fn test_review_blunder() {
+        // white walks the king away instead of taking the hanging queen
+        let start = Board::from_fen("r3k3/8/8/3q4/8/8/8/R2RK3 w - - 0 1", false).unwrap();
+        let mut record = GameRecord::new(start, 0);
+        record.push("e1e2".parse().unwrap());
+
+        let reviews = review_game(
+            &record,
+            &AlphaBetaNegamax::new(2),
+            Arc::new(PawnDifferenceScore::default()),
+        );
+
+        assert_eq!(reviews.len(), 1);
+        assert_eq!(reviews[0].class, MoveClass::Blunder);
+        assert_eq!(reviews[0].best, "d1d5".parse().unwrap());
+        assert!(reviews[0].eval < Score::ZERO);
+
+        // visit counts can't say how bad a move was
+        let reviews = review_game(
+            &record,
+            &MonteCarloTreeSearch::new(100),
+            Arc::new(PawnDifferenceScore::default()),
+        );
+        assert!(reviews.is_empty());
+    }
+
+    #[test]
+    fn test_review_best_moves() {
+        let evaluator = AlphaBetaNegamax::new(2);
+        let score_fn: Arc<dyn ScoreFunction> = Arc::new(PawnDifferenceScore::default());
+
+        // a game where both sides play the evaluator's own choices
+        let mut record = GameRecord::new(Board::startpos(), 0);
+        for _ in 0..6 {
+            let evals = evaluator.eval_moves(record.board(), score_fn.clone());
+            let best = evals
+                .iter()
+                .fold(None, |best: Option<&(Move, Score)>, eval| match best {
+                    Some(best) if best.1 >= eval.1 => Some(best),
+                    _ => Some(eval),
+                })
+                .unwrap();
+            record.push(best.0);
+        }
+
+        let reviews = review_game(&record, &evaluator, score_fn);
+
+        assert_eq!(reviews.len(), 6);
+        for review in reviews {
+            assert_eq!(review.class, MoveClass::Best);
+            assert_eq!(review.played, review.best);
+            assert_eq!(review.loss, 0);
+        }
+    }
+
+    #[test]
+    fn test_review_async_matches_review() {
+        let evaluator = AlphaBetaNegamax::new(2);
+        let score_fn: Arc<dyn ScoreFunction> = Arc::new(PawnDifferenceScore::default());
+
+        let mut record = GameRecord::new(Board::startpos(), 0);
+        for mov in ["e2e4", "e7e5", "d1h5", "b8c6", "h5f7"] {
+            record.push(mov.parse().unwrap());
+        }
+
+        let mut pauses = 0;
+        let reviews = block_on(review_game_async(
+            &record,
+            &evaluator,
+            score_fn.clone(),
+            || {
+                pauses += 1;
+                std::future::ready(())
+            },
+        ));
+
+        assert_eq!(reviews, review_game(&record, &evaluator, score_fn));
+        assert_eq!(pauses, record.moves.len() - 1);
+    }
+}
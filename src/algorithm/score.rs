@@ -1,40 +1,244 @@
+pub mod nnue;
+pub mod terms;
+
+use super::params::EvalParams;
 use cozy_chess::{Board, Color, Piece};
+use nnue::Network;
+use std::{
+    fmt,
+    iter::Sum,
+    ops::{Add, AddAssign, Neg, Sub, SubAssign},
+    sync::Arc,
+};
+use terms::Term;
+
+/// Evaluation of a position in centipawns.
+///
+/// Mates are encoded as scores within `MAX_MATE_PLY` of `MATE`, so that a mate
+/// found closer to the root always outranks a slower one and any material
+/// score compares below every mate.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Score(i32);
+
+impl Score {
+    pub const ZERO: Score = Score(0);
+    pub const MATE: Score = Score(100_000);
+    // strictly outside every reachable score, used as the search window bounds
+    pub const INFINITY: Score = Score(1_000_000);
+
+    const MAX_MATE_PLY: i32 = 1_000;
+
+    pub const fn centipawns(centipawns: i32) -> Self {
+        Self(centipawns)
+    }
+
+    pub fn pawns(pawns: f64) -> Self {
+        Self((pawns * 100.0).round() as i32)
+    }
+
+    /// Score for the side that delivers mate `ply` half-moves from now.
+    pub const fn mate_in(ply: usize) -> Self {
+        Self(Self::MATE.0 - ply as i32)
+    }
+
+    /// Score for the side that gets mated `ply` half-moves from now.
+    pub const fn mated_in(ply: usize) -> Self {
+        Self(-Self::MATE.0 + ply as i32)
+    }
+
+    pub fn as_centipawns(&self) -> i32 {
+        self.0
+    }
+
+    pub fn as_pawns(&self) -> f64 {
+        self.0 as f64 / 100.0
+    }
+
+    pub fn is_mate(&self) -> bool {
+        let distance = Self::MATE.0 - self.0.abs();
+        (0..Self::MAX_MATE_PLY).contains(&distance)
+    }
+
+    /// Half-moves until mate, positive when the side to move is mating.
+    pub fn mate_ply(&self) -> Option<i32> {
+        match self.is_mate() {
+            true => Some(self.0.signum() * (Self::MATE.0 - self.0.abs())),
+            false => None,
+        }
+    }
+}
+
+impl Neg for Score {
+    type Output = Score;
+
+    fn neg(self) -> Self::Output {
+        Score(-self.0)
+    }
+}
+
+impl Add for Score {
+    type Output = Score;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Score(self.0 + rhs.0)
+    }
+}
 
-pub trait ScoreFunction {
+impl Sub for Score {
+    type Output = Score;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        Score(self.0 - rhs.0)
+    }
+}
+
+impl AddAssign for Score {
+    fn add_assign(&mut self, rhs: Self) {
+        self.0 += rhs.0;
+    }
+}
+
+impl SubAssign for Score {
+    fn sub_assign(&mut self, rhs: Self) {
+        self.0 -= rhs.0;
+    }
+}
+
+impl Sum for Score {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Score::ZERO, |a, b| a + b)
+    }
+}
+
+impl fmt::Display for Score {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.mate_ply() {
+            // mates are shown in full moves, e.g. #3 or #-2
+            Some(ply) => write!(f, "#{}", ply.signum() * ((ply.abs() + 1) / 2)),
+            None => write!(f, "{:+.2}", self.as_pawns()),
+        }
+    }
+}
+
+pub trait ScoreFunction: Send + Sync {
     // always returns score from white's perspective
-    fn score(&self, board: &Board) -> f64;
+    fn score(&self, board: &Board) -> Score;
+
+    // searches keep the network's accumulator up to date move by move
+    // instead of calling `score` at every leaf
+    fn network(&self) -> Option<&Network> {
+        None
+    }
 }
 
 #[derive(Clone, Default)]
-pub struct PawnDifferenceScore;
+pub struct PawnDifferenceScore {
+    params: EvalParams,
+}
+
+impl PawnDifferenceScore {
+    pub fn new(params: EvalParams) -> Self {
+        Self { params }
+    }
+}
 
 impl ScoreFunction for PawnDifferenceScore {
-    fn score(&self, board: &Board) -> f64 {
-        let mut score = 0.0;
+    fn score(&self, board: &Board) -> Score {
+        Score::centipawns(material(board, &self.params))
+    }
+}
 
-        for white_piece in board.colors(Color::White) {
-            score += piece_value(board.piece_on(white_piece).expect("should be piece here"));
-        }
+fn material(board: &Board, params: &EvalParams) -> i32 {
+    let mut score = 0;
 
-        for black_piece in board.colors(Color::Black) {
-            score -= piece_value(board.piece_on(black_piece).expect("should be piece here"));
+    for white_piece in board.colors(Color::White) {
+        score += params.piece_value(board.piece_on(white_piece).expect("should be piece here"));
+    }
+
+    for black_piece in board.colors(Color::Black) {
+        score -= params.piece_value(board.piece_on(black_piece).expect("should be piece here"));
+    }
+
+    score
+}
+
+/// Material plus a set of positional terms, all weighted by the same params.
+#[derive(Clone)]
+pub struct CompositeScore {
+    params: EvalParams,
+    terms: Vec<Arc<dyn Term>>,
+}
+
+impl CompositeScore {
+    pub fn new(params: EvalParams) -> Self {
+        Self {
+            params,
+            terms: terms::all_terms(),
         }
+    }
 
-        score
+    pub fn with_terms(mut self, terms: Vec<Arc<dyn Term>>) -> Self {
+        self.terms = terms;
+        self
     }
 }
 
-pub fn piece_value(piece: Piece) -> f64 {
-    match piece {
-        Piece::Pawn => 1.0,
-        Piece::Knight => 3.0,
-        Piece::Bishop => 3.5,
-        Piece::Rook => 5.0,
-        Piece::Queen => 9.0,
-        Piece::King => 0.0,
+impl Default for CompositeScore {
+    fn default() -> Self {
+        Self::new(EvalParams::default())
     }
 }
 
+impl ScoreFunction for CompositeScore {
+    fn score(&self, board: &Board) -> Score {
+        let positional = self
+            .terms
+            .iter()
+            .map(|term| {
+                term.score(board, Color::White, &self.params)
+                    - term.score(board, Color::Black, &self.params)
+            })
+            .sum::<i32>();
+
+        Score::centipawns(material(board, &self.params) + positional)
+    }
+}
+
+/// Weighted sum of other score functions. Every component scores from white's
+/// perspective, so the sum does too whatever the weights are.
+#[derive(Clone, Default)]
+pub struct WeightedSumScore {
+    components: Vec<(f64, Arc<dyn ScoreFunction>)>,
+}
+
+impl WeightedSumScore {
+    pub fn new(components: Vec<(f64, Arc<dyn ScoreFunction>)>) -> Self {
+        Self { components }
+    }
+
+    pub fn with_component(mut self, weight: f64, score_fn: Arc<dyn ScoreFunction>) -> Self {
+        self.components.push((weight, score_fn));
+        self
+    }
+}
+
+impl ScoreFunction for WeightedSumScore {
+    fn score(&self, board: &Board) -> Score {
+        let sum = self
+            .components
+            .iter()
+            .map(|(weight, score_fn)| weight * score_fn.score(board).as_centipawns() as f64)
+            .sum::<f64>();
+
+        Score::centipawns(sum.round() as i32)
+    }
+}
+
+// default value in centipawns
+pub fn piece_value(piece: Piece) -> i32 {
+    EvalParams::default().piece_value(piece)
+}
+
 #[cfg(test)]
 mod test {
     use cozy_chess::FenParseError;
@@ -46,13 +250,13 @@ mod test {
         let score_fn = PawnDifferenceScore::default();
 
         let board = Board::default();
-        assert_eq!(score_fn.score(&board), 0.0);
+        assert_eq!(score_fn.score(&board), Score::ZERO);
 
         let board = Board::from_fen("rnbqkbnr/8/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", false)?;
-        assert_eq!(score_fn.score(&board), 8.0);
+        assert_eq!(score_fn.score(&board), Score::centipawns(800));
 
         let board = Board::from_fen("rnbqkbnr/pppppppp/8/8/8/8/8/RNBQKBNR w KQkq - 0 1", false)?;
-        assert_eq!(score_fn.score(&board), -8.0);
+        assert_eq!(score_fn.score(&board), Score::centipawns(-800));
 
         Ok(())
     }
@@ -63,32 +267,144 @@ mod test {
 
         // new game: score should be 0
         let mut board = Board::startpos();
-        assert_eq!(score_fn.score(&board), 0.0);
+        assert_eq!(score_fn.score(&board), Score::ZERO);
 
         // white pawn from D2 to D4: score should be 0
         board.play("d2d4".parse().unwrap());
-        assert_eq!(score_fn.score(&board), 0.0);
+        assert_eq!(score_fn.score(&board), Score::ZERO);
 
         // black pawn from E7 to E5: score should be 0
         board.play("e7e5".parse().unwrap());
-        assert_eq!(score_fn.score(&board), 0.0);
+        assert_eq!(score_fn.score(&board), Score::ZERO);
 
         // white pawn takes on E5: score should be 1.0
         board.play("d4e5".parse().unwrap());
-        assert_eq!(score_fn.score(&board), 1.0);
+        assert_eq!(score_fn.score(&board), Score::centipawns(100));
 
         // black moves queen to G5: score should be 1.0
         board.play("d8g5".parse().unwrap());
-        assert_eq!(score_fn.score(&board), 1.0);
+        assert_eq!(score_fn.score(&board), Score::centipawns(100));
 
         // white pushes pawn to E6: score should be 1.0
         board.play("e5e6".parse().unwrap());
-        assert_eq!(score_fn.score(&board), 1.0);
+        assert_eq!(score_fn.score(&board), Score::centipawns(100));
 
         // black queen takes bishop on C1: score should be -2.5
         board.play("g5c1".parse().unwrap());
-        assert_eq!(score_fn.score(&board), -2.5);
+        assert_eq!(score_fn.score(&board), Score::centipawns(-250));
+
+        Ok(())
+    }
+
+    #[test]
+    fn custom_params_pawn_difference_score() -> Result<(), FenParseError> {
+        let mut params = EvalParams::default();
+        params.set_piece_value(Piece::Pawn, 90);
+        params.set_piece_value(Piece::Bishop, 300);
+        let score_fn = PawnDifferenceScore::new(params);
+
+        // white is a pawn up
+        let board = Board::from_fen("4k3/pp6/8/8/8/8/PPP5/4K3 w - - 0 1", false)?;
+        assert_eq!(score_fn.score(&board), Score::centipawns(90));
+
+        // black has the bishop pair against a bishop and knight
+        let board = Board::from_fen("2b1kb2/8/8/8/8/8/8/2B1KN2 w - - 0 1", false)?;
+        assert_eq!(score_fn.score(&board), Score::ZERO);
 
         Ok(())
     }
+
+    #[test]
+    fn test_composite_score() -> Result<(), FenParseError> {
+        let score_fn = CompositeScore::default();
+
+        // the start position is symmetric
+        assert_eq!(score_fn.score(&Board::startpos()), Score::ZERO);
+
+        // mirroring the position and the side to move flips the sign
+        let board = Board::from_fen(
+            "r1bqk2r/pp3ppp/2n2n2/3p4/1b1P4/2N2N2/PP2BPPP/R1BQK2R w KQkq - 0 1",
+            false,
+        )?;
+        let mirrored = Board::from_fen(
+            "r1bqk2r/pp2bppp/2n2n2/1B1p4/3P4/2N2N2/PP3PPP/R1BQK2R b KQkq - 0 1",
+            false,
+        )?;
+        assert_eq!(score_fn.score(&board), -score_fn.score(&mirrored));
+
+        // without any terms only material is left
+        let score_fn = CompositeScore::default().with_terms(Vec::new());
+        assert_eq!(
+            score_fn.score(&board),
+            PawnDifferenceScore::default().score(&board)
+        );
+
+        // white's bishop pair is worth something once the term is enabled
+        let board = Board::from_fen("4kn2/8/8/8/8/8/8/2B1KB2 w - - 0 1", false)?;
+        let with_pair = CompositeScore::default().with_terms(vec![Arc::new(terms::BishopPair)]);
+        assert_eq!(
+            with_pair.score(&board),
+            score_fn.score(&board) + Score::centipawns(EvalParams::default().bishop_pair)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_weighted_sum_score() -> Result<(), FenParseError> {
+        // white is a knight up but black has the bishop pair
+        let board = Board::from_fen("2b1kb2/8/8/8/8/8/8/1N2KBN1 b - - 0 1", false)?;
+        let mirrored = Board::from_fen("1n2kbn1/8/8/8/8/8/8/2B1KB2 w - - 0 1", false)?;
+
+        let material = Arc::new(PawnDifferenceScore::default());
+        let bishop_pair =
+            Arc::new(CompositeScore::default().with_terms(vec![Arc::new(terms::BishopPair)]));
+
+        let score_fn = WeightedSumScore::default()
+            .with_component(0.5, material.clone())
+            .with_component(2.0, bishop_pair.clone());
+
+        assert_eq!(
+            score_fn.score(&board),
+            Score::pawns(
+                0.5 * material.score(&board).as_pawns()
+                    + 2.0 * bishop_pair.score(&board).as_pawns()
+            )
+        );
+
+        // still from white's point of view with the colours swapped
+        assert_eq!(score_fn.score(&mirrored), -score_fn.score(&board));
+
+        // nothing to add up
+        assert_eq!(WeightedSumScore::default().score(&board), Score::ZERO);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_score_ordering() {
+        assert!(Score::mate_in(1) > Score::mate_in(3));
+        assert!(Score::mate_in(99) > Score::centipawns(5_000));
+        assert!(Score::mated_in(1) < Score::mated_in(3));
+        assert!(Score::mated_in(99) < Score::centipawns(-5_000));
+        assert!(Score::INFINITY > Score::mate_in(0));
+        assert_eq!(-Score::mate_in(4), Score::mated_in(4));
+    }
+
+    #[test]
+    fn test_score_mate_encoding() {
+        assert!(Score::mate_in(5).is_mate());
+        assert!(Score::mated_in(2).is_mate());
+        assert!(!Score::centipawns(350).is_mate());
+        assert!(!Score::INFINITY.is_mate());
+
+        assert_eq!(Score::mate_in(5).mate_ply(), Some(5));
+        assert_eq!(Score::mated_in(2).mate_ply(), Some(-2));
+        assert_eq!(Score::centipawns(-250).mate_ply(), None);
+
+        assert_eq!(Score::mate_in(5).to_string(), "#3");
+        assert_eq!(Score::mated_in(2).to_string(), "#-1");
+        assert_eq!(Score::centipawns(-250).to_string(), "-2.50");
+        assert_eq!(Score::pawns(3.5), Score::centipawns(350));
+    }
 }
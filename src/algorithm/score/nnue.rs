@@ -0,0 +1,432 @@
+use super::{Score, ScoreFunction};
+use cozy_chess::{Board, Color, File, Piece, Rank, Square};
+use std::{fmt, sync::Arc};
+
+const MAGIC: &[u8; 4] = b"NNUE";
+const VERSION: u32 = 1;
+const HEADER_SIZE: usize = 12;
+
+// the king's square is grouped into a bucket by which half of the board it is
+// on and whether it is still on its first two ranks
+const KING_BUCKETS: usize = 4;
+// own and enemy pawns, knights, bishops, rooks and queens on any square
+const PIECE_FEATURES: usize = 2 * 5 * 64;
+pub const FEATURES: usize = KING_BUCKETS * PIECE_FEATURES;
+
+// hidden activations are clipped to 0..=CLIP, and the output is divided by
+// OUTPUT_SCALE to give centipawns
+const CLIP: i16 = 127;
+const OUTPUT_SCALE: i32 = 64;
+
+// reference network that reproduces the pawn difference score, used in tests.
+// it is built by hand rather than trained: `reference_network_bytes` in the
+// tests writes it out and checks it still matches
+const REFERENCE_NETWORK: &[u8] = include_bytes!("nnue/reference.nnue");
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NnueError {
+    InvalidMagic,
+    UnsupportedVersion(u32),
+    InvalidLength { expected: usize, found: usize },
+}
+
+impl fmt::Display for NnueError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NnueError::InvalidMagic => write!(f, "file does not start with NNUE"),
+            NnueError::UnsupportedVersion(version) => {
+                write!(f, "network version {} is not supported", version)
+            }
+            NnueError::InvalidLength { expected, found } => write!(
+                f,
+                "network should be {} bytes long but is {}",
+                expected, found
+            ),
+        }
+    }
+}
+
+/// Small efficiently updatable neural network with one hidden layer.
+///
+/// Inputs are HalfKP-like: every non-king piece is a feature relative to each
+/// king, seen from that king's side of the board. Each side's features feed
+/// its own accumulator through the same weights, and the output layer reads
+/// the side to move's accumulator first.
+///
+/// The file is little endian: the magic `NNUE`, a `u32` version and a `u32`
+/// hidden layer size, then `i16` feature weights (grouped by feature), `i16`
+/// hidden biases, `i16` output weights for both accumulators and an `i32`
+/// output bias.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Network {
+    hidden: usize,
+    feature_weights: Vec<i16>,
+    feature_bias: Vec<i16>,
+    output_weights: Vec<i16>,
+    output_bias: i32,
+}
+
+impl Network {
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, NnueError> {
+        if bytes.len() < HEADER_SIZE {
+            return Err(NnueError::InvalidLength {
+                expected: HEADER_SIZE,
+                found: bytes.len(),
+            });
+        }
+
+        if &bytes[0..4] != MAGIC {
+            return Err(NnueError::InvalidMagic);
+        }
+
+        let version = u32::from_le_bytes(bytes[4..8].try_into().unwrap());
+        if version != VERSION {
+            return Err(NnueError::UnsupportedVersion(version));
+        }
+
+        let hidden = u32::from_le_bytes(bytes[8..12].try_into().unwrap()) as usize;
+        let expected = HEADER_SIZE + 2 * (FEATURES * hidden + hidden + 2 * hidden) + 4;
+        if bytes.len() != expected {
+            return Err(NnueError::InvalidLength {
+                expected,
+                found: bytes.len(),
+            });
+        }
+
+        let mut rest = &bytes[HEADER_SIZE..];
+        let mut read_i16s = |count: usize| {
+            let (values, tail) = rest.split_at(2 * count);
+            rest = tail;
+            values
+                .chunks_exact(2)
+                .map(|pair| i16::from_le_bytes([pair[0], pair[1]]))
+                .collect::<Vec<_>>()
+        };
+
+        let feature_weights = read_i16s(FEATURES * hidden);
+        let feature_bias = read_i16s(hidden);
+        let output_weights = read_i16s(2 * hidden);
+        let output_bias = i32::from_le_bytes(rest.try_into().unwrap());
+
+        Ok(Self {
+            hidden,
+            feature_weights,
+            feature_bias,
+            output_weights,
+            output_bias,
+        })
+    }
+
+    pub fn reference() -> Self {
+        Self::from_bytes(REFERENCE_NETWORK).expect("reference network should be valid")
+    }
+
+    /// Score from the side to move's point of view.
+    pub fn evaluate(&self, accumulator: &Accumulator, side: Color) -> Score {
+        let (ours, theirs) = self.output_weights.split_at(self.hidden);
+
+        let layer = |values: &[i16], weights: &[i16]| {
+            values
+                .iter()
+                .zip(weights)
+                .map(|(value, weight)| (*value).clamp(0, CLIP) as i32 * *weight as i32)
+                .sum::<i32>()
+        };
+
+        let output = self.output_bias
+            + layer(&accumulator.values[side as usize], ours)
+            + layer(&accumulator.values[!side as usize], theirs);
+
+        Score::centipawns(output / OUTPUT_SCALE)
+    }
+
+    fn add_feature(&self, values: &mut [i16], feature: usize, sign: i16) {
+        let weights = &self.feature_weights[feature * self.hidden..(feature + 1) * self.hidden];
+
+        for (value, weight) in values.iter_mut().zip(weights) {
+            *value = value.wrapping_add(sign.wrapping_mul(*weight));
+        }
+    }
+
+    fn refresh(&self, board: &Board, perspective: Color) -> Vec<i16> {
+        let mut values = self.feature_bias.clone();
+        let bucket = king_bucket(board, perspective);
+
+        for color in Color::ALL {
+            for piece in Piece::ALL.into_iter().filter(|piece| *piece != Piece::King) {
+                for square in board.colored_pieces(color, piece) {
+                    let feature = feature(perspective, bucket, color, piece, square);
+                    self.add_feature(&mut values, feature, 1);
+                }
+            }
+        }
+
+        values
+    }
+}
+
+/// Hidden layer values for both sides, indexed by colour.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Accumulator {
+    values: [Vec<i16>; 2],
+}
+
+impl Accumulator {
+    pub fn new(network: &Network, board: &Board) -> Self {
+        Self {
+            values: [
+                network.refresh(board, Color::White),
+                network.refresh(board, Color::Black),
+            ],
+        }
+    }
+
+    /// Accumulator for `after`, which is `before` with one move played. Only
+    /// the pieces that changed are updated, unless a king moved into another
+    /// bucket, in which case that side starts again.
+    pub fn play(&self, network: &Network, before: &Board, after: &Board) -> Self {
+        let mut accumulator = self.clone();
+
+        for perspective in Color::ALL {
+            let bucket = king_bucket(after, perspective);
+            let values = &mut accumulator.values[perspective as usize];
+
+            if bucket != king_bucket(before, perspective) {
+                *values = network.refresh(after, perspective);
+                continue;
+            }
+
+            for color in Color::ALL {
+                for piece in Piece::ALL.into_iter().filter(|piece| *piece != Piece::King) {
+                    let old = before.colored_pieces(color, piece);
+                    let new = after.colored_pieces(color, piece);
+
+                    for square in old & !new {
+                        let feature = feature(perspective, bucket, color, piece, square);
+                        network.add_feature(values, feature, -1);
+                    }
+
+                    for square in new & !old {
+                        let feature = feature(perspective, bucket, color, piece, square);
+                        network.add_feature(values, feature, 1);
+                    }
+                }
+            }
+        }
+
+        accumulator
+    }
+}
+
+// squares are mirrored for black so each side sees itself at the bottom
+fn relative(square: Square, perspective: Color) -> Square {
+    match perspective {
+        Color::White => square,
+        Color::Black => square.flip_rank(),
+    }
+}
+
+fn king_bucket(board: &Board, perspective: Color) -> usize {
+    let king = relative(board.king(perspective), perspective);
+    let kingside = king.file() >= File::E;
+    let advanced = king.rank() >= Rank::Third;
+
+    kingside as usize + 2 * advanced as usize
+}
+
+fn feature(perspective: Color, bucket: usize, color: Color, piece: Piece, square: Square) -> usize {
+    let enemy = color != perspective;
+
+    bucket * PIECE_FEATURES
+        + (enemy as usize * 5 + piece as usize) * 64
+        + relative(square, perspective) as usize
+}
+
+/// Score function backed by a `Network`. Searches that support it keep an
+/// `Accumulator` up to date as they play moves rather than calling `score`.
+#[derive(Clone)]
+pub struct NnueScore {
+    network: Arc<Network>,
+}
+
+impl NnueScore {
+    pub fn new(network: Arc<Network>) -> Self {
+        Self { network }
+    }
+}
+
+impl Default for NnueScore {
+    fn default() -> Self {
+        Self::new(Arc::new(Network::reference()))
+    }
+}
+
+impl ScoreFunction for NnueScore {
+    fn score(&self, board: &Board) -> Score {
+        let accumulator = Accumulator::new(&self.network, board);
+
+        match board.side_to_move() {
+            Color::White => self.network.evaluate(&accumulator, Color::White),
+            Color::Black => -self.network.evaluate(&accumulator, Color::Black),
+        }
+    }
+
+    fn network(&self) -> Option<&Network> {
+        Some(&self.network)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::algorithm::{params::EvalParams, score::PawnDifferenceScore};
+
+    // one hidden neuron counts each kind of piece on each side: every piece
+    // adds COUNT, so up to 15 of a kind fit under CLIP. The side to move's own
+    // pieces score their value and the enemy's the negative, from both
+    // accumulators, so the output works out to the material difference
+    fn reference_network_bytes() -> Vec<u8> {
+        const COUNT: i16 = 8;
+        const HIDDEN: usize = 2 * 5;
+
+        let params = EvalParams::default();
+        let values = [
+            params.pawn,
+            params.knight,
+            params.bishop,
+            params.rook,
+            params.queen,
+        ];
+        let output_weight = |enemy: bool, piece: usize| {
+            let weight = (OUTPUT_SCALE / (2 * COUNT as i32) * values[piece]) as i16;
+            match enemy {
+                true => -weight,
+                false => weight,
+            }
+        };
+
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend(VERSION.to_le_bytes());
+        bytes.extend((HIDDEN as u32).to_le_bytes());
+
+        // the same weights for every king bucket and square
+        for feature in 0..FEATURES {
+            let neuron = feature % PIECE_FEATURES / 64;
+            for i in 0..HIDDEN {
+                let weight = match i == neuron {
+                    true => COUNT,
+                    false => 0,
+                };
+                bytes.extend(weight.to_le_bytes());
+            }
+        }
+
+        bytes.extend([0; 2 * HIDDEN]);
+
+        // the other side's accumulator sees the same pieces from the other
+        // side, so it gets the same weights negated
+        for theirs in [false, true] {
+            for neuron in 0..HIDDEN {
+                let enemy = neuron >= 5;
+                bytes.extend(output_weight(enemy != theirs, neuron % 5).to_le_bytes());
+            }
+        }
+
+        bytes.extend(0i32.to_le_bytes());
+        bytes
+    }
+
+    // set WRITE_REFERENCE_NETWORK to regenerate the file, then run the tests
+    // again so the new file is compiled in
+    #[test]
+    fn test_reference_network_is_generated() {
+        let bytes = reference_network_bytes();
+
+        if std::env::var_os("WRITE_REFERENCE_NETWORK").is_some() {
+            let path = concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/src/algorithm/score/nnue/reference.nnue"
+            );
+            std::fs::write(path, &bytes).unwrap();
+        }
+
+        assert_eq!(bytes, REFERENCE_NETWORK);
+    }
+
+    #[test]
+    fn test_from_bytes() {
+        assert_eq!(
+            Network::from_bytes(b"NNUE"),
+            Err(NnueError::InvalidLength {
+                expected: HEADER_SIZE,
+                found: 4
+            })
+        );
+        assert_eq!(
+            Network::from_bytes(b"EUNN\x01\0\0\0\0\0\0\0"),
+            Err(NnueError::InvalidMagic)
+        );
+        assert_eq!(
+            Network::from_bytes(b"NNUE\x02\0\0\0\0\0\0\0"),
+            Err(NnueError::UnsupportedVersion(2))
+        );
+
+        // an empty hidden layer still needs its output bias
+        assert_eq!(
+            Network::from_bytes(b"NNUE\x01\0\0\0\0\0\0\0"),
+            Err(NnueError::InvalidLength {
+                expected: HEADER_SIZE + 4,
+                found: HEADER_SIZE
+            })
+        );
+        assert!(Network::from_bytes(b"NNUE\x01\0\0\0\0\0\0\0\x07\0\0\0").is_ok());
+    }
+
+    #[test]
+    fn test_reference_network_matches_pawn_difference() {
+        let nnue = NnueScore::default();
+        let pawn_difference = PawnDifferenceScore::default();
+
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "4k3/8/8/3q4/8/8/3R4/3RK3 b - - 0 1",
+            "1QQQQQQQ/QQ6/8/8/8/8/7k/K7 b - - 0 1",
+        ] {
+            let board = Board::from_fen(fen, false).unwrap();
+            assert_eq!(nnue.score(&board), pawn_difference.score(&board), "{}", fen);
+        }
+    }
+
+    #[test]
+    fn test_incremental_updates_match_refresh() {
+        let network = Network::reference();
+
+        // castling both ways, en passant, promotions and kings changing bucket
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 1",
+            "1r6/2P5/8/3pP3/8/8/8/k6K w - d6 0 1",
+            "4k3/4p3/8/8/8/8/3PK3/8 w - - 0 1",
+        ] {
+            let board = Board::from_fen(fen, false).unwrap();
+            let accumulator = Accumulator::new(&network, &board);
+
+            board.generate_moves(|piece_moves| {
+                for mov in piece_moves {
+                    let mut after = board.clone();
+                    after.play(mov);
+
+                    assert_eq!(
+                        accumulator.play(&network, &board, &after),
+                        Accumulator::new(&network, &after),
+                        "{} in {}",
+                        mov,
+                        fen
+                    );
+                }
+                false
+            });
+        }
+    }
+}
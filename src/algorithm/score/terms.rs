@@ -0,0 +1,301 @@
+use super::super::params::EvalParams;
+use cozy_chess::{
+    get_bishop_moves, get_king_moves, get_knight_moves, get_pawn_attacks, get_pawn_quiets,
+    get_rook_moves, BitBoard, Board, Color, File, Piece, Rank, Square,
+};
+use std::sync::Arc;
+
+/// Positional part of a `CompositeScore`.
+pub trait Term: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    // score for `color` alone, the composite takes the difference between sides
+    fn score(&self, board: &Board, color: Color, params: &EvalParams) -> i32;
+}
+
+/// Every term, in the order they are shown in the opponent settings.
+pub fn all_terms() -> Vec<Arc<dyn Term>> {
+    vec![
+        Arc::new(Mobility),
+        Arc::new(PawnStructure),
+        Arc::new(KingSafety),
+        Arc::new(BishopPair),
+        Arc::new(RookOpenFiles),
+    ]
+}
+
+pub struct Mobility;
+
+impl Term for Mobility {
+    fn name(&self) -> &'static str {
+        "Mobility"
+    }
+
+    fn score(&self, board: &Board, color: Color, params: &EvalParams) -> i32 {
+        // the side not to move can't have its legal moves generated, since
+        // it may be giving check, so its moves are counted without checking
+        // they leave its king safe
+        let moves = match board.side_to_move() == color {
+            true => legal_move_count(board),
+            false => pseudo_legal_move_count(board, color),
+        };
+
+        params.mobility * moves
+    }
+}
+
+fn legal_move_count(board: &Board) -> i32 {
+    let mut count = 0;
+    board.generate_moves(|piece_moves| {
+        count += piece_moves.len() as i32;
+        false
+    });
+    count
+}
+
+fn pseudo_legal_move_count(board: &Board, color: Color) -> i32 {
+    let own = board.colors(color);
+    let enemy = board.colors(!color);
+    let occupied = board.occupied();
+    let mut count = 0;
+
+    for square in board.colored_pieces(color, Piece::Pawn) {
+        let targets =
+            get_pawn_quiets(square, color, occupied) | (get_pawn_attacks(square, color) & enemy);
+        // each promotion counts once per piece, as in the legal moves
+        let promotions = targets & Rank::Eighth.relative_to(color).bitboard();
+        count += (targets.len() + 3 * promotions.len()) as i32;
+    }
+
+    for piece in [
+        Piece::Knight,
+        Piece::Bishop,
+        Piece::Rook,
+        Piece::Queen,
+        Piece::King,
+    ] {
+        for square in board.colored_pieces(color, piece) {
+            let targets = match piece {
+                Piece::Knight => get_knight_moves(square),
+                Piece::Bishop => get_bishop_moves(square, occupied),
+                Piece::Rook => get_rook_moves(square, occupied),
+                Piece::Queen => {
+                    get_bishop_moves(square, occupied) | get_rook_moves(square, occupied)
+                }
+                _ => get_king_moves(square),
+            };
+            count += (targets & !own).len() as i32;
+        }
+    }
+
+    count
+}
+
+pub struct PawnStructure;
+
+impl Term for PawnStructure {
+    fn name(&self) -> &'static str {
+        "Pawn Structure"
+    }
+
+    fn score(&self, board: &Board, color: Color, params: &EvalParams) -> i32 {
+        let pawns = board.colored_pieces(color, Piece::Pawn);
+        let enemy_pawns = board.colored_pieces(!color, Piece::Pawn);
+        let mut score = 0;
+
+        for file in File::ALL {
+            let count = (pawns & file.bitboard()).len() as i32;
+
+            if count > 1 {
+                score += params.doubled_pawn * (count - 1);
+            }
+
+            if (pawns & file.adjacent()).is_empty() {
+                score += params.isolated_pawn * count;
+            }
+        }
+
+        for square in pawns {
+            let files = square.file().bitboard() | square.file().adjacent();
+
+            if (enemy_pawns & files & ranks_ahead(square, color, 7)).is_empty() {
+                score += params.passed_pawn;
+            }
+        }
+
+        score
+    }
+}
+
+// up to `count` ranks in front of the square from `color`'s point of view
+fn ranks_ahead(square: Square, color: Color, count: usize) -> BitBoard {
+    let rank = square.rank().relative_to(color) as usize;
+
+    Rank::ALL
+        .into_iter()
+        .filter(|other| (rank + 1..=rank + count).contains(&(other.relative_to(color) as usize)))
+        .fold(BitBoard::EMPTY, |a, b| a | b.bitboard())
+}
+
+pub struct KingSafety;
+
+impl Term for KingSafety {
+    fn name(&self) -> &'static str {
+        "King Safety"
+    }
+
+    fn score(&self, board: &Board, color: Color, params: &EvalParams) -> i32 {
+        let king = board.king(color);
+        let occupied = board.occupied();
+
+        let files = king.file().bitboard() | king.file().adjacent();
+        let shelter =
+            board.colored_pieces(color, Piece::Pawn) & files & ranks_ahead(king, color, 2);
+
+        let zone = get_king_moves(king) | king.bitboard();
+        let attackers = board
+            .colors(!color)
+            .into_iter()
+            .filter(|square| {
+                let attacks = match board.piece_on(*square).expect("should be piece here") {
+                    Piece::Knight => get_knight_moves(*square),
+                    Piece::Bishop => get_bishop_moves(*square, occupied),
+                    Piece::Rook => get_rook_moves(*square, occupied),
+                    Piece::Queen => {
+                        get_bishop_moves(*square, occupied) | get_rook_moves(*square, occupied)
+                    }
+                    Piece::Pawn | Piece::King => BitBoard::EMPTY,
+                };
+
+                !(attacks & zone).is_empty()
+            })
+            .count() as i32;
+
+        params.king_shelter * shelter.len() as i32 + params.king_attacker * attackers
+    }
+}
+
+pub struct BishopPair;
+
+impl Term for BishopPair {
+    fn name(&self) -> &'static str {
+        "Bishop Pair"
+    }
+
+    fn score(&self, board: &Board, color: Color, params: &EvalParams) -> i32 {
+        match board.colored_pieces(color, Piece::Bishop).len() >= 2 {
+            true => params.bishop_pair,
+            false => 0,
+        }
+    }
+}
+
+pub struct RookOpenFiles;
+
+impl Term for RookOpenFiles {
+    fn name(&self) -> &'static str {
+        "Rooks on Open Files"
+    }
+
+    fn score(&self, board: &Board, color: Color, params: &EvalParams) -> i32 {
+        let pawns = board.pieces(Piece::Pawn);
+        let own_pawns = board.colored_pieces(color, Piece::Pawn);
+
+        board
+            .colored_pieces(color, Piece::Rook)
+            .into_iter()
+            .map(|square| {
+                let file = square.file().bitboard();
+
+                if (pawns & file).is_empty() {
+                    params.rook_open_file
+                } else if (own_pawns & file).is_empty() {
+                    params.rook_half_open_file
+                } else {
+                    0
+                }
+            })
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn term_scores(term: &dyn Term, fen: &str) -> (i32, i32) {
+        let board = Board::from_fen(fen, false).unwrap();
+        let params = EvalParams::default();
+
+        (
+            term.score(&board, Color::White, &params),
+            term.score(&board, Color::Black, &params),
+        )
+    }
+
+    #[test]
+    fn test_mobility() {
+        let params = EvalParams::default();
+        assert_eq!(
+            term_scores(
+                &Mobility,
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"
+            ),
+            (20 * params.mobility, 20 * params.mobility)
+        );
+
+        // black is in check, and white still gets its king's three moves
+        // and the rook's thirteen
+        assert_eq!(
+            term_scores(&Mobility, "4k3/8/8/8/8/8/8/K3R3 b - - 0 1").0,
+            16 * params.mobility
+        );
+    }
+
+    #[test]
+    fn test_pawn_structure() {
+        let params = EvalParams::default();
+
+        // white has doubled, isolated c pawns, black has a passed h pawn
+        let (white, black) = term_scores(&PawnStructure, "4k3/7p/8/8/8/2P5/2P5/4K3 w - - 0 1");
+
+        assert_eq!(
+            white,
+            params.doubled_pawn + 2 * params.isolated_pawn + 2 * params.passed_pawn
+        );
+        assert_eq!(black, params.isolated_pawn + params.passed_pawn);
+
+        // blocked from the side, so neither pawn is passed
+        let (white, black) = term_scores(&PawnStructure, "4k3/8/3p4/8/2P5/8/8/4K3 w - - 0 1");
+        assert_eq!(white, params.isolated_pawn);
+        assert_eq!(black, params.isolated_pawn);
+    }
+
+    #[test]
+    fn test_king_safety() {
+        let params = EvalParams::default();
+
+        // castled white king behind three pawns, black's queen and knight
+        // both eye the squares around it
+        let (white, black) = term_scores(&KingSafety, "4k3/8/8/8/8/5n2/5PPP/3q2K1 w - - 0 1");
+
+        assert_eq!(white, 3 * params.king_shelter + 2 * params.king_attacker);
+        assert_eq!(black, 0);
+    }
+
+    #[test]
+    fn test_bishop_pair_and_rooks() {
+        let params = EvalParams::default();
+
+        assert_eq!(
+            term_scores(&BishopPair, "2b1kb2/8/8/8/8/8/8/2B1KN2 w - - 0 1"),
+            (0, params.bishop_pair)
+        );
+
+        // open d file and half-open h file for black, closed files for white
+        assert_eq!(
+            term_scores(&RookOpenFiles, "3rk2r/p7/8/8/8/8/PP5P/1R2K2R w - - 0 1"),
+            (0, params.rook_open_file + params.rook_half_open_file)
+        );
+    }
+}
@@ -0,0 +1,283 @@
+use super::{
+    choose::{BlunderChooser, Chooser, SoftmaxChooser},
+    eval::{Evaluator, PrincipalVariationSearch},
+    score::{Score, ScoreFunction},
+    ComputerPlayer,
+};
+use cozy_chess::{Board, Color, GameStatus};
+use std::{collections::HashMap, sync::Arc};
+
+// a blunder gives away at least a minor piece
+pub const BLUNDER_LOSS: Score = Score::centipawns(300);
+
+/// Engine settings that play at roughly `rating`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Strength {
+    pub rating: u32,
+    pub depth: usize,
+    // stops deepening early on busy positions, `None` searches to full depth
+    pub node_limit: Option<usize>,
+    // in pawns, see `SoftmaxChooser`
+    pub temperature: f64,
+    pub blunder_rate: f64,
+}
+
+/// Calibrated levels, weakest first. Ratings are relative to the weakest
+/// level, which is pinned at 400, and come from `calibrate --games 100` with
+/// the pawn difference score, rounded to 50. The error bars on each gap were
+/// up to ±100, so treat them as a rough guide. One-ply levels with less
+/// randomness than the weakest came out even with it, as games between them
+/// are nearly all drawn, so only the weakest is kept.
+pub const LEVELS: [Strength; 6] = [
+    Strength {
+        rating: 400,
+        depth: 1,
+        node_limit: Some(1_000),
+        temperature: 1.0,
+        blunder_rate: 0.3,
+    },
+    Strength {
+        rating: 750,
+        depth: 2,
+        node_limit: Some(3_000),
+        temperature: 0.3,
+        blunder_rate: 0.1,
+    },
+    Strength {
+        rating: 950,
+        depth: 2,
+        node_limit: Some(5_000),
+        temperature: 0.1,
+        blunder_rate: 0.05,
+    },
+    Strength {
+        rating: 1250,
+        depth: 3,
+        node_limit: Some(10_000),
+        temperature: 0.1,
+        blunder_rate: 0.02,
+    },
+    Strength {
+        rating: 1650,
+        depth: 4,
+        node_limit: Some(50_000),
+        temperature: 0.05,
+        blunder_rate: 0.0,
+    },
+    Strength {
+        rating: 2100,
+        depth: 5,
+        node_limit: None,
+        temperature: 0.0,
+        blunder_rate: 0.0,
+    },
+];
+
+impl Strength {
+    /// Interpolates between the calibrated levels. The depth comes from the
+    /// weaker level, since a search can't be part way between two depths, and
+    /// so does the node limit when the stronger level has none.
+    pub fn for_rating(rating: u32) -> Self {
+        let first = LEVELS[0];
+        let last = LEVELS[LEVELS.len() - 1];
+
+        if rating <= first.rating {
+            return first;
+        }
+
+        if rating >= last.rating {
+            return last;
+        }
+
+        let upper = LEVELS.partition_point(|level| level.rating <= rating);
+        let (weaker, stronger) = (LEVELS[upper - 1], LEVELS[upper]);
+
+        let t = (rating - weaker.rating) as f64 / (stronger.rating - weaker.rating) as f64;
+        let lerp = |a: f64, b: f64| a + t * (b - a);
+
+        Self {
+            rating,
+            depth: weaker.depth,
+            node_limit: match (weaker.node_limit, stronger.node_limit) {
+                (Some(a), Some(b)) => Some(lerp(a as f64, b as f64).round() as usize),
+                (limit, _) => limit,
+            },
+            temperature: lerp(weaker.temperature, stronger.temperature),
+            blunder_rate: lerp(weaker.blunder_rate, stronger.blunder_rate),
+        }
+    }
+
+    pub fn search(&self) -> PrincipalVariationSearch {
+        PrincipalVariationSearch::new(self.depth).with_node_limit(self.node_limit)
+    }
+
+    pub fn evaluator(&self) -> Arc<dyn Evaluator> {
+        Arc::new(self.search())
+    }
+
+    pub fn chooser(&self) -> Arc<dyn Chooser> {
+        let softmax = Arc::new(SoftmaxChooser::new(self.temperature));
+
+        match self.blunder_rate > 0.0 {
+            true => {
+                Arc::new(BlunderChooser::new(self.blunder_rate, BLUNDER_LOSS).with_chooser(softmax))
+            }
+            false => softmax,
+        }
+    }
+
+    pub fn player(&self, score_fn: Arc<dyn ScoreFunction>) -> ComputerPlayer {
+        ComputerPlayer::new(self.evaluator(), score_fn, self.chooser())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GameResult {
+    WhiteWin,
+    BlackWin,
+    Draw,
+}
+
+/// Plays a game between two engines from `board`, calling it a draw on
+/// threefold repetition or after `max_plies` half-moves.
+pub fn play_game(
+    white: &ComputerPlayer,
+    black: &ComputerPlayer,
+    mut board: Board,
+    max_plies: usize,
+) -> GameResult {
+    let mut seen = HashMap::new();
+
+    for _ in 0..max_plies {
+        match board.status() {
+            GameStatus::Won => {
+                return match board.side_to_move() {
+                    Color::White => GameResult::BlackWin,
+                    Color::Black => GameResult::WhiteWin,
+                }
+            }
+            GameStatus::Drawn => return GameResult::Draw,
+            GameStatus::Ongoing => {}
+        }
+
+        let repetitions = seen.entry(board.hash()).or_insert(0);
+        *repetitions += 1;
+        if *repetitions >= 3 {
+            return GameResult::Draw;
+        }
+
+        let player = match board.side_to_move() {
+            Color::White => white,
+            Color::Black => black,
+        };
+
+        let Some(mov) = player.get_move(board.clone()) else {
+            return GameResult::Draw;
+        };
+        board.play(mov);
+    }
+
+    GameResult::Draw
+}
+
+/// Rating difference implied by a match score, along with the half-width of
+/// its 95% confidence interval, both in Elo.
+pub fn elo_difference(wins: usize, draws: usize, losses: usize) -> (f64, f64) {
+    let games = (wins + draws + losses) as f64;
+    if games == 0.0 {
+        return (0.0, f64::INFINITY);
+    }
+
+    let score = (wins as f64 + draws as f64 / 2.0) / games;
+    let variance = (wins as f64 * (1.0 - score).powi(2)
+        + draws as f64 * (0.5 - score).powi(2)
+        + losses as f64 * score.powi(2))
+        / games;
+    let error = 1.96 * (variance / games).sqrt();
+
+    // a clean sweep would be infinitely many points apart
+    let bound = 0.5 / games;
+    let elo = |score: f64| {
+        let score = score.clamp(bound, 1.0 - bound);
+        -400.0 * (1.0 / score - 1.0).log10()
+    };
+
+    (elo(score), (elo(score + error) - elo(score - error)) / 2.0)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::algorithm::{
+        choose::GreedyChooser, eval::AlphaBetaNegamax, score::PawnDifferenceScore,
+    };
+
+    #[test]
+    fn test_for_rating() {
+        assert_eq!(Strength::for_rating(0), LEVELS[0]);
+        assert_eq!(Strength::for_rating(3000), LEVELS[LEVELS.len() - 1]);
+        assert_eq!(Strength::for_rating(LEVELS[2].rating), LEVELS[2]);
+
+        // stronger ratings never search less or play looser
+        let strengths = (0..3000)
+            .step_by(50)
+            .map(Strength::for_rating)
+            .collect::<Vec<_>>();
+
+        for pair in strengths.windows(2) {
+            assert!(pair[0].depth <= pair[1].depth);
+            assert!(
+                pair[0].node_limit.unwrap_or(usize::MAX)
+                    <= pair[1].node_limit.unwrap_or(usize::MAX)
+            );
+            assert!(pair[0].temperature >= pair[1].temperature);
+            assert!(pair[0].blunder_rate >= pair[1].blunder_rate);
+        }
+    }
+
+    #[test]
+    fn test_levels_are_ordered() {
+        // a level rated the same as its neighbour could never be picked
+        for pair in LEVELS.windows(2) {
+            assert!(pair[0].rating < pair[1].rating);
+        }
+    }
+
+    #[test]
+    fn test_elo_difference() {
+        let (elo, error) = elo_difference(10, 0, 10);
+        assert_eq!(elo, 0.0);
+        assert!(error > 0.0);
+
+        // 75% is about 191 points
+        let (elo, _) = elo_difference(15, 0, 5);
+        assert!((elo - 190.85).abs() < 0.1);
+
+        let (elo, _) = elo_difference(5, 0, 15);
+        assert!((elo + 190.85).abs() < 0.1);
+
+        // more games mean tighter error bars
+        assert!(elo_difference(150, 0, 50).1 < elo_difference(15, 0, 5).1);
+    }
+
+    #[test]
+    fn test_play_game() {
+        let player = ComputerPlayer::new(
+            Arc::new(AlphaBetaNegamax::new(2)),
+            Arc::new(PawnDifferenceScore::default()),
+            Arc::new(GreedyChooser),
+        );
+
+        // white mates straight away
+        let board = Board::from_fen("k7/8/1K6/8/8/8/8/7R w - - 0 1", false).unwrap();
+        assert_eq!(play_game(&player, &player, board, 10), GameResult::WhiteWin);
+
+        // black is already mated
+        let board = Board::from_fen("R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1", false).unwrap();
+        assert_eq!(play_game(&player, &player, board, 10), GameResult::WhiteWin);
+
+        // nothing happens with bare kings
+        let board = Board::from_fen("k7/8/1K6/8/8/8/8/8 w - - 0 1", false).unwrap();
+        assert_eq!(play_game(&player, &player, board, 10), GameResult::Draw);
+    }
+}
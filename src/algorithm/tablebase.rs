@@ -0,0 +1,798 @@
+#[cfg(test)]
+mod generate;
+mod table;
+
+use cozy_chess::{BitBoard, Board, Color, GameStatus, Move, Piece};
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    fmt,
+    sync::{Mutex, OnceLock},
+};
+use table::{Kind, Material, Table};
+
+// three-piece tables shipped with the app, so basic endings are played
+// perfectly without downloading anything. they are solved and written out in
+// the Syzygy format by `generate` in the tests, which checks they still match
+const BUNDLED: [(&str, &[u8]); 10] = [
+    ("KQvK.rtbw", include_bytes!("tablebase/KQvK.rtbw")),
+    ("KQvK.rtbz", include_bytes!("tablebase/KQvK.rtbz")),
+    ("KRvK.rtbw", include_bytes!("tablebase/KRvK.rtbw")),
+    ("KRvK.rtbz", include_bytes!("tablebase/KRvK.rtbz")),
+    ("KBvK.rtbw", include_bytes!("tablebase/KBvK.rtbw")),
+    ("KBvK.rtbz", include_bytes!("tablebase/KBvK.rtbz")),
+    ("KNvK.rtbw", include_bytes!("tablebase/KNvK.rtbw")),
+    ("KNvK.rtbz", include_bytes!("tablebase/KNvK.rtbz")),
+    ("KPvK.rtbw", include_bytes!("tablebase/KPvK.rtbw")),
+    ("KPvK.rtbz", include_bytes!("tablebase/KPvK.rtbz")),
+];
+
+// distance for a position whose best move resets the fifty-move count, by
+// result from a loss up to a win
+const WDL_TO_DTZ: [i32; 5] = [-1, -101, 0, 101, 1];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TablebaseError {
+    // files are named after the material they hold, like KQvKR.rtbw
+    InvalidName(String),
+    InvalidMagic,
+    // the file ends before the data its header describes
+    Truncated,
+    // the header or the compressed data don't add up
+    Corrupted,
+    Io(String),
+}
+
+impl fmt::Display for TablebaseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TablebaseError::InvalidName(name) => write!(f, "{} is not a Syzygy table name", name),
+            TablebaseError::InvalidMagic => write!(f, "file is not a Syzygy table"),
+            TablebaseError::Truncated => write!(f, "table file is cut short"),
+            TablebaseError::Corrupted => write!(f, "table file is corrupted"),
+            TablebaseError::Io(err) => write!(f, "couldn't read table file: {}", err),
+        }
+    }
+}
+
+/// Result with best play for the side to move. Cursed wins and blessed
+/// losses are only wins and losses if the fifty-move rule is ignored.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Wdl {
+    Loss,
+    BlessedLoss,
+    Draw,
+    CursedWin,
+    Win,
+}
+
+impl Wdl {
+    fn from_value(value: i32) -> Self {
+        match value {
+            i32::MIN..=-2 => Wdl::Loss,
+            -1 => Wdl::BlessedLoss,
+            0 => Wdl::Draw,
+            1 => Wdl::CursedWin,
+            _ => Wdl::Win,
+        }
+    }
+}
+
+/// Tablebase result from the side to move's perspective.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Probe {
+    pub wdl: Wdl,
+    // half-moves until a capture, pawn move or mate resets the fifty-move
+    // count with best play. positive when winning, negative when losing,
+    // and zero for draws
+    pub dtz: i32,
+}
+
+pub trait Tablebase: Send + Sync {
+    // largest number of pieces, kings included, that can be probed
+    fn max_pieces(&self) -> usize;
+
+    fn probe(&self, board: &Board) -> Option<Probe>;
+
+    /// Picks the move that resets the fifty-move count soonest when winning
+    /// and latest when losing, or any move that holds the draw. Wins that
+    /// can't be forced before the fifty-move rule draws the game rank below
+    /// wins that can.
+    fn best_move(&self, board: &Board) -> Option<Move> {
+        if board.occupied().len() as usize > self.max_pieces() {
+            return None;
+        }
+
+        let mut best: Option<(Move, (i32, i32, i32))> = None;
+
+        for mov in legal_moves(board) {
+            let mut temp_board = board.clone();
+            temp_board.play(mov);
+
+            // every reply has to be in the tables for the choice to be perfect
+            let reply = self.probe(&temp_board)?;
+            let rank = rank_move(board, &temp_board, reply);
+
+            match best {
+                Some((_, best_rank)) if best_rank >= rank => {}
+                _ => best = Some((mov, rank)),
+            }
+        }
+
+        best.map(|(mov, _)| mov)
+    }
+}
+
+// orders moves by whether they win in time, then by the distance to the
+// next reset of the fifty-move count and last by the reply's own distance
+fn rank_move(board: &Board, after: &Board, reply: Probe) -> (i32, i32, i32) {
+    let dtz = if after.status() == GameStatus::Won {
+        1
+    } else if after.halfmove_clock() == 0 {
+        WDL_TO_DTZ[4 - reply.wdl as usize]
+    } else {
+        match -reply.dtz {
+            dtz if dtz > 0 => dtz + 1,
+            dtz if dtz < 0 => dtz - 1,
+            _ => 0,
+        }
+    };
+
+    let clock = board.halfmove_clock() as i32;
+    let class = match dtz {
+        dtz if dtz > 0 && dtz + clock <= 100 => 4,
+        dtz if dtz > 0 => 3,
+        0 => 2,
+        dtz if clock - dtz > 100 => 1,
+        _ => 0,
+    };
+
+    (class, -dtz, reply.dtz)
+}
+
+/// Endgame tables in the Syzygy format, probed for the result and for the
+/// distance to the next capture, pawn move or mate.
+///
+/// Files are added by name, like `KQvKR.rtbw` for results and `KQvKR.rtbz`
+/// for distances, and each is only read the first time a position needs it.
+/// A file that turns out to be unreadable is treated as missing. Positions
+/// with castling rights aren't in the tables.
+#[derive(Default)]
+pub struct SyzygyTablebase {
+    // keyed by the material as named, so either colouring is found by
+    // looking up the position's material and then its colours swapped
+    tables: HashMap<Material, Entry>,
+    max_pieces: usize,
+}
+
+#[derive(Default)]
+struct Entry {
+    wdl: LazyTable,
+    dtz: LazyTable,
+}
+
+#[derive(Default)]
+struct LazyTable {
+    source: Mutex<Option<Source>>,
+    table: OnceLock<Option<Table>>,
+}
+
+enum Source {
+    Bytes(Cow<'static, [u8]>),
+    #[cfg(not(target_arch = "wasm32"))]
+    File(std::path::PathBuf),
+}
+
+impl Source {
+    fn read(self) -> Result<Cow<'static, [u8]>, TablebaseError> {
+        match self {
+            Source::Bytes(bytes) => Ok(bytes),
+            #[cfg(not(target_arch = "wasm32"))]
+            Source::File(path) => std::fs::read(path)
+                .map(Cow::Owned)
+                .map_err(|err| TablebaseError::Io(err.to_string())),
+        }
+    }
+}
+
+impl LazyTable {
+    fn new(source: Source) -> Self {
+        Self {
+            source: Mutex::new(Some(source)),
+            table: OnceLock::new(),
+        }
+    }
+
+    fn get(&self, material: &Material, kind: Kind) -> Option<&Table> {
+        self.table
+            .get_or_init(|| {
+                let source = self
+                    .source
+                    .lock()
+                    .expect("table source lock should not be poisoned")
+                    .take()?;
+
+                source
+                    .read()
+                    .and_then(|data| Table::new(material, kind, data))
+                    .ok()
+            })
+            .as_ref()
+    }
+}
+
+impl SyzygyTablebase {
+    /// Tables for a king and one piece or pawn against a bare king.
+    pub fn bundled() -> Self {
+        let mut tablebase = Self::default();
+
+        for (name, bytes) in BUNDLED {
+            tablebase
+                .add_bytes(name, Cow::Borrowed(bytes))
+                .expect("bundled tables should be named properly");
+        }
+
+        tablebase
+    }
+
+    pub fn add_bytes(
+        &mut self,
+        file_name: &str,
+        bytes: Cow<'static, [u8]>,
+    ) -> Result<(), TablebaseError> {
+        self.add(file_name, Source::Bytes(bytes))
+    }
+
+    /// Adds every table file in a directory, returning how many there were.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn add_directory(
+        &mut self,
+        path: impl AsRef<std::path::Path>,
+    ) -> Result<usize, TablebaseError> {
+        let entries = std::fs::read_dir(path).map_err(|err| TablebaseError::Io(err.to_string()))?;
+        let mut added = 0;
+
+        for entry in entries {
+            let path = entry
+                .map_err(|err| TablebaseError::Io(err.to_string()))?
+                .path();
+            let Some(file_name) = path.file_name().and_then(|name| name.to_str()) else {
+                continue;
+            };
+            let file_name = file_name.to_string();
+
+            let is_table = [Kind::Wdl, Kind::Dtz]
+                .iter()
+                .any(|kind| file_name.ends_with(&format!(".{}", kind.extension())));
+
+            if is_table {
+                self.add(&file_name, Source::File(path))?;
+                added += 1;
+            }
+        }
+
+        Ok(added)
+    }
+
+    fn add(&mut self, file_name: &str, source: Source) -> Result<(), TablebaseError> {
+        let invalid = || TablebaseError::InvalidName(file_name.to_string());
+
+        let (name, extension) = file_name.rsplit_once('.').ok_or_else(invalid)?;
+        let kind = [Kind::Wdl, Kind::Dtz]
+            .into_iter()
+            .find(|kind| kind.extension() == extension)
+            .ok_or_else(invalid)?;
+        // pieces have to be in the usual order, so each ending has one name
+        let material = Material::from_name(name)
+            .filter(|material| material.name() == name)
+            .ok_or_else(invalid)?;
+
+        let entry = self.tables.entry(material).or_default();
+        match kind {
+            Kind::Wdl => entry.wdl = LazyTable::new(source),
+            Kind::Dtz => entry.dtz = LazyTable::new(source),
+        }
+
+        self.max_pieces = self.max_pieces.max(material.pieces());
+        Ok(())
+    }
+
+    // the table holding a material balance, the material as the table names
+    // it and whether the position's colours are swapped to match
+    fn entry(&self, material: &Material) -> Option<(&Entry, Material, bool)> {
+        if let Some(entry) = self.tables.get(material) {
+            return Some((entry, *material, false));
+        }
+
+        let flipped = material.flipped();
+        self.tables
+            .get(&flipped)
+            .map(|entry| (entry, flipped, true))
+    }
+
+    // result from the table alone, so captures and en passant aren't looked at
+    fn probe_wdl_table(&self, board: &Board) -> Option<i32> {
+        let material = Material::from_board(board);
+        if material.pieces() == 2 {
+            return Some(0);
+        }
+
+        let (entry, named, flipped) = self.entry(&material)?;
+        let table = entry.wdl.get(&named, Kind::Wdl)?;
+        let (flip, side) = orientation(&named, flipped, board.side_to_move());
+
+        table
+            .wdl(side, |code| squares(board, code, flip, table.has_pawns()))
+            .ok()
+    }
+
+    // stored distance for a position with result `wdl`, or `Some(None)` when
+    // the table only has the other side to move
+    fn probe_dtz_table(&self, board: &Board, wdl: i32) -> Option<Option<i32>> {
+        let material = Material::from_board(board);
+        let (entry, named, flipped) = self.entry(&material)?;
+        let table = entry.dtz.get(&named, Kind::Dtz)?;
+        let (flip, side) = orientation(&named, flipped, board.side_to_move());
+
+        table
+            .dtz(side, wdl, |code| {
+                squares(board, code, flip, table.has_pawns())
+            })
+            .ok()
+    }
+
+    // alpha-beta over captures, since tables may store anything for
+    // positions where a capture is the best move
+    fn probe_ab(&self, board: &Board, mut alpha: i32, beta: i32) -> Option<i32> {
+        for mov in legal_moves(board) {
+            if !is_capture(board, mov) {
+                continue;
+            }
+
+            let mut temp_board = board.clone();
+            temp_board.play(mov);
+
+            let value = -self.probe_ab(&temp_board, -beta, -alpha)?;
+            if value > alpha {
+                if value >= beta {
+                    return Some(value);
+                }
+                alpha = value;
+            }
+        }
+
+        let value = self.probe_wdl_table(board)?;
+        Some(alpha.max(value))
+    }
+
+    // result for the side to move, and whether it comes from a capture or
+    // en passant that should be played straight away
+    fn probe_wdl(&self, board: &Board) -> Option<(i32, bool)> {
+        let moves = legal_moves(board);
+        let mut best_capture = -3;
+        let mut best_en_passant = -3;
+
+        for &mov in &moves {
+            if !is_capture(board, mov) {
+                continue;
+            }
+
+            let mut temp_board = board.clone();
+            temp_board.play(mov);
+
+            let value = -self.probe_ab(&temp_board, -2, -best_capture)?;
+            if value == 2 {
+                return Some((2, true));
+            }
+
+            match is_en_passant(board, mov) {
+                false => best_capture = best_capture.max(value),
+                true => best_en_passant = best_en_passant.max(value),
+            }
+        }
+
+        let value = self.probe_wdl_table(board)?;
+
+        if best_en_passant > best_capture {
+            if best_en_passant > value {
+                return Some((best_en_passant, true));
+            }
+            best_capture = best_en_passant;
+        }
+
+        if best_capture >= value {
+            return Some((best_capture, best_capture > 0));
+        }
+
+        // the table has the position as stalemate, but en passant is allowed
+        let only_en_passant = moves.iter().all(|mov| is_en_passant(board, *mov));
+        if best_en_passant > -3 && value == 0 && only_en_passant {
+            return Some((best_en_passant, true));
+        }
+
+        Some((value, false))
+    }
+
+    // result and distance for the side to move
+    fn probe_dtz(&self, board: &Board) -> Option<(i32, i32)> {
+        let (wdl, zeroing) = self.probe_wdl(board)?;
+        let result = (wdl + 2) as usize;
+
+        if wdl == 0 {
+            return Some((0, 0));
+        }
+
+        if zeroing {
+            return Some((wdl, WDL_TO_DTZ[result]));
+        }
+
+        let moves = legal_moves(board);
+        let is_pawn_move = |mov: Move| board.piece_on(mov.from) == Some(Piece::Pawn);
+
+        // a winning pawn move resets the count straight away
+        if wdl > 0 {
+            for &mov in &moves {
+                if !is_pawn_move(mov) || is_capture(board, mov) {
+                    continue;
+                }
+
+                let mut temp_board = board.clone();
+                temp_board.play(mov);
+
+                if -self.probe_wdl(&temp_board)?.0 == wdl {
+                    return Some((wdl, WDL_TO_DTZ[result]));
+                }
+            }
+        }
+
+        if let Some(dtz) = self.probe_dtz_table(board, wdl)? {
+            let dtz = match wdl > 0 {
+                true => WDL_TO_DTZ[result] + dtz,
+                false => WDL_TO_DTZ[result] - dtz,
+            };
+            return Some((wdl, dtz));
+        }
+
+        // only the other side to move is stored, so look one move ahead.
+        // moves that reset the count are already covered by the result
+        let mut best = match wdl > 0 {
+            true => i32::MAX,
+            false => WDL_TO_DTZ[result],
+        };
+
+        for mov in moves {
+            if is_capture(board, mov) || is_pawn_move(mov) {
+                continue;
+            }
+
+            let mut temp_board = board.clone();
+            temp_board.play(mov);
+
+            let value = -self.probe_dtz(&temp_board)?.1;
+
+            if value == 1 && temp_board.status() == GameStatus::Won {
+                best = 1;
+            } else if wdl > 0 {
+                if value > 0 && value + 1 < best {
+                    best = value + 1;
+                }
+            } else if value - 1 < best {
+                best = value - 1;
+            }
+        }
+
+        Some((wdl, best))
+    }
+}
+
+impl Tablebase for SyzygyTablebase {
+    fn max_pieces(&self) -> usize {
+        self.max_pieces
+    }
+
+    fn probe(&self, board: &Board) -> Option<Probe> {
+        let castling = Color::ALL.into_iter().any(|color| {
+            let rights = board.castle_rights(color);
+            rights.short.is_some() || rights.long.is_some()
+        });
+
+        if castling || board.occupied().len() as usize > self.max_pieces {
+            return None;
+        }
+
+        let (wdl, dtz) = self.probe_dtz(board)?;
+
+        Some(Probe {
+            wdl: Wdl::from_value(wdl),
+            dtz,
+        })
+    }
+}
+
+// whether the board's colours are swapped to match the table, and which side
+// is to move in the table's colours. symmetric tables only store white to move
+fn orientation(material: &Material, flipped: bool, turn: Color) -> (bool, usize) {
+    match material.is_symmetric() {
+        true => (turn == Color::Black, 0),
+        false => (flipped, ((turn == Color::White) == flipped) as usize),
+    }
+}
+
+// squares of a piece code from a table header, where 8 marks black. pawns
+// only move one way, so swapping colours turns the board around as well
+fn squares(board: &Board, code: u8, flip: bool, has_pawns: bool) -> BitBoard {
+    let Some(piece) = Piece::ALL.get((code & 7).wrapping_sub(1) as usize) else {
+        return BitBoard::EMPTY;
+    };
+
+    let color = match (code & 8 != 0) != flip {
+        false => Color::White,
+        true => Color::Black,
+    };
+
+    let pieces = board.colored_pieces(color, *piece);
+    match flip && has_pawns {
+        true => pieces.flip_ranks(),
+        false => pieces,
+    }
+}
+
+fn legal_moves(board: &Board) -> Vec<Move> {
+    let mut moves = Vec::new();
+    board.generate_moves(|piece_moves| {
+        moves.extend(piece_moves);
+        false
+    });
+    moves
+}
+
+fn is_en_passant(board: &Board, mov: Move) -> bool {
+    board.piece_on(mov.from) == Some(Piece::Pawn)
+        && mov.from.file() != mov.to.file()
+        && board.piece_on(mov.to).is_none()
+}
+
+fn is_capture(board: &Board, mov: Move) -> bool {
+    board.colors(!board.side_to_move()).has(mov.to) || is_en_passant(board, mov)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn probe(fen: &str) -> Option<Probe> {
+        let tablebase = SyzygyTablebase::bundled();
+        tablebase.probe(&Board::from_fen(fen, false).unwrap())
+    }
+
+    // set WRITE_TABLEBASE to regenerate the files, then run the tests again
+    // so the new files are compiled in
+    #[test]
+    fn test_bundled_tables_are_generated() {
+        // pawn tables promote into the piece tables, so those come first
+        let mut tablebase = SyzygyTablebase::default();
+        let mut changed = Vec::new();
+
+        for (wdl_name, _) in BUNDLED.into_iter().step_by(2) {
+            let name = wdl_name.trim_end_matches(".rtbw");
+            let material = Material::from_name(name).unwrap();
+            let (wdl, dtz) = generate::generate(&tablebase, &material);
+
+            for (kind, bytes) in [(Kind::Wdl, wdl), (Kind::Dtz, dtz)] {
+                let file_name = format!("{}.{}", name, kind.extension());
+                assert!(
+                    bytes.len() < 40_000,
+                    "{} is {} bytes",
+                    file_name,
+                    bytes.len()
+                );
+
+                if std::env::var_os("WRITE_TABLEBASE").is_some() {
+                    let path = format!(
+                        "{}/src/algorithm/tablebase/{}",
+                        env!("CARGO_MANIFEST_DIR"),
+                        file_name
+                    );
+                    std::fs::write(path, &bytes).unwrap();
+                }
+
+                let bundled = BUNDLED.iter().find(|(bundled, _)| *bundled == file_name);
+                if bundled.unwrap().1 != bytes {
+                    changed.push(file_name.clone());
+                }
+
+                tablebase.add_bytes(&file_name, Cow::Owned(bytes)).unwrap();
+            }
+        }
+
+        assert!(changed.is_empty(), "{:?} changed", changed);
+    }
+
+    #[test]
+    fn test_longest_wins() {
+        // KQK is mate in at most 10 moves and KRK in at most 16, while a lone
+        // minor piece can't win at all
+        let tablebase = SyzygyTablebase::bundled();
+
+        for (name, longest) in [("KQvK", 19), ("KRvK", 31), ("KBvK", 0), ("KNvK", 0)] {
+            let material = Material::from_name(name).unwrap();
+            let table = tablebase.tables[&material]
+                .dtz
+                .get(&material, Kind::Dtz)
+                .unwrap();
+            let wdl = tablebase.tables[&material]
+                .wdl
+                .get(&material, Kind::Wdl)
+                .unwrap();
+
+            let mut max = 0;
+            for king in 0..64 {
+                for other_king in 0..64 {
+                    for piece in 0..64 {
+                        let squares = |code: u8| match code {
+                            6 => BitBoard(1 << king),
+                            14 => BitBoard(1 << other_king),
+                            _ => BitBoard(1 << piece),
+                        };
+
+                        if king == other_king || king == piece || other_king == piece {
+                            continue;
+                        }
+
+                        if wdl.wdl(0, squares).unwrap() == 2 {
+                            let stored = table.dtz(0, 2, squares).unwrap().unwrap();
+                            max = max.max(WDL_TO_DTZ[4] + stored);
+                        }
+                    }
+                }
+            }
+
+            // illegal positions repeat values from legal ones, so they
+            // don't change the largest
+            assert_eq!(max, longest, "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_probe_known_positions() {
+        let win = |dtz| Some(Probe { wdl: Wdl::Win, dtz });
+        let loss = |dtz| {
+            Some(Probe {
+                wdl: Wdl::Loss,
+                dtz,
+            })
+        };
+        let draw = Some(Probe {
+            wdl: Wdl::Draw,
+            dtz: 0,
+        });
+
+        // Rh8 is mate
+        assert_eq!(probe("k7/8/1K6/8/8/8/8/7R w - - 0 1"), win(1));
+
+        // same position with colours reversed
+        assert_eq!(probe("7r/8/8/8/8/1k6/8/K7 b - - 0 1"), win(1));
+
+        // already mated
+        assert_eq!(probe("k6Q/8/1K6/8/8/8/8/8 b - - 0 1"), loss(-1));
+
+        // mated after Rh8, and every black move allows it
+        assert_eq!(
+            probe("k7/8/1K6/8/8/8/8/7R b - - 0 1").map(|p| p.wdl),
+            Some(Wdl::Loss)
+        );
+        assert_eq!(probe("k7/8/1K6/8/8/8/8/6R1 b - - 0 1"), loss(-2));
+
+        // the king takes the undefended rook
+        assert_eq!(probe("8/8/8/8/8/1k6/1R6/7K b - - 0 1"), draw);
+
+        // a lone minor piece and a bare king
+        assert_eq!(probe("8/8/3k4/8/8/2N5/8/4K3 w - - 0 1"), draw);
+        assert_eq!(probe("8/8/8/8/8/8/8/k1K5 w - - 0 1"), draw);
+
+        // promoting wins at once, so the count is reset on the first move
+        assert_eq!(probe("8/4P3/8/8/8/k7/8/7K w - - 0 1"), win(1));
+
+        // the king in front of its pawn on the sixth rank wins either way,
+        // while the defending king in front of a rook pawn holds
+        assert_eq!(
+            probe("4k3/8/4K3/4P3/8/8/8/8 w - - 0 1").map(|p| p.wdl),
+            Some(Wdl::Win)
+        );
+        assert_eq!(
+            probe("4k3/8/4K3/4P3/8/8/8/8 b - - 0 1").map(|p| p.wdl),
+            Some(Wdl::Loss)
+        );
+        assert_eq!(probe("k7/8/K7/P7/8/8/8/8 w - - 0 1"), draw);
+        assert_eq!(
+            probe("8/8/8/p7/k7/8/8/K7 b - - 0 1"),
+            probe("k7/8/K7/P7/8/8/8/8 w - - 0 1")
+        );
+
+        // black pawns are found by turning the board around
+        assert_eq!(probe("8/8/8/8/8/k7/4p3/7K b - - 0 1"), win(1));
+
+        // too many pieces
+        assert_eq!(probe("k7/8/1K6/8/8/8/P7/7R w - - 0 1"), None);
+
+        // castling isn't in the tables
+        assert_eq!(probe("4k3/8/8/8/8/8/8/R3K3 w Q - 0 1"), None);
+    }
+
+    #[test]
+    fn test_best_move() {
+        let tablebase = SyzygyTablebase::bundled();
+
+        let board = Board::from_fen("k7/8/1K6/8/8/8/8/7R w - - 0 1", false).unwrap();
+        assert_eq!(tablebase.best_move(&board), Some("h1h8".parse().unwrap()));
+
+        // Qc7 would be stalemate
+        let board = Board::from_fen("k7/8/1K6/8/8/8/8/2Q5 w - - 0 1", false).unwrap();
+        let mov = tablebase.best_move(&board).unwrap();
+        assert_ne!(mov, "c1c7".parse().unwrap());
+
+        let mut board = board;
+        board.play(mov);
+        assert_eq!(
+            tablebase.probe(&board).map(|probe| probe.wdl),
+            Some(Wdl::Loss)
+        );
+
+        // any promotion wins, but a queen mates soonest
+        let board = Board::from_fen("8/4P3/8/8/8/k7/8/7K w - - 0 1", false).unwrap();
+        assert_eq!(tablebase.best_move(&board), Some("e7e8q".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_converts_endings() {
+        let tablebase = SyzygyTablebase::bundled();
+
+        for fen in [
+            "8/8/3k4/8/8/8/8/R3K3 w - - 0 1",
+            "4k3/8/4K3/4P3/8/8/8/8 w - - 0 1",
+        ] {
+            let mut board = Board::from_fen(fen, false).unwrap();
+            assert_eq!(tablebase.probe(&board).unwrap().wdl, Wdl::Win, "{}", fen);
+
+            // both sides play perfectly, and the distance counts down until
+            // the next capture, pawn move or mate
+            while board.status() == GameStatus::Ongoing {
+                let before = tablebase.probe(&board).unwrap();
+                let mov = tablebase.best_move(&board).unwrap();
+                board.play(mov);
+
+                if board.halfmove_clock() > 0 && board.status() == GameStatus::Ongoing {
+                    let after = tablebase.probe(&board).unwrap();
+                    assert_eq!(after.dtz, -before.dtz + before.dtz.signum(), "{}", fen);
+                }
+            }
+
+            assert_eq!(board.status(), GameStatus::Won, "{}", fen);
+        }
+    }
+
+    #[test]
+    fn test_add_files() {
+        let mut tablebase = SyzygyTablebase::default();
+        assert_eq!(tablebase.max_pieces(), 0);
+
+        let error = tablebase.add_bytes("KQK.rtbw", Cow::Borrowed(&[]));
+        assert_eq!(
+            error,
+            Err(TablebaseError::InvalidName("KQK.rtbw".to_string()))
+        );
+        assert!(tablebase.add_bytes("KQvK.txt", Cow::Borrowed(&[])).is_err());
+        assert!(tablebase
+            .add_bytes("KRQvK.rtbw", Cow::Borrowed(&[]))
+            .is_err());
+
+        // unreadable files are only found when probed, and then left out
+        tablebase
+            .add_bytes("KQvK.rtbw", Cow::Borrowed(&[1, 2, 3]))
+            .unwrap();
+        assert_eq!(tablebase.max_pieces(), 3);
+
+        let board = Board::from_fen("k7/8/1K6/8/8/8/8/6Q1 w - - 0 1", false).unwrap();
+        assert_eq!(tablebase.probe(&board), None);
+    }
+}
@@ -0,0 +1,1016 @@
+use super::{
+    table::{Encoding, Kind, Material, MAX_PIECES},
+    SyzygyTablebase,
+};
+use cozy_chess::{
+    get_bishop_moves, get_king_moves, get_knight_moves, get_pawn_attacks, get_rook_moves, BitBoard,
+    Board, BoardBuilder, Color, Piece, Square,
+};
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap, VecDeque},
+};
+
+// piece codes in table headers, with black pieces having bit 3 set
+const PAWN: u8 = 1;
+const KING: u8 = 6;
+const BLACK: u8 = 8;
+
+// compressed blocks are 64 bytes, and the index has an entry every 1024 values
+const BLOCK_SIZE: u8 = 6;
+const IDX_BITS: u8 = 10;
+// leaves room for the index entry past the last block
+const MAX_BLOCK_VALUES: usize = 65536 - (1 << IDX_BITS);
+
+// symbols stand for at most 256 values, and there can't be 4096 of them since
+// that number marks a single value
+const MAX_SYMBOL_LEN: usize = 256;
+const MAX_SYMBOLS: usize = 4095;
+const MAX_CODE_LEN: usize = 32;
+
+/// Solves an ending by retrograde analysis and writes its result and
+/// distance tables. Captures and promotions are looked up in `tablebase`,
+/// which has to hold every table they lead to.
+pub fn generate(tablebase: &SyzygyTablebase, material: &Material) -> (Vec<u8>, Vec<u8>) {
+    let codes = piece_codes(material);
+    let header = header_bytes(material, &codes);
+    let has_pawns = codes[0] & 7 == PAWN;
+    let symmetric = material.is_symmetric();
+
+    let wdl_start = file_start(Kind::Wdl, !symmetric, has_pawns, &header);
+    let (encoding, _) =
+        Encoding::read(material, Kind::Wdl, &wdl_start).expect("header should be valid");
+
+    let solution = Solution::new(tablebase, &encoding, &codes, symmetric);
+
+    // every stored side of every file, with both sides for results
+    let sides = if symmetric { 1 } else { 2 };
+    let mut wdl_values = Vec::new();
+    let mut dtz_values = Vec::new();
+
+    for file in 0..encoding.files() {
+        for side in 0..sides {
+            let nodes = solution.nodes(file, side);
+            let results = nodes.clone().map(|node| solution.result(node));
+            wdl_values.push(fill_unused(
+                results.map(|result| result.map(|wdl| (wdl + 2) as u16)),
+            ));
+
+            if side == 0 {
+                let distances = nodes.map(|node| match solution.result(node) {
+                    Some(0) | None => None,
+                    Some(_) => Some(solution.distance[node].max(1) as u16 - 1),
+                });
+                dtz_values.push(fill_unused(distances));
+            }
+        }
+    }
+
+    // distances are stored for white to move, and are exact for wins and losses
+    let dtz_flags = 4 | 8;
+    let wdl_sections = wdl_values
+        .iter()
+        .map(|values| compress(values, Kind::Wdl, 0))
+        .collect();
+    let dtz_sections = dtz_values
+        .iter()
+        .map(|values| compress(values, Kind::Dtz, dtz_flags))
+        .collect();
+
+    (
+        write_file(wdl_start, wdl_sections),
+        write_file(
+            file_start(Kind::Dtz, !symmetric, has_pawns, &header),
+            dtz_sections,
+        ),
+    )
+}
+
+// the order pieces are placed in: pawns first in pawn tables, then the kings,
+// then any other piece without a twin, and then the rest grouped by kind
+fn piece_codes(material: &Material) -> Vec<u8> {
+    let mut groups = Vec::new();
+    for color in Color::ALL {
+        for piece in Piece::ALL {
+            let code = piece as u8 + 1 + BLACK * color as u8;
+            groups.push((code, material.count(color, piece)));
+        }
+    }
+
+    let white_pawns = material.count(Color::White, Piece::Pawn);
+    let black_pawns = material.count(Color::Black, Piece::Pawn);
+    let leading = match black_pawns == 0 || (white_pawns > 0 && black_pawns >= white_pawns) {
+        true => PAWN,
+        false => PAWN | BLACK,
+    };
+
+    groups.sort_by_key(|(code, count)| {
+        let pawn = code & 7 == PAWN;
+        let rank = match (pawn, *code == leading, code & 7 == KING, *count == 1) {
+            (true, true, _, _) => 0,
+            (true, false, _, _) => 1,
+            (_, _, true, _) => 2,
+            (_, _, _, true) => 3,
+            _ => 4,
+        };
+        (rank, *code)
+    });
+
+    groups
+        .into_iter()
+        .flat_map(|(code, count)| std::iter::repeat_n(code, count))
+        .collect()
+}
+
+// order bytes and piece bytes for one file, the same for both sides to move
+fn header_bytes(material: &Material, codes: &[u8]) -> Vec<u8> {
+    let both_pawns = material.count(Color::White, Piece::Pawn) > 0
+        && material.count(Color::Black, Piece::Pawn) > 0;
+
+    // the leading group is indexed first and the other pawns second
+    let mut header = vec![0];
+    if both_pawns {
+        header.push(0x11);
+    }
+
+    header.extend(codes.iter().map(|code| code | (code << 4)));
+    header
+}
+
+fn file_start(kind: Kind, split: bool, has_pawns: bool, header: &[u8]) -> Vec<u8> {
+    let files = if has_pawns { 4 } else { 1 };
+
+    let mut bytes = kind.magic().to_vec();
+    bytes.push(split as u8 | ((has_pawns as u8) << 1));
+    for _ in 0..files {
+        bytes.extend(header);
+    }
+
+    if bytes.len() % 2 == 1 {
+        bytes.push(0);
+    }
+    bytes
+}
+
+// positions that never come up can hold anything, so they repeat the value
+// before them to keep runs long
+fn fill_unused(values: impl Iterator<Item = Option<u16>>) -> Vec<u16> {
+    let values = values.collect::<Vec<_>>();
+    let mut last = values.iter().flatten().next().copied().unwrap_or_default();
+
+    values
+        .into_iter()
+        .map(|value| {
+            last = value.unwrap_or(last);
+            last
+        })
+        .collect()
+}
+
+/// Position for the generator's own move generation, which is much quicker
+/// than building boards for every position in a table.
+#[derive(Clone, Copy, Debug)]
+struct Position {
+    // piece codes and squares
+    pieces: [(u8, u8); MAX_PIECES],
+    len: usize,
+    turn: u8,
+    // square a pawn that just moved two squares can be taken on
+    en_passant: Option<u8>,
+}
+
+struct Child {
+    position: Position,
+    // captures and pawn moves reset the fifty-move count
+    zeroing: bool,
+    // captures and promotions go to another table
+    changes_material: bool,
+    en_passant: bool,
+}
+
+impl Position {
+    fn squares(&self, code: u8) -> BitBoard {
+        self.pieces[..self.len]
+            .iter()
+            .filter(|(other, _)| *other == code)
+            .fold(BitBoard::EMPTY, |squares, (_, square)| {
+                squares | Square::index(*square as usize).bitboard()
+            })
+    }
+
+    fn color(&self, color: u8) -> BitBoard {
+        self.pieces[..self.len]
+            .iter()
+            .filter(|(code, _)| code >> 3 == color)
+            .fold(BitBoard::EMPTY, |squares, (_, square)| {
+                squares | Square::index(*square as usize).bitboard()
+            })
+    }
+
+    fn attacks(code: u8, square: Square, occupied: BitBoard) -> BitBoard {
+        let color = match code >> 3 {
+            0 => Color::White,
+            _ => Color::Black,
+        };
+
+        match code & 7 {
+            PAWN => get_pawn_attacks(square, color),
+            2 => get_knight_moves(square),
+            3 => get_bishop_moves(square, occupied),
+            4 => get_rook_moves(square, occupied),
+            5 => get_bishop_moves(square, occupied) | get_rook_moves(square, occupied),
+            _ => get_king_moves(square),
+        }
+    }
+
+    fn in_check(&self, color: u8) -> bool {
+        let king = self.squares(KING | (color << 3));
+        let occupied = self.color(0) | self.color(1);
+
+        self.pieces[..self.len]
+            .iter()
+            .filter(|(code, _)| code >> 3 != color)
+            .any(|(code, square)| {
+                let attacks = Self::attacks(*code, Square::index(*square as usize), occupied);
+                !(attacks & king).is_empty()
+            })
+    }
+
+    fn is_legal(&self) -> bool {
+        !self.in_check(self.turn ^ 1)
+    }
+
+    // with the colours swapped and the board turned around
+    fn flipped(&self) -> Self {
+        let mut flipped = *self;
+        for (code, square) in &mut flipped.pieces[..self.len] {
+            *code ^= BLACK;
+            *square ^= 0x38;
+        }
+        flipped.turn ^= 1;
+        flipped.en_passant = self.en_passant.map(|square| square ^ 0x38);
+        flipped
+    }
+
+    fn board(&self) -> Board {
+        let mut builder = BoardBuilder::empty();
+
+        for (code, square) in &self.pieces[..self.len] {
+            let piece = Piece::index((code & 7) as usize - 1);
+            let color = Color::index((code >> 3) as usize);
+            *builder.square_mut(Square::index(*square as usize)) = Some((piece, color));
+        }
+
+        builder.side_to_move = Color::index(self.turn as usize);
+        builder
+            .build()
+            .expect("generated positions should be legal")
+    }
+
+    fn children(&self) -> Vec<Child> {
+        let own = self.color(self.turn);
+        let enemy = self.color(self.turn ^ 1);
+        let occupied = own | enemy;
+        let color = Color::index(self.turn as usize);
+        let forward: i8 = if self.turn == 0 { 8 } else { -8 };
+
+        let mut children = Vec::new();
+
+        for i in 0..self.len {
+            let (code, from) = self.pieces[i];
+            if code >> 3 != self.turn {
+                continue;
+            }
+
+            let from_square = Square::index(from as usize);
+            // target square, the square of the pawn taken en passant and the
+            // square left behind by a double step
+            let mut targets = Vec::new();
+
+            if code & 7 == PAWN {
+                let one = (from as i8 + forward) as u8;
+                if !occupied.has(Square::index(one as usize)) {
+                    targets.push((one, None, None));
+
+                    let start = if self.turn == 0 { 1 } else { 6 };
+                    let two = (one as i8 + forward) as u8;
+                    if from >> 3 == start && !occupied.has(Square::index(two as usize)) {
+                        targets.push((two, None, Some(one)));
+                    }
+                }
+
+                let attacks = get_pawn_attacks(from_square, color);
+                for square in attacks & enemy {
+                    targets.push((square as u8, None, None));
+                }
+
+                if let Some(en_passant) = self.en_passant {
+                    if attacks.has(Square::index(en_passant as usize)) {
+                        let taken = (en_passant as i8 - forward) as u8;
+                        targets.push((en_passant, Some(taken), None));
+                    }
+                }
+            } else {
+                for square in Self::attacks(code, from_square, occupied) & !own {
+                    targets.push((square as u8, None, None));
+                }
+            }
+
+            for (to, taken, skipped) in targets {
+                let taken = taken.or(Some(to).filter(|to| enemy.has(Square::index(*to as usize))));
+                let promotion = code & 7 == PAWN && (to >> 3 == 0 || to >> 3 == 7);
+
+                let promotions: &[u8] = match promotion {
+                    true => &[5, 4, 3, 2],
+                    false => &[code & 7],
+                };
+
+                for piece in promotions {
+                    let mut child = *self;
+                    child.turn ^= 1;
+                    child.en_passant = None;
+                    child.pieces[i] = (piece | (code & BLACK), to);
+
+                    if let Some(taken) = taken {
+                        let j = (0..child.len)
+                            .find(|j| child.pieces[*j].1 == taken && *j != i)
+                            .expect("should be piece to take");
+                        child.pieces[j] = child.pieces[child.len - 1];
+                        child.len -= 1;
+                    }
+
+                    if child.in_check(self.turn) {
+                        continue;
+                    }
+
+                    // only kept when a pawn could take, so equal positions
+                    // stay equal
+                    if let Some(skipped) = skipped {
+                        let takers = get_pawn_attacks(Square::index(skipped as usize), color)
+                            & child.squares(PAWN | ((self.turn ^ 1) << 3));
+                        if !takers.is_empty() {
+                            child.en_passant = Some(skipped);
+                        }
+                    }
+
+                    children.push(Child {
+                        position: child,
+                        zeroing: taken.is_some() || code & 7 == PAWN,
+                        changes_material: taken.is_some() || promotion,
+                        en_passant: taken.is_some() && taken != Some(to),
+                    });
+                }
+            }
+        }
+
+        children
+    }
+}
+
+// placing each piece in turn, with identical pieces in increasing order of
+// square and pawns off the first and last ranks
+fn place(codes: &[u8], position: &mut Position, visit: &mut impl FnMut(&Position)) {
+    let i = position.len;
+    if i == codes.len() {
+        for turn in 0..2 {
+            position.turn = turn;
+            if position.is_legal() {
+                visit(position);
+            }
+        }
+        return;
+    }
+
+    let code = codes[i];
+    let start = match i > 0 && codes[i - 1] == code {
+        true => position.pieces[i - 1].1 + 1,
+        false => 0,
+    };
+
+    for square in start..64 {
+        let on_back_rank = square < 8 || square >= 56;
+        let taken = position.pieces[..i]
+            .iter()
+            .any(|(_, other)| *other == square);
+
+        if taken || (code & 7 == PAWN && on_back_rank) {
+            continue;
+        }
+
+        position.pieces[i] = (code, square);
+        position.len += 1;
+        place(codes, position, visit);
+        position.len -= 1;
+    }
+}
+
+// no result yet in the retrograde analysis
+const UNKNOWN: i8 = i8::MIN;
+const NO_DISTANCE: u16 = u16::MAX;
+
+// a move to a position in the same table. the mover can't do better than
+// `cap`, which is below a win when the reply could take en passant
+#[derive(Clone, Copy)]
+struct Edge {
+    parent: u32,
+    child: u32,
+    zeroing: bool,
+    cap: i8,
+}
+
+/// Results and distances for every position in a table, numbered by file,
+/// side to move and index.
+struct Solution {
+    offsets: Vec<[usize; 2]>,
+    sizes: Vec<[usize; 2]>,
+    // results for the side to move are -2, 0 or 2, since the fifty-move
+    // rule doesn't come into play in the tables solved here
+    value: Vec<i8>,
+    distance: Vec<u16>,
+}
+
+impl Solution {
+    fn new(
+        tablebase: &SyzygyTablebase,
+        encoding: &Encoding,
+        codes: &[u8],
+        symmetric: bool,
+    ) -> Self {
+        let mut offsets = Vec::new();
+        let mut sizes = Vec::new();
+        let mut total = 0;
+
+        for file in 0..encoding.files() {
+            let mut file_offsets = [0; 2];
+            let mut file_sizes = [0; 2];
+
+            for side in 0..2 {
+                let size = encoding.size(file, side).unwrap_or_default() as usize;
+                file_offsets[side] = total;
+                file_sizes[side] = size;
+                total += size;
+            }
+
+            offsets.push(file_offsets);
+            sizes.push(file_sizes);
+        }
+
+        // symmetric tables only hold white to move
+        let node = |position: &Position| {
+            let position = match symmetric && position.turn == 1 {
+                true => position.flipped(),
+                false => *position,
+            };
+
+            let (file, index) = encoding
+                .index(position.turn as usize, |code| position.squares(code))
+                .expect("positions should have the table's material");
+            (
+                offsets[file][position.turn as usize] + index as usize,
+                position,
+            )
+        };
+
+        let mut representatives = vec![None; total];
+        let mut position = Position {
+            pieces: [(0, 0); MAX_PIECES],
+            len: 0,
+            turn: 0,
+            en_passant: None,
+        };
+
+        place(codes, &mut position, &mut |position| {
+            let (node, position) = node(position);
+            representatives[node].get_or_insert(position);
+        });
+
+        let mut value = vec![UNKNOWN; total];
+        let mut edges = Vec::new();
+        // best result of moves to other tables, and moves left that might not
+        // lose for positions still unknown
+        let mut external = vec![-3i8; total];
+        let mut open = vec![0u32; total];
+        let mut mated = vec![false; total];
+        let mut zeroing_win = vec![false; total];
+
+        for (parent, position) in representatives.iter().enumerate() {
+            let Some(position) = position else {
+                continue;
+            };
+
+            let children = position.children();
+            if children.is_empty() {
+                mated[parent] = position.in_check(position.turn);
+                value[parent] = if mated[parent] { -2 } else { 0 };
+                continue;
+            }
+
+            for child in children {
+                let (result, cap) = Self::reply(tablebase, &child);
+
+                match result {
+                    Some(result) => {
+                        external[parent] = external[parent].max(result);
+                        zeroing_win[parent] |= result == 2;
+                    }
+                    None => {
+                        let (child_node, _) = node(&child.position);
+                        if cap > -2 {
+                            open[parent] += 1;
+                        }
+                        edges.push(Edge {
+                            parent: parent as u32,
+                            child: child_node as u32,
+                            zeroing: child.zeroing,
+                            cap,
+                        });
+                    }
+                }
+            }
+        }
+
+        let parents = Self::parents(total, &edges, |_| true);
+        let mut queue = VecDeque::new();
+
+        for node in 0..total {
+            if representatives[node].is_none() || value[node] != UNKNOWN {
+                if value[node] == -2 {
+                    queue.push_back(node);
+                }
+                continue;
+            }
+
+            if external[node] == 2 {
+                value[node] = 2;
+                queue.push_back(node);
+            } else if open[node] == 0 && external[node] < 0 {
+                value[node] = -2;
+                queue.push_back(node);
+            }
+        }
+
+        // results, ignoring how long they take
+        while let Some(node) = queue.pop_front() {
+            for edge in &parents[node] {
+                let parent = edge.parent as usize;
+                if value[parent] != UNKNOWN || edge.cap == -2 {
+                    continue;
+                }
+
+                match (-value[node]).min(edge.cap) {
+                    2 => {
+                        value[parent] = 2;
+                        queue.push_back(parent);
+                    }
+                    -2 => {
+                        open[parent] -= 1;
+                        if open[parent] == 0 && external[parent] < 0 {
+                            value[parent] = -2;
+                            queue.push_back(parent);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        for node in 0..total {
+            if value[node] == UNKNOWN && representatives[node].is_some() {
+                value[node] = 0;
+            }
+        }
+
+        // distances only follow moves that keep the fifty-move count going,
+        // since a win by capture or pawn move is a distance of one
+        for edge in &edges {
+            let parent = edge.parent as usize;
+            let result = (-value[edge.child as usize]).min(edge.cap);
+            zeroing_win[parent] |= edge.zeroing && result == 2;
+        }
+
+        let parents = Self::parents(total, &edges, |edge| !edge.zeroing);
+        let mut distance = vec![NO_DISTANCE; total];
+        let mut remaining = vec![0u32; total];
+        for edge in edges.iter().filter(|edge| !edge.zeroing) {
+            remaining[edge.parent as usize] += 1;
+        }
+
+        let mut queue = VecDeque::new();
+        let mut later = Vec::new();
+
+        for node in 0..total {
+            match value[node] {
+                -2 if mated[node] => {
+                    distance[node] = 0;
+                    queue.push_back(node);
+                }
+                -2 if remaining[node] == 0 => {
+                    distance[node] = 1;
+                    later.push(node);
+                }
+                2 if zeroing_win[node] => {
+                    distance[node] = 1;
+                    later.push(node);
+                }
+                _ => {}
+            }
+        }
+        queue.extend(later);
+
+        while let Some(node) = queue.pop_front() {
+            for edge in &parents[node] {
+                let parent = edge.parent as usize;
+                if distance[parent] != NO_DISTANCE {
+                    continue;
+                }
+
+                match (value[node], value[parent]) {
+                    (-2, 2) => {
+                        distance[parent] = distance[node] + 1;
+                        queue.push_back(parent);
+                    }
+                    (2, -2) => {
+                        remaining[parent] -= 1;
+                        if remaining[parent] == 0 {
+                            distance[parent] = distance[node] + 1;
+                            queue.push_back(parent);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        for node in 0..total {
+            if value[node] == 2 || value[node] == -2 {
+                assert!(
+                    distance[node] <= 100,
+                    "cursed results aren't supported by the generator"
+                );
+            }
+        }
+
+        Self {
+            offsets,
+            sizes,
+            value,
+            distance,
+        }
+    }
+
+    // result of a move for the side making it when it leaves the table, or
+    // the best it can do when the reply could take en passant
+    fn reply(tablebase: &SyzygyTablebase, child: &Child) -> (Option<i8>, i8) {
+        let probe = |position: &Position| {
+            let (result, _) = tablebase
+                .probe_wdl(&position.board())
+                .expect("tables for captures and promotions should be there");
+            assert!(
+                result % 2 == 0,
+                "cursed results aren't supported by the generator"
+            );
+            result as i8
+        };
+
+        if child.changes_material {
+            return (Some(-probe(&child.position)), 2);
+        }
+
+        if child.position.en_passant.is_none() {
+            return (None, 2);
+        }
+
+        let replies = child.position.children();
+        let taking = replies
+            .iter()
+            .filter(|reply| reply.en_passant)
+            .map(|reply| -probe(&reply.position))
+            .max();
+
+        match taking {
+            None => (None, 2),
+            Some(taking) if replies.iter().all(|reply| reply.en_passant) => (Some(-taking), 2),
+            Some(taking) => (None, -taking),
+        }
+    }
+
+    fn parents(total: usize, edges: &[Edge], keep: impl Fn(&Edge) -> bool) -> Vec<Vec<Edge>> {
+        let mut parents = vec![Vec::new(); total];
+        for edge in edges.iter().filter(|edge| keep(edge)) {
+            parents[edge.child as usize].push(*edge);
+        }
+        parents
+    }
+
+    fn nodes(&self, file: usize, side: usize) -> std::ops::Range<usize> {
+        let start = self.offsets[file][side];
+        start..start + self.sizes[file][side]
+    }
+
+    fn result(&self, node: usize) -> Option<i8> {
+        Some(self.value[node]).filter(|value| *value != UNKNOWN)
+    }
+}
+
+/// A sub-table's compressed values: its pairs header, index table, size
+/// table and blocks.
+struct Section {
+    header: Vec<u8>,
+    index: Vec<u8>,
+    sizes: Vec<u8>,
+    blocks: Vec<u8>,
+}
+
+#[derive(Clone, Copy)]
+enum Symbol {
+    Value(u16),
+    Pair(usize, usize),
+}
+
+fn compress(values: &[u16], kind: Kind, flags: u8) -> Section {
+    let empty = |header| Section {
+        header,
+        index: Vec::new(),
+        sizes: Vec::new(),
+        blocks: Vec::new(),
+    };
+
+    if values.iter().all(|value| *value == values[0]) {
+        match kind {
+            Kind::Wdl => return empty(vec![flags | 0x80, values[0] as u8]),
+            Kind::Dtz if values[0] == 0 => return empty(vec![flags | 0x80, 0]),
+            Kind::Dtz => {}
+        }
+    }
+
+    let (symbols, lengths, sequence) = pair_symbols(values);
+    let code_lengths = code_lengths(&sequence, symbols.len());
+
+    // symbols are numbered by code length, longest first, with the ones that
+    // only appear inside pairs at the end
+    let mut order = (0..symbols.len()).collect::<Vec<_>>();
+    order.sort_by_key(|symbol| {
+        let len = code_lengths[*symbol];
+        (len == 0, Reverse(len), *symbol)
+    });
+    let mut number = vec![0; symbols.len()];
+    for (i, symbol) in order.iter().enumerate() {
+        number[*symbol] = i;
+    }
+
+    let min_len = *code_lengths.iter().filter(|len| **len > 0).min().unwrap();
+    let max_len = *code_lengths.iter().max().unwrap();
+
+    // the first symbol of each length, and canonical codes counted up from
+    // the smallest code of that length
+    let count = |len: usize| code_lengths.iter().filter(|other| **other == len).count();
+    let mut offsets = vec![0; max_len + 2];
+    let mut base = vec![0u64; max_len + 2];
+    for len in (min_len..=max_len).rev() {
+        offsets[len] = offsets[len + 1] + count(len + 1);
+        base[len] = (base[len + 1] + count(len + 1) as u64) / 2;
+    }
+
+    let mut codes = vec![(0u64, 0usize); symbols.len()];
+    for symbol in &order {
+        let len = code_lengths[*symbol];
+        if len > 0 {
+            codes[*symbol] = (base[len] + (number[*symbol] - offsets[len]) as u64, len);
+        }
+    }
+
+    // whole symbols are packed into blocks, most significant bit first
+    let block_bits = 8 << BLOCK_SIZE;
+    let mut blocks = Vec::new();
+    let mut block_values = Vec::new();
+    let mut bits = Vec::new();
+    let mut in_block = 0;
+
+    for symbol in &sequence {
+        let (code, len) = codes[*symbol];
+        let symbol_values = lengths[*symbol];
+
+        if bits.len() + len > block_bits || in_block + symbol_values > MAX_BLOCK_VALUES {
+            block_values.push(in_block);
+            blocks.push(std::mem::take(&mut bits));
+            in_block = 0;
+        }
+
+        bits.extend((0..len).rev().map(|bit| (code >> bit) & 1 == 1));
+        in_block += symbol_values;
+    }
+    block_values.push(in_block);
+    blocks.push(bits);
+
+    let mut block_bytes = Vec::new();
+    for bits in &blocks {
+        let mut bytes = vec![0u8; 1 << BLOCK_SIZE];
+        for (i, bit) in bits.iter().enumerate() {
+            bytes[i / 8] |= (*bit as u8) << (7 - i % 8);
+        }
+        block_bytes.extend(bytes);
+    }
+
+    let sizes = block_values
+        .iter()
+        .flat_map(|count| (*count as u16 - 1).to_le_bytes())
+        .collect();
+
+    // each index entry points at the value in the middle of its range
+    let mut starts = vec![0];
+    for count in &block_values {
+        starts.push(starts.last().unwrap() + count);
+    }
+
+    let mut index = Vec::new();
+    let entries = values.len().div_ceil(1 << IDX_BITS);
+    for entry in 0..entries {
+        let middle = (entry << IDX_BITS) + (1 << (IDX_BITS - 1));
+        let block = match starts.iter().rposition(|start| *start <= middle) {
+            Some(block) => block.min(block_values.len() - 1),
+            None => 0,
+        };
+
+        index.extend((block as u32).to_le_bytes());
+        index.extend(((middle - starts[block]) as u16).to_le_bytes());
+    }
+
+    let mut header = vec![flags, BLOCK_SIZE, IDX_BITS, 0];
+    header.extend((blocks.len() as u32).to_le_bytes());
+    header.push(max_len as u8);
+    header.push(min_len as u8);
+    for len in min_len..=max_len {
+        header.extend((offsets[len] as u16).to_le_bytes());
+    }
+
+    header.extend((symbols.len() as u16).to_le_bytes());
+    for symbol in &order {
+        let (left, right) = match symbols[*symbol] {
+            Symbol::Value(value) => (value as usize, 0xfff),
+            Symbol::Pair(left, right) => (number[left], number[right]),
+        };
+
+        header.push(left as u8);
+        header.push(((left >> 8) & 0x0f) as u8 | ((right & 0x0f) << 4) as u8);
+        header.push((right >> 4) as u8);
+    }
+
+    if symbols.len() % 2 == 1 {
+        header.push(0);
+    }
+
+    Section {
+        header,
+        index,
+        sizes,
+        blocks: block_bytes,
+    }
+}
+
+// replaces the most common pair of neighbouring symbols with a new symbol
+// until pairs stop repeating, returning the symbols, how many values each
+// stands for and the values as symbols
+fn pair_symbols(values: &[u16]) -> (Vec<Symbol>, Vec<usize>, Vec<usize>) {
+    let mut distinct = values.to_vec();
+    distinct.sort_unstable();
+    distinct.dedup();
+
+    let mut symbols = distinct
+        .iter()
+        .map(|value| Symbol::Value(*value))
+        .collect::<Vec<_>>();
+    let mut lengths = vec![1; symbols.len()];
+    let mut sequence = values
+        .iter()
+        .map(|value| distinct.binary_search(value).unwrap())
+        .collect::<Vec<_>>();
+
+    while symbols.len() < MAX_SYMBOLS {
+        let mut counts = HashMap::new();
+        let mut previous = None;
+
+        for pair in sequence.windows(2) {
+            let pair = (pair[0], pair[1]);
+            // overlapping pairs in a run can't both be replaced
+            if previous == Some(pair) && pair.0 == pair.1 {
+                previous = None;
+                continue;
+            }
+
+            if lengths[pair.0] + lengths[pair.1] <= MAX_SYMBOL_LEN {
+                *counts.entry(pair).or_insert(0) += 1;
+            }
+            previous = Some(pair);
+        }
+
+        let Some((pair, count)) = counts
+            .into_iter()
+            .max_by_key(|(pair, count)| (*count, Reverse(*pair)))
+        else {
+            break;
+        };
+
+        if count < 8 {
+            break;
+        }
+
+        let symbol = symbols.len();
+        symbols.push(Symbol::Pair(pair.0, pair.1));
+        lengths.push(lengths[pair.0] + lengths[pair.1]);
+
+        let mut replaced = Vec::with_capacity(sequence.len());
+        let mut i = 0;
+        while i < sequence.len() {
+            if i + 1 < sequence.len() && (sequence[i], sequence[i + 1]) == pair {
+                replaced.push(symbol);
+                i += 2;
+            } else {
+                replaced.push(sequence[i]);
+                i += 1;
+            }
+        }
+        sequence = replaced;
+    }
+
+    (symbols, lengths, sequence)
+}
+
+// Huffman code lengths for the symbols in the sequence, zero for the rest.
+// frequencies are flattened until no code is too long
+fn code_lengths(sequence: &[usize], symbols: usize) -> Vec<usize> {
+    let mut frequencies = vec![0u64; symbols];
+    for symbol in sequence {
+        frequencies[*symbol] += 1;
+    }
+
+    loop {
+        let used = (0..symbols)
+            .filter(|symbol| frequencies[*symbol] > 0)
+            .collect::<Vec<_>>();
+        let mut lengths = vec![0; symbols];
+
+        // a single symbol still needs a one bit code
+        if used.len() == 1 {
+            lengths[used[0]] = 1;
+            return lengths;
+        }
+
+        // nodes are leaves first, then merged nodes pointing at their parent
+        let mut parent = vec![usize::MAX; used.len()];
+        let mut heap = used
+            .iter()
+            .enumerate()
+            .map(|(node, symbol)| Reverse((frequencies[*symbol], node)))
+            .collect::<BinaryHeap<_>>();
+
+        while heap.len() > 1 {
+            let Reverse((first, a)) = heap.pop().unwrap();
+            let Reverse((second, b)) = heap.pop().unwrap();
+            let node = parent.len();
+            parent.push(usize::MAX);
+            parent[a] = node;
+            parent[b] = node;
+            heap.push(Reverse((first + second, node)));
+        }
+
+        for (leaf, symbol) in used.iter().enumerate() {
+            let mut node = leaf;
+            while parent[node] != usize::MAX {
+                lengths[*symbol] += 1;
+                node = parent[node];
+            }
+        }
+
+        if lengths.iter().all(|len| *len <= MAX_CODE_LEN) {
+            return lengths;
+        }
+
+        for frequency in &mut frequencies {
+            if *frequency > 0 {
+                *frequency = *frequency / 2 + 1;
+            }
+        }
+    }
+}
+
+fn write_file(start: Vec<u8>, sections: Vec<Section>) -> Vec<u8> {
+    let mut bytes = start;
+
+    for section in &sections {
+        bytes.extend(&section.header);
+    }
+    for section in &sections {
+        bytes.extend(&section.index);
+    }
+    for section in &sections {
+        bytes.extend(&section.sizes);
+    }
+    for section in &sections {
+        bytes.resize(bytes.len().div_ceil(64) * 64, 0);
+        bytes.extend(&section.blocks);
+    }
+
+    // files end in a checksum that probing never reads, left empty here
+    bytes.resize(bytes.len().div_ceil(64) * 64 + 16, 0);
+    bytes
+}
@@ -0,0 +1,1177 @@
+use super::TablebaseError;
+use cozy_chess::{BitBoard, Board, Color, Piece};
+use std::borrow::Cow;
+
+// the index encoding covers tables with up to this many pieces
+pub const MAX_PIECES: usize = 7;
+
+const WDL_MAGIC: [u8; 4] = [0x71, 0xe8, 0x23, 0x5d];
+const DTZ_MAGIC: [u8; 4] = [0xd7, 0x66, 0x0c, 0xa5];
+
+// piece letters in table names, in cozy_chess order
+const LETTERS: [char; 6] = ['P', 'N', 'B', 'R', 'Q', 'K'];
+
+// stored distances are looked up through a map by result, and are halved
+// unless the flag for the result says they are exact
+const WDL_TO_MAP: [usize; 5] = [1, 3, 0, 2, 0];
+const EXACT_FLAGS: [u8; 5] = [8, 0, 0, 0, 4];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kind {
+    // win, draw or loss for both sides to move
+    Wdl,
+    // distance to the next capture, pawn move or mate, for one side to move
+    Dtz,
+}
+
+impl Kind {
+    pub fn extension(self) -> &'static str {
+        match self {
+            Kind::Wdl => "rtbw",
+            Kind::Dtz => "rtbz",
+        }
+    }
+
+    pub fn magic(self) -> [u8; 4] {
+        match self {
+            Kind::Wdl => WDL_MAGIC,
+            Kind::Dtz => DTZ_MAGIC,
+        }
+    }
+}
+
+/// Pieces of each colour, which picks the table a position is stored in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Material {
+    // by colour and then by piece, in cozy_chess order
+    counts: [[u8; 6]; 2],
+}
+
+impl Material {
+    pub fn from_board(board: &Board) -> Self {
+        let mut counts = [[0; 6]; 2];
+
+        for color in Color::ALL {
+            for piece in Piece::ALL {
+                counts[color as usize][piece as usize] =
+                    board.colored_pieces(color, piece).len() as u8;
+            }
+        }
+
+        Self { counts }
+    }
+
+    /// Reads a table name such as `KQvKR`, with white's pieces before the `v`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        if name.len() > MAX_PIECES + 1 {
+            return None;
+        }
+
+        let (white, black) = name.split_once('v')?;
+        let mut counts = [[0; 6]; 2];
+
+        for (side, letters) in counts.iter_mut().zip([white, black]) {
+            for letter in letters.chars() {
+                let piece = LETTERS.iter().position(|other| *other == letter)?;
+                side[piece] += 1;
+            }
+
+            if side[Piece::King as usize] != 1 {
+                return None;
+            }
+        }
+
+        Some(Self { counts })
+    }
+
+    pub fn name(&self) -> String {
+        let side = |counts: &[u8; 6]| {
+            Piece::ALL
+                .into_iter()
+                .rev()
+                .flat_map(|piece| {
+                    std::iter::repeat_n(LETTERS[piece as usize], counts[piece as usize] as usize)
+                })
+                .collect::<String>()
+        };
+
+        format!("{}v{}", side(&self.counts[0]), side(&self.counts[1]))
+    }
+
+    pub fn flipped(&self) -> Self {
+        Self {
+            counts: [self.counts[1], self.counts[0]],
+        }
+    }
+
+    pub fn pieces(&self) -> usize {
+        self.counts
+            .iter()
+            .flatten()
+            .map(|count| *count as usize)
+            .sum()
+    }
+
+    pub fn count(&self, color: Color, piece: Piece) -> usize {
+        self.counts[color as usize][piece as usize] as usize
+    }
+
+    pub fn is_symmetric(&self) -> bool {
+        self.counts[0] == self.counts[1]
+    }
+}
+
+// what the index encoding needs to know about a table's material
+#[derive(Clone, Copy, Debug)]
+struct Shape {
+    pieces: usize,
+    // pawns of the colour the leading pawn is picked from, then the other
+    // colour's, both zero in tables without pawns
+    pawns: [u8; 2],
+    // the kings are the only unique pieces, so they are placed as a pair
+    kings_first: bool,
+}
+
+impl Shape {
+    fn new(material: &Material) -> Self {
+        let white = material.count(Color::White, Piece::Pawn) as u8;
+        let black = material.count(Color::Black, Piece::Pawn) as u8;
+
+        let pawns = match black == 0 || (white > 0 && black >= white) {
+            true => [white, black],
+            false => [black, white],
+        };
+
+        let unique = material
+            .counts
+            .iter()
+            .flatten()
+            .filter(|count| **count == 1);
+
+        Self {
+            pieces: material.pieces(),
+            pawns,
+            kings_first: unique.count() == 2,
+        }
+    }
+
+    fn has_pawns(&self) -> bool {
+        self.pawns[0] > 0
+    }
+}
+
+// how the positions of one table are numbered: the pieces in the order they
+// are placed, the size of each group of identical pieces and the factor each
+// group's index is multiplied by
+#[derive(Clone, Copy, Debug)]
+struct EncInfo {
+    pieces: [u8; MAX_PIECES],
+    norm: [u8; MAX_PIECES],
+    factor: [u64; MAX_PIECES],
+    size: u64,
+}
+
+impl EncInfo {
+    // `header` starts at the order byte; white to move is the low nibble of
+    // each byte and black to move the high nibble
+    fn new(shape: &Shape, header: &[u8], shift: u8, file: usize) -> Result<Self, TablebaseError> {
+        let num = shape.pieces;
+        let more_pawns = shape.pawns[1] > 0;
+        let first = 1 + more_pawns as usize;
+
+        let mut pieces = [0; MAX_PIECES];
+        for (i, piece) in pieces.iter_mut().take(num).enumerate() {
+            *piece = (header[i + first] >> shift) & 0x0f;
+        }
+
+        let order = ((header[0] >> shift) & 0x0f) as usize;
+        let order2 = match more_pawns {
+            true => ((header[1] >> shift) & 0x0f) as usize,
+            false => 0x0f,
+        };
+
+        let mut norm = [0; MAX_PIECES];
+        let mut k = match (shape.has_pawns(), shape.kings_first) {
+            (true, _) => shape.pawns[0] as usize,
+            (false, true) => 2,
+            (false, false) => 3,
+        };
+        norm[0] = k as u8;
+
+        if more_pawns {
+            norm[k] = shape.pawns[1];
+            k += norm[k] as usize;
+        }
+
+        let mut i = k;
+        while i < num {
+            let mut j = i;
+            while j < num && pieces[j] == pieces[i] {
+                norm[i] += 1;
+                j += 1;
+            }
+            i += norm[i] as usize;
+        }
+
+        let mut factor = [0; MAX_PIECES];
+        let mut size = 1;
+        let mut free = 64 - k;
+        let mut group = 0;
+
+        while k < num || group == order || group == order2 {
+            if group == order {
+                factor[0] = size;
+                size *= match (shape.has_pawns(), shape.kings_first) {
+                    (true, _) => PAWN_FACTOR_FILE[norm[0] as usize - 1][file],
+                    (false, true) => 462,
+                    (false, false) => 31332,
+                };
+            } else if group == order2 {
+                let leading = norm[0] as usize;
+                factor[leading] = size;
+                size *= binomial(norm[leading] as usize, 48 - leading);
+            } else if k < num {
+                factor[k] = size;
+                size *= binomial(norm[k] as usize, free);
+                free -= norm[k] as usize;
+                k += norm[k] as usize;
+            } else {
+                return Err(TablebaseError::Corrupted);
+            }
+
+            group += 1;
+        }
+
+        Ok(Self {
+            pieces,
+            norm,
+            factor,
+            size,
+        })
+    }
+}
+
+/// Numbering of the positions in a table, read from its header.
+#[derive(Clone, Debug)]
+pub struct Encoding {
+    shape: Shape,
+    // by leading pawn file (just one without pawns) and then by side to move,
+    // where a table stored for one side only has white to move
+    infos: Vec<[Option<EncInfo>; 2]>,
+}
+
+impl Encoding {
+    /// Reads the header at the start of a file, returning where it ends.
+    pub fn read(
+        material: &Material,
+        kind: Kind,
+        data: &[u8],
+    ) -> Result<(Self, usize), TablebaseError> {
+        if data.get(..4) != Some(&kind.magic()[..]) {
+            return Err(TablebaseError::InvalidMagic);
+        }
+
+        let shape = Shape::new(material);
+        let split = kind == Kind::Wdl && byte(data, 4)? & 1 != 0;
+        let files = if shape.has_pawns() { 4 } else { 1 };
+        let header_len = shape.pieces + 1 + (shape.pawns[1] > 0) as usize;
+
+        let mut pos = 5;
+        let mut infos = Vec::new();
+
+        for file in 0..files {
+            let header = data
+                .get(pos..pos + header_len)
+                .ok_or(TablebaseError::Truncated)?;
+
+            let white = EncInfo::new(&shape, header, 0, file)?;
+            let black = match split {
+                true => Some(EncInfo::new(&shape, header, 4, file)?),
+                false => None,
+            };
+
+            infos.push([Some(white), black]);
+            pos += header_len;
+        }
+
+        Ok((Self { shape, infos }, pos + (pos & 1)))
+    }
+
+    pub fn files(&self) -> usize {
+        self.infos.len()
+    }
+
+    #[cfg(test)]
+    pub fn size(&self, file: usize, side: usize) -> Option<u64> {
+        self.infos[file][side].map(|info| info.size)
+    }
+
+    /// Index of a position, and the leading pawn's file when there are pawns.
+    /// `squares` gives the squares of each piece code in the header, already
+    /// turned around when the position is looked up with colours swapped.
+    pub fn index(&self, side: usize, squares: impl Fn(u8) -> BitBoard) -> Option<(usize, u64)> {
+        let num = self.shape.pieces;
+        let mut p = [0u8; MAX_PIECES];
+        let mut i = 0;
+
+        let file = match self.shape.has_pawns() {
+            true => {
+                let leading = self.infos[0][0]?.pieces[0];
+
+                for square in squares(leading) {
+                    p[i] = square as u8;
+                    i += 1;
+                }
+
+                if i != self.shape.pawns[0] as usize {
+                    return None;
+                }
+
+                leading_pawn_file(&mut p[..i])
+            }
+            false => 0,
+        };
+
+        let info = self.infos[file][side]?;
+
+        while i < num {
+            let group = squares(info.pieces[i]);
+            if group.is_empty() {
+                return None;
+            }
+
+            for square in group {
+                if i < num {
+                    p[i] = square as u8;
+                    i += 1;
+                }
+            }
+        }
+
+        let index = match self.shape.has_pawns() {
+            true => encode_pawn(&self.shape, &info, &mut p[..num]),
+            false => encode_piece(&self.shape, &info, &mut p[..num]),
+        };
+
+        Some((file, index))
+    }
+}
+
+/// A table file with its header read, ready to be probed.
+pub struct Table {
+    data: Cow<'static, [u8]>,
+    symmetric: bool,
+    encoding: Encoding,
+    // laid out like the encoding
+    pairs: Vec<[Option<Pairs>; 2]>,
+    // for distance tables, the side stored for each file and how distances
+    // are scaled, with the map of stored values to distances if there is one
+    dtz_flags: Vec<u8>,
+    dtz_maps: Vec<Option<DtzMap>>,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct DtzMap {
+    start: usize,
+    wide: bool,
+    // where the map for each result starts, counted in entries from `start`
+    index: [usize; 4],
+}
+
+impl Table {
+    pub fn new(
+        material: &Material,
+        kind: Kind,
+        data: Cow<'static, [u8]>,
+    ) -> Result<Self, TablebaseError> {
+        let (encoding, mut pos) = Encoding::read(material, kind, &data)?;
+        let files = encoding.files();
+
+        let mut pairs = Vec::new();
+        let mut sizes = Vec::new();
+        let mut dtz_flags = Vec::new();
+
+        for infos in &encoding.infos {
+            let mut file_pairs = [None, None];
+
+            for (side, info) in infos.iter().enumerate() {
+                let Some(info) = info else {
+                    continue;
+                };
+
+                if side == 0 {
+                    dtz_flags.push(byte(&data, pos)?);
+                }
+
+                let (side_pairs, side_sizes) = Pairs::read(&data, &mut pos, info.size, kind)?;
+                file_pairs[side] = Some(side_pairs);
+                sizes.push(side_sizes);
+            }
+
+            pairs.push(file_pairs);
+        }
+
+        let mut dtz_maps = vec![None; files];
+        if kind == Kind::Dtz {
+            let start = pos;
+
+            for (flags, map) in dtz_flags.iter().zip(&mut dtz_maps) {
+                if flags & 2 == 0 {
+                    continue;
+                }
+
+                let wide = flags & 16 != 0;
+                let mut index = [0; 4];
+
+                if wide {
+                    pos += pos & 1;
+                    for entry in &mut index {
+                        *entry = (pos - start) / 2 + 1;
+                        pos += 2 + 2 * read_u16(&data, pos)? as usize;
+                    }
+                } else {
+                    for entry in &mut index {
+                        *entry = pos + 1 - start;
+                        pos += 1 + byte(&data, pos)? as usize;
+                    }
+                }
+
+                *map = Some(DtzMap { start, wide, index });
+            }
+
+            pos += pos & 1;
+        }
+
+        // every sub-table's index table, then every size table, then the
+        // compressed blocks, each sub-table's blocks aligned to 64 bytes
+        for section in 0..3 {
+            let mut sizes = sizes.iter();
+
+            for compressed in pairs.iter_mut().flatten().flatten() {
+                let size = sizes.next().expect("should be size for every sub-table")[section];
+
+                if section == 2 {
+                    pos = (pos + 0x3f) & !0x3f;
+                }
+
+                if let Pairs::Compressed(compressed) = compressed {
+                    match section {
+                        0 => compressed.index_table = pos,
+                        1 => compressed.size_table = pos,
+                        _ => compressed.blocks = pos,
+                    }
+                }
+
+                pos += size;
+            }
+        }
+
+        if pos > data.len() {
+            return Err(TablebaseError::Truncated);
+        }
+
+        Ok(Self {
+            data,
+            symmetric: material.is_symmetric(),
+            encoding,
+            pairs,
+            dtz_flags,
+            dtz_maps,
+        })
+    }
+
+    pub fn has_pawns(&self) -> bool {
+        self.encoding.shape.has_pawns()
+    }
+
+    /// Result for the side to move, from -2 for a loss to 2 for a win, where
+    /// -1 and 1 are results the fifty-move rule turns into draws.
+    pub fn wdl(
+        &self,
+        side: usize,
+        squares: impl Fn(u8) -> BitBoard,
+    ) -> Result<i32, TablebaseError> {
+        let (file, index) = self
+            .encoding
+            .index(side, squares)
+            .ok_or(TablebaseError::Corrupted)?;
+
+        let pairs = self.pairs[file][side]
+            .as_ref()
+            .ok_or(TablebaseError::Corrupted)?;
+
+        Ok(pairs.value(&self.data, index, Kind::Wdl)? as i32 - 2)
+    }
+
+    /// Stored distance in plies for a position with result `wdl`, or `None`
+    /// when the table only has the other side to move.
+    pub fn dtz(
+        &self,
+        side: usize,
+        wdl: i32,
+        squares: impl Fn(u8) -> BitBoard,
+    ) -> Result<Option<i32>, TablebaseError> {
+        let (file, index) = self
+            .encoding
+            .index(0, squares)
+            .ok_or(TablebaseError::Corrupted)?;
+
+        let flags = self.dtz_flags[file];
+        if !self.symmetric && (flags & 1) as usize != side {
+            return Ok(None);
+        }
+
+        let pairs = self.pairs[file][0]
+            .as_ref()
+            .ok_or(TablebaseError::Corrupted)?;
+        let mut value = pairs.value(&self.data, index, Kind::Dtz)? as usize;
+
+        let result = (wdl + 2) as usize;
+
+        if let Some(map) = &self.dtz_maps[file] {
+            let entry = map.index[WDL_TO_MAP[result]] + value;
+            value = match map.wide {
+                true => read_u16(&self.data, map.start + 2 * entry)? as usize,
+                false => byte(&self.data, map.start + entry)? as usize,
+            };
+        }
+
+        if flags & EXACT_FLAGS[result] == 0 || wdl & 1 != 0 {
+            value *= 2;
+        }
+
+        Ok(Some(value as i32))
+    }
+}
+
+// values of one sub-table, either all the same or Huffman coded symbols that
+// each stand for a run of one or more values
+#[derive(Clone, Debug)]
+enum Pairs {
+    Constant(u16),
+    Compressed(Compressed),
+}
+
+#[derive(Clone, Debug)]
+struct Compressed {
+    // log2 of the bytes in a block and of the values between index entries
+    block_size: u8,
+    idx_bits: u8,
+    min_len: u8,
+    // symbol number of the first code of each length, from `min_len` up
+    offsets: usize,
+    // smallest code of each length, left aligned
+    base: Vec<u64>,
+    // values each symbol stands for, minus one
+    sym_len: Vec<u8>,
+    // three bytes per symbol: a value, or the two symbols it pairs up
+    sym_pat: usize,
+    index_table: usize,
+    size_table: usize,
+    blocks: usize,
+}
+
+impl Pairs {
+    // reads the header at `pos`, returning the sizes of the sub-table's index
+    // table, size table and blocks
+    fn read(
+        data: &[u8],
+        pos: &mut usize,
+        size: u64,
+        kind: Kind,
+    ) -> Result<(Self, [usize; 3]), TablebaseError> {
+        let start = *pos;
+        let flags = byte(data, start)?;
+
+        if flags & 0x80 != 0 {
+            let value = match kind {
+                Kind::Wdl => byte(data, start + 1)? as u16,
+                Kind::Dtz => 0,
+            };
+
+            *pos += 2;
+            return Ok((Pairs::Constant(value), [0; 3]));
+        }
+
+        let block_size = byte(data, start + 1)?;
+        let idx_bits = byte(data, start + 2)?;
+        let real_blocks = read_u32(data, start + 4)? as usize;
+        let blocks = real_blocks + byte(data, start + 3)? as usize;
+        let max_len = byte(data, start + 8)?;
+        let min_len = byte(data, start + 9)?;
+
+        if block_size >= 32
+            || !(1..64).contains(&idx_bits)
+            || min_len == 0
+            || max_len < min_len
+            || max_len >= 64
+        {
+            return Err(TablebaseError::Corrupted);
+        }
+
+        let lengths = (max_len - min_len + 1) as usize;
+        let offsets = start + 10;
+        let num_syms = read_u16(data, offsets + 2 * lengths)? as usize;
+        let sym_pat = offsets + 2 * lengths + 2;
+
+        *pos = sym_pat + 3 * num_syms + (num_syms & 1);
+
+        let sym_len = symbol_lengths(data, sym_pat, num_syms)?;
+
+        let mut base = vec![0u64; lengths];
+        for i in (0..lengths - 1).rev() {
+            let count =
+                read_u16(data, offsets + 2 * i)?.wrapping_sub(read_u16(data, offsets + 2 * i + 2)?);
+            base[i] = base[i + 1].wrapping_add(count as u64) / 2;
+        }
+        for (i, base) in base.iter_mut().enumerate() {
+            *base <<= 64 - (min_len as usize + i);
+        }
+
+        let indices = (size + (1 << idx_bits) - 1) >> idx_bits;
+        let sizes = [6 * indices as usize, 2 * blocks, real_blocks << block_size];
+
+        let compressed = Compressed {
+            block_size,
+            idx_bits,
+            min_len,
+            offsets,
+            base,
+            sym_len,
+            sym_pat,
+            index_table: 0,
+            size_table: 0,
+            blocks: 0,
+        };
+
+        Ok((Pairs::Compressed(compressed), sizes))
+    }
+
+    fn value(&self, data: &[u8], index: u64, kind: Kind) -> Result<u16, TablebaseError> {
+        let compressed = match self {
+            Pairs::Constant(value) => return Ok(*value),
+            Pairs::Compressed(compressed) => compressed,
+        };
+
+        let pattern = compressed.symbol(data, index)?;
+        let low = byte(data, pattern)? as u16;
+
+        match kind {
+            Kind::Wdl => Ok(low),
+            Kind::Dtz => Ok(low | ((byte(data, pattern + 1)? as u16 & 0x0f) << 8)),
+        }
+    }
+}
+
+impl Compressed {
+    // finds the block holding the value, decodes symbols up to it and then
+    // splits the symbol down to the value's own, returning its pattern
+    fn symbol(&self, data: &[u8], index: u64) -> Result<usize, TablebaseError> {
+        let bits = self.idx_bits;
+        let entry = self.index_table + 6 * (index >> bits) as usize;
+
+        let mut lit = (index & ((1 << bits) - 1)) as i64 - (1 << (bits - 1));
+        let mut block = read_u32(data, entry)? as usize;
+        lit += read_u16(data, entry + 4)? as i64;
+
+        let block_len =
+            |block: usize| read_u16(data, self.size_table + 2 * block).map(|len| len as i64 + 1);
+
+        if lit < 0 {
+            while lit < 0 {
+                block = block.checked_sub(1).ok_or(TablebaseError::Corrupted)?;
+                lit += block_len(block)?;
+            }
+        } else {
+            while lit >= block_len(block)? {
+                lit -= block_len(block)?;
+                block += 1;
+            }
+        }
+
+        let mut ptr = self.blocks + (block << self.block_size);
+        let mut code = read_u64_be(data, ptr);
+        let mut bit_count = 0;
+        ptr += 8;
+
+        let mut sym = loop {
+            let mut len = 0;
+            while code < self.base[len] {
+                len += 1;
+                if len == self.base.len() {
+                    return Err(TablebaseError::Corrupted);
+                }
+            }
+
+            let code_len = self.min_len as usize + len;
+            let first = read_u16(data, self.offsets + 2 * len)? as usize;
+            let sym = first + ((code - self.base[len]) >> (64 - code_len)) as usize;
+            let sym_len = *self.sym_len.get(sym).ok_or(TablebaseError::Corrupted)? as i64;
+
+            if lit <= sym_len {
+                break sym;
+            }
+
+            lit -= sym_len + 1;
+            code <<= code_len;
+            bit_count += code_len;
+
+            if bit_count >= 32 {
+                bit_count -= 32;
+                code |= (read_u32_be(data, ptr) as u64) << bit_count;
+                ptr += 4;
+            }
+        };
+
+        while self.sym_len[sym] != 0 {
+            let (left, right) = symbol_pair(data, self.sym_pat, sym)?;
+            let left_len = *self.sym_len.get(left).ok_or(TablebaseError::Corrupted)? as i64;
+
+            if lit <= left_len {
+                sym = left;
+            } else {
+                lit -= left_len + 1;
+                sym = right;
+            }
+        }
+
+        Ok(self.sym_pat + 3 * sym)
+    }
+}
+
+// a symbol whose right half is 0xfff is a single value
+const LEAF: usize = 0xfff;
+
+fn symbol_pair(data: &[u8], sym_pat: usize, sym: usize) -> Result<(usize, usize), TablebaseError> {
+    let pattern = sym_pat + 3 * sym;
+    let [a, b, c] = [
+        byte(data, pattern)?,
+        byte(data, pattern + 1)?,
+        byte(data, pattern + 2)?,
+    ]
+    .map(|byte| byte as usize);
+
+    Ok((((b & 0x0f) << 8) | a, (c << 4) | (b >> 4)))
+}
+
+fn symbol_lengths(data: &[u8], sym_pat: usize, num_syms: usize) -> Result<Vec<u8>, TablebaseError> {
+    let mut lengths = vec![None::<u8>; num_syms];
+
+    for sym in 0..num_syms {
+        let mut stack = vec![sym];
+
+        while let Some(&top) = stack.last() {
+            if lengths[top].is_some() {
+                stack.pop();
+                continue;
+            }
+
+            let (left, right) = symbol_pair(data, sym_pat, top)?;
+            if right == LEAF {
+                lengths[top] = Some(0);
+                stack.pop();
+                continue;
+            }
+
+            let halves = [left, right].map(|half| lengths.get(half).copied());
+
+            match halves {
+                [Some(Some(left)), Some(Some(right))] => {
+                    let len = left.checked_add(right).and_then(|len| len.checked_add(1));
+                    lengths[top] = Some(len.ok_or(TablebaseError::Corrupted)?);
+                    stack.pop();
+                }
+                [Some(_), Some(_)] if stack.len() <= num_syms => {
+                    stack.extend(
+                        [left, right]
+                            .into_iter()
+                            .filter(|half| lengths[*half].is_none()),
+                    );
+                }
+                _ => return Err(TablebaseError::Corrupted),
+            }
+        }
+    }
+
+    Ok(lengths
+        .into_iter()
+        .map(|len| len.unwrap_or_default())
+        .collect())
+}
+
+fn byte(data: &[u8], pos: usize) -> Result<u8, TablebaseError> {
+    data.get(pos).copied().ok_or(TablebaseError::Truncated)
+}
+
+fn read_u16(data: &[u8], pos: usize) -> Result<u16, TablebaseError> {
+    let bytes = data.get(pos..pos + 2).ok_or(TablebaseError::Truncated)?;
+    Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+fn read_u32(data: &[u8], pos: usize) -> Result<u32, TablebaseError> {
+    let bytes = data.get(pos..pos + 4).ok_or(TablebaseError::Truncated)?;
+    Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+// compressed blocks are read as big endian bit streams, with zeros past the
+// end of the file since the last block can end early
+fn read_u32_be(data: &[u8], pos: usize) -> u32 {
+    (0..4).fold(0, |value, i| {
+        (value << 8) | *data.get(pos + i).unwrap_or(&0) as u32
+    })
+}
+
+fn read_u64_be(data: &[u8], pos: usize) -> u64 {
+    ((read_u32_be(data, pos) as u64) << 32) | read_u32_be(data, pos + 4) as u64
+}
+
+// squares are numbered a1 = 0 to h8 = 63 throughout, as in cozy_chess
+const fn rank(square: usize) -> usize {
+    square >> 3
+}
+
+const fn file(square: usize) -> usize {
+    square & 7
+}
+
+// positive above the a1-h8 diagonal, negative below it
+const fn off_diagonal(square: usize) -> i32 {
+    rank(square) as i32 - file(square) as i32
+}
+
+const fn flip_diagonal(square: u8) -> u8 {
+    ((square & 7) << 3) | (square >> 3)
+}
+
+const fn binomial(k: usize, n: usize) -> u64 {
+    if k > n {
+        return 0;
+    }
+
+    let mut result = 1;
+    let mut i = 0;
+    while i < k {
+        result = result * (n - i) as u64 / (i + 1) as u64;
+        i += 1;
+    }
+    result
+}
+
+// squares of the a1-d1-d4 triangle, below the diagonal first
+const TRIANGLE_SQUARES: [usize; 10] = [1, 2, 3, 10, 11, 19, 0, 9, 18, 27];
+
+// position of every square in the triangle, once mirrored into it
+const TRIANGLE: [u64; 64] = {
+    let mut table = [0; 64];
+    let mut square = 0;
+    while square < 64 {
+        let mut folded = square;
+        if file(folded) > 3 {
+            folded ^= 7;
+        }
+        if rank(folded) > 3 {
+            folded ^= 0x38;
+        }
+        if off_diagonal(folded) > 0 {
+            folded = flip_diagonal(folded as u8) as usize;
+        }
+
+        let mut i = 0;
+        while TRIANGLE_SQUARES[i] != folded {
+            i += 1;
+        }
+        table[square] = i as u64;
+        square += 1;
+    }
+    table
+};
+
+// squares below the a1-h8 diagonal row by row, then the diagonal itself
+const LOWER: [u64; 64] = {
+    let mut table = [0; 64];
+    let mut square = 0;
+    while square < 64 {
+        let (low, high) = match rank(square) < file(square) {
+            true => (rank(square), file(square)),
+            false => (file(square), rank(square)),
+        };
+
+        table[square] = match low == high {
+            true => 28 + low as u64,
+            false => (low * (15 - low) / 2 + high - low - 1) as u64,
+        };
+        square += 1;
+    }
+    table
+};
+
+// the a1-h8 diagonal, then the h1-a8 one
+const DIAG: [u64; 64] = {
+    let mut table = [0; 64];
+    let mut square = 0;
+    while square < 64 {
+        if rank(square) == file(square) {
+            table[square] = rank(square) as u64;
+        } else if rank(square) + file(square) == 7 {
+            table[square] = 8 + rank(square) as u64;
+        }
+        square += 1;
+    }
+    table
+};
+
+// every legal placement of two kings with the first in the triangle. when
+// the first is on the diagonal the second can't be above it, and placements
+// with both on the diagonal come last
+const KK_IDX: [[u64; 64]; 10] = {
+    let mut table = [[0; 64]; 10];
+    let mut both_on_diagonal = [(0, 0); 32];
+    let mut diagonal_count = 0;
+    let mut code = 0;
+
+    let mut idx = 0;
+    while idx < 10 {
+        let first = TRIANGLE_SQUARES[idx];
+        let mut second = 0;
+        while second < 64 {
+            let adjacent =
+                rank(first).abs_diff(rank(second)) <= 1 && file(first).abs_diff(file(second)) <= 1;
+
+            if adjacent {
+                // includes both kings on one square
+            } else if off_diagonal(first) == 0 && off_diagonal(second) > 0 {
+                // mirrored below the diagonal instead
+            } else if off_diagonal(first) == 0 && off_diagonal(second) == 0 {
+                both_on_diagonal[diagonal_count] = (idx, second);
+                diagonal_count += 1;
+            } else {
+                table[idx][second] = code;
+                code += 1;
+            }
+            second += 1;
+        }
+        idx += 1;
+    }
+
+    let mut i = 0;
+    while i < diagonal_count {
+        let (idx, second) = both_on_diagonal[i];
+        table[idx][second] = code;
+        code += 1;
+        i += 1;
+    }
+    table
+};
+
+const FILE_TO_FILE: [usize; 8] = [0, 1, 2, 3, 3, 2, 1, 0];
+
+// leading pawn squares numbered by file pair and then rank, so the pawn with
+// the lowest number leads
+const FLAP: [usize; 64] = {
+    let mut table = [0; 64];
+    let mut square = 8;
+    while square < 56 {
+        table[square] = FILE_TO_FILE[file(square)] * 6 + rank(square) - 1;
+        square += 1;
+    }
+    table
+};
+
+// order the other pawns of the leading colour are counted in
+const PAWN_TWIST: [u64; 64] = {
+    let mut table = [0; 64];
+    let mut square = 8;
+    while square < 56 {
+        let mirrored = (file(square) >= 4) as usize;
+        table[square] =
+            (47 - 12 * FILE_TO_FILE[file(square)] - 2 * (rank(square) - 1) - mirrored) as u64;
+        square += 1;
+    }
+    table
+};
+
+// index of the leading pawn's square within its file pair, and the number
+// of placements on each file pair, by how many other pawns of its colour
+// there are
+const PAWN_IDX: [[u64; 24]; 6] = pawn_tables().0;
+const PAWN_FACTOR_FILE: [[u64; 4]; 6] = pawn_tables().1;
+
+const fn pawn_tables() -> ([[u64; 24]; 6], [[u64; 4]; 6]) {
+    let mut idx = [[0; 24]; 6];
+    let mut factor = [[0; 4]; 6];
+
+    let mut others = 0;
+    while others < 6 {
+        let mut sum = 0;
+        let mut j = 0;
+        while j < 24 {
+            idx[others][j] = sum;
+            let square = (1 + j % 6) * 8 + j / 6;
+            sum += binomial(others, PAWN_TWIST[square] as usize);
+
+            if (j + 1) % 6 == 0 {
+                factor[others][j / 6] = sum;
+                sum = 0;
+            }
+            j += 1;
+        }
+        others += 1;
+    }
+
+    (idx, factor)
+}
+
+// moves the pawn with the lowest flap number to the front
+fn leading_pawn_file(p: &mut [u8]) -> usize {
+    for i in 1..p.len() {
+        if FLAP[p[0] as usize] > FLAP[p[i] as usize] {
+            p.swap(0, i);
+        }
+    }
+
+    FILE_TO_FILE[file(p[0] as usize)]
+}
+
+// combinatorial index of each later group, skipping squares taken by the
+// pieces placed before it
+fn encode_rest(info: &EncInfo, p: &mut [u8], mut k: usize, mut index: u64) -> u64 {
+    while k < p.len() {
+        let group = info.norm[k] as usize;
+        p[k..k + group].sort_unstable();
+
+        let mut s = 0;
+        for i in k..k + group {
+            let skips = p[..k].iter().filter(|other| p[i] > **other).count();
+            s += binomial(i - k + 1, p[i] as usize - skips);
+        }
+
+        index += s * info.factor[k];
+        k += group;
+    }
+
+    index
+}
+
+fn encode_piece(shape: &Shape, info: &EncInfo, p: &mut [u8]) -> u64 {
+    if p[0] & 0x04 != 0 {
+        p.iter_mut().for_each(|square| *square ^= 0x07);
+    }
+
+    if p[0] & 0x20 != 0 {
+        p.iter_mut().for_each(|square| *square ^= 0x38);
+    }
+
+    let leading = if shape.kings_first { 2 } else { 3 };
+
+    if let Some(i) = p
+        .iter()
+        .position(|square| off_diagonal(*square as usize) != 0)
+    {
+        if off_diagonal(p[i] as usize) > 0 && i < leading {
+            p.iter_mut()
+                .for_each(|square| *square = flip_diagonal(*square));
+        }
+    }
+
+    let [a, b, c] = [0, 1, 2].map(|i| p.get(i).copied().unwrap_or_default() as usize);
+
+    let index = if shape.kings_first {
+        KK_IDX[TRIANGLE[a] as usize][b]
+    } else {
+        let s1 = (b > a) as u64;
+        let s2 = (c > a) as u64 + (c > b) as u64;
+        let (b, c) = (b as u64, c as u64);
+
+        if off_diagonal(a) != 0 {
+            TRIANGLE[a] * 63 * 62 + (b - s1) * 62 + (c - s2)
+        } else if off_diagonal(b as usize) != 0 {
+            6 * 63 * 62 + DIAG[a] * 28 * 62 + LOWER[b as usize] * 62 + c - s2
+        } else if off_diagonal(c as usize) != 0 {
+            6 * 63 * 62
+                + 4 * 28 * 62
+                + DIAG[a] * 7 * 28
+                + (DIAG[b as usize] - s1) * 28
+                + LOWER[c as usize]
+        } else {
+            6 * 63 * 62
+                + 4 * 28 * 62
+                + 4 * 7 * 28
+                + DIAG[a] * 7 * 6
+                + (DIAG[b as usize] - s1) * 6
+                + (DIAG[c as usize] - s2)
+        }
+    };
+
+    encode_rest(info, p, leading, index * info.factor[0])
+}
+
+fn encode_pawn(shape: &Shape, info: &EncInfo, p: &mut [u8]) -> u64 {
+    if p[0] & 0x04 != 0 {
+        p.iter_mut().for_each(|square| *square ^= 0x07);
+    }
+
+    let mut k = info.norm[0] as usize;
+    p[1..k].sort_unstable_by_key(|square| std::cmp::Reverse(PAWN_TWIST[*square as usize]));
+
+    let mut index = PAWN_IDX[k - 1][FLAP[p[0] as usize]];
+    for i in 1..k {
+        index += binomial(k - i, PAWN_TWIST[p[i] as usize] as usize);
+    }
+    index *= info.factor[0];
+
+    // the other colour's pawns, which can't be on the first or last rank
+    if shape.pawns[1] > 0 {
+        let end = k + info.norm[k] as usize;
+        p[k..end].sort_unstable();
+
+        let mut s = 0;
+        for i in k..end {
+            let skips = p[..k].iter().filter(|other| p[i] > **other).count();
+            s += binomial(i - k + 1, p[i] as usize - skips - 8);
+        }
+
+        index += s * info.factor[k];
+        k = end;
+    }
+
+    encode_rest(info, p, k, index)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_material_names() {
+        let material = Material::from_name("KRNvKQ").unwrap();
+        assert_eq!(material.name(), "KRNvKQ");
+        assert_eq!(material.flipped().name(), "KQvKRN");
+        assert_eq!(material.pieces(), 5);
+        assert!(!material.is_symmetric());
+
+        let board = Board::from_fen("8/8/8/3k4/2q5/8/1NR5/4K3 w - - 0 1", false).unwrap();
+        assert_eq!(Material::from_board(&board), material);
+
+        assert!(Material::from_name("KNvKN").unwrap().is_symmetric());
+        assert_eq!(Material::from_name("QvK"), None);
+        assert_eq!(Material::from_name("KXvK"), None);
+        assert_eq!(Material::from_name("KQRBNPPvK"), None);
+    }
+
+    #[test]
+    fn test_index_tables() {
+        // every placement of two kings gets its own index
+        let max = KK_IDX.iter().flatten().max().unwrap();
+        assert_eq!(*max, 461);
+
+        // pieces with three unique ones placed first
+        let shape = Shape::new(&Material::from_name("KQvK").unwrap());
+        let info = EncInfo::new(&shape, &[0, 6, 5, 14], 0, 0).unwrap();
+        assert_eq!(info.size, 31332);
+
+        // a lone pawn leads, and the kings go on any of the other squares
+        let shape = Shape::new(&Material::from_name("KPvK").unwrap());
+        let info = EncInfo::new(&shape, &[0, 1, 6, 14], 0, 0).unwrap();
+        assert_eq!(info.size, 6 * 63 * 62);
+    }
+}
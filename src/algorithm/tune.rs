@@ -0,0 +1,258 @@
+use super::{params::EvalParams, score::ScoreFunction};
+use cozy_chess::Board;
+
+/// Position labelled with the result of the game it came from.
+#[derive(Clone, Debug)]
+pub struct LabelledPosition {
+    pub board: Board,
+    // from white's perspective: 1 for a win, 0.5 for a draw and 0 for a loss
+    pub result: f64,
+}
+
+/// Parses a FEN or EPD line followed by a result, written either as a PGN
+/// result (`1-0`, `0-1`, `1/2-1/2`, optionally quoted as in `c9 "1-0";`) or
+/// as a bracketed number (`[1.0]`, `[0.5]`, `[0.0]`).
+pub fn parse_labelled_position(line: &str) -> Option<LabelledPosition> {
+    let fields = line.split_whitespace().collect::<Vec<_>>();
+    if fields.len() < 4 {
+        return None;
+    }
+
+    // EPD lines stop after the en passant square, FEN lines carry the clocks
+    let has_clocks = fields.len() >= 6 && fields[4..6].iter().all(|f| f.parse::<u16>().is_ok());
+    let (fen, rest) = match has_clocks {
+        true => (fields[..6].join(" "), &fields[6..]),
+        false => (format!("{} 0 1", fields[..4].join(" ")), &fields[4..]),
+    };
+
+    let board = Board::from_fen(&fen, false).ok()?;
+    let rest = rest.join(" ");
+
+    let result = if rest.contains("1/2-1/2") {
+        0.5
+    } else if rest.contains("1-0") {
+        1.0
+    } else if rest.contains("0-1") {
+        0.0
+    } else {
+        let start = rest.find('[')?;
+        let end = start + rest[start..].find(']')?;
+        rest[start + 1..end].trim().parse::<f64>().ok()?
+    };
+
+    (0.0..=1.0)
+        .contains(&result)
+        .then_some(LabelledPosition { board, result })
+}
+
+pub fn sigmoid(x: &f64) -> f64 {
+    1.0 / (1.0 + (-x).exp())
+}
+
+/// Mean squared error between the game results and the win probability
+/// predicted from the score, scaled by `k` per pawn.
+pub fn loss(score_fn: &dyn ScoreFunction, positions: &[LabelledPosition], k: f64) -> f64 {
+    let total = positions
+        .iter()
+        .map(|position| {
+            let predicted = sigmoid(&(k * score_fn.score(&position.board).as_pawns()));
+            (position.result - predicted).powi(2)
+        })
+        .sum::<f64>();
+
+    total / positions.len().max(1) as f64
+}
+
+/// Finds the scaling constant that best fits the current weights, which
+/// keeps the tuner from changing every weight just to rescale the scores.
+pub fn find_k(score_fn: &dyn ScoreFunction, positions: &[LabelledPosition]) -> f64 {
+    let mut best_k = 1.0;
+    let mut best_loss = loss(score_fn, positions, best_k);
+    let mut step = 1.0;
+
+    for _ in 0..4 {
+        let start = (best_k - 10.0 * step).max(step);
+
+        for i in 0..=20 {
+            let k = start + i as f64 * step;
+            let k_loss = loss(score_fn, positions, k);
+
+            if k_loss < best_loss {
+                best_k = k;
+                best_loss = k_loss;
+            }
+        }
+
+        step /= 10.0;
+    }
+
+    best_k
+}
+
+/// Texel-style local search: nudges one weight at a time and keeps the change
+/// whenever it lowers the loss, halving the step once nothing improves.
+pub struct Tuner<F> {
+    make_score_fn: F,
+    k: f64,
+    initial_step: i32,
+    max_iterations: usize,
+}
+
+impl<F, S> Tuner<F>
+where
+    F: Fn(&EvalParams) -> S,
+    S: ScoreFunction,
+{
+    pub fn new(make_score_fn: F, k: f64) -> Self {
+        Self {
+            make_score_fn,
+            k,
+            initial_step: 16,
+            max_iterations: 100,
+        }
+    }
+
+    pub fn with_initial_step(mut self, initial_step: i32) -> Self {
+        self.initial_step = initial_step;
+        self
+    }
+
+    pub fn with_max_iterations(mut self, max_iterations: usize) -> Self {
+        self.max_iterations = max_iterations;
+        self
+    }
+
+    pub fn loss(&self, params: &EvalParams, positions: &[LabelledPosition]) -> f64 {
+        loss(&(self.make_score_fn)(params), positions, self.k)
+    }
+
+    /// Tunes the weights, calling `on_iteration` with the loss after each pass.
+    pub fn tune(
+        &self,
+        params: EvalParams,
+        positions: &[LabelledPosition],
+        mut on_iteration: impl FnMut(usize, f64, &EvalParams),
+    ) -> EvalParams {
+        let mut best = params;
+        let mut best_loss = self.loss(&best, positions);
+        let mut step = self.initial_step.max(1);
+
+        for iteration in 0..self.max_iterations {
+            let mut improved = false;
+            let weight_count = best.clone().weights_mut().len();
+
+            for i in 0..weight_count {
+                for delta in [step, -step] {
+                    let mut trial = best.clone();
+                    *trial.weights_mut()[i].1 += delta;
+
+                    let trial_loss = self.loss(&trial, positions);
+
+                    if trial_loss < best_loss {
+                        best = trial;
+                        best_loss = trial_loss;
+                        improved = true;
+                        break;
+                    }
+                }
+            }
+
+            on_iteration(iteration, best_loss, &best);
+
+            if !improved {
+                if step == 1 {
+                    break;
+                }
+                step /= 2;
+            }
+        }
+
+        best
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::algorithm::score::PawnDifferenceScore;
+
+    #[test]
+    fn test_parse_labelled_position() {
+        let position = parse_labelled_position(
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1 [1.0]",
+        )
+        .unwrap();
+        assert_eq!(position.result, 1.0);
+        assert_eq!(position.board.fullmove_number(), 1);
+
+        let position =
+            parse_labelled_position(r#"8/8/4k3/8/8/4K3/4P3/8 w - - c9 "1/2-1/2";"#).unwrap();
+        assert_eq!(position.result, 0.5);
+
+        let position = parse_labelled_position("8/8/4k3/8/8/4K3/4P3/8 b - - 12 40 0-1").unwrap();
+        assert_eq!(position.result, 0.0);
+        assert_eq!(position.board.halfmove_clock(), 12);
+
+        // no result, bad result and bad FEN
+        assert!(parse_labelled_position("8/8/4k3/8/8/4K3/4P3/8 w - - 0 1").is_none());
+        assert!(parse_labelled_position("8/8/4k3/8/8/4K3/4P3/8 w - - [2.0]").is_none());
+        assert!(parse_labelled_position("8/8/4k3/8 w - - 1-0").is_none());
+    }
+
+    #[test]
+    fn test_sigmoid() {
+        assert_eq!(sigmoid(&0.0), 0.5);
+        assert!(sigmoid(&10.0) > 0.99);
+        assert!(sigmoid(&-10.0) < 0.01);
+    }
+
+    fn knight_beats_pawns() -> Vec<LabelledPosition> {
+        // a knight against three pawns keeps winning in this made up data set,
+        // while equal material draws
+        [
+            ("6k1/5ppp/8/8/8/8/8/1N4K1 w - - 0 1", 1.0),
+            ("6k1/p1p1p3/8/8/8/8/8/1N4K1 w - - 0 1", 1.0),
+            ("1n4k1/8/8/8/8/8/5PPP/6K1 w - - 0 1", 0.0),
+            ("1n4k1/8/8/8/8/8/P1P1P3/6K1 b - - 0 1", 0.0),
+            ("6k1/5ppp/8/8/8/8/5PPP/6K1 w - - 0 1", 0.5),
+            ("1n4k1/8/8/8/8/8/8/1N4K1 w - - 0 1", 0.5),
+        ]
+        .into_iter()
+        .map(|(fen, result)| LabelledPosition {
+            board: Board::from_fen(fen, false).unwrap(),
+            result,
+        })
+        .collect()
+    }
+
+    #[test]
+    fn test_tuner_reduces_loss() {
+        let positions = knight_beats_pawns();
+        let tuner = Tuner::new(
+            |params: &EvalParams| PawnDifferenceScore::new(params.clone()),
+            1.0,
+        );
+
+        let initial = EvalParams::default();
+        let initial_loss = tuner.loss(&initial, &positions);
+
+        let mut losses = Vec::new();
+        let tuned = tuner.tune(initial, &positions, |_, loss, _| losses.push(loss));
+        let tuned_loss = tuner.loss(&tuned, &positions);
+
+        assert!(tuned_loss < initial_loss);
+        assert!(losses.windows(2).all(|pair| pair[1] <= pair[0]));
+        assert!(tuned.knight > 3 * tuned.pawn);
+    }
+
+    #[test]
+    fn test_find_k() {
+        let positions = knight_beats_pawns();
+        let score_fn = PawnDifferenceScore::default();
+
+        let k = find_k(&score_fn, &positions);
+
+        assert!(k > 0.0);
+        assert!(loss(&score_fn, &positions, k) <= loss(&score_fn, &positions, 1.0));
+    }
+}
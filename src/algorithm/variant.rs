@@ -0,0 +1,107 @@
+use cozy_chess::Board;
+use rand::Rng;
+use std::fmt;
+
+/// Where a game starts from.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Variant {
+    #[default]
+    Standard,
+    /// Fischer Random, numbered 0-959 by the Scharnagl scheme. Number 518 is
+    /// the standard start position.
+    Chess960(u32),
+}
+
+impl Variant {
+    pub const CHESS960_POSITIONS: u32 = 960;
+
+    pub fn chess960(number: u32) -> Option<Self> {
+        match number < Self::CHESS960_POSITIONS {
+            true => Some(Self::Chess960(number)),
+            false => None,
+        }
+    }
+
+    pub fn random_chess960(rng: &mut impl Rng) -> Self {
+        Self::Chess960(rng.gen_range(0..Self::CHESS960_POSITIONS))
+    }
+
+    pub fn start(&self) -> Board {
+        match self {
+            Variant::Standard => Board::startpos(),
+            Variant::Chess960(number) => Board::chess960_startpos(*number),
+        }
+    }
+
+    pub fn is_chess960(&self) -> bool {
+        matches!(self, Variant::Chess960(_))
+    }
+
+    /// FEN for `board`. Chess960 needs Shredder FEN, which names the castling
+    /// rooks by file, since the rooks don't have to start in the corners.
+    pub fn fen(&self, board: &Board) -> String {
+        match self {
+            Variant::Standard => format!("{}", board),
+            Variant::Chess960(_) => format!("{:#}", board),
+        }
+    }
+}
+
+impl fmt::Display for Variant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Variant::Standard => write!(f, "Standard"),
+            Variant::Chess960(number) => write!(f, "Chess960 #{}", number),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use cozy_chess::{Color, Piece};
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    #[test]
+    fn test_chess960_start_positions() {
+        assert_eq!(Variant::chess960(518).unwrap().start(), Board::startpos());
+        assert_eq!(Variant::chess960(960), None);
+
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        for _ in 0..20 {
+            let variant = Variant::random_chess960(&mut rng);
+            let board = variant.start();
+
+            // the king sits between its rooks, which it can castle with
+            let king = board.king(Color::White);
+            let rights = board.castle_rights(Color::White);
+            assert!(rights.long.unwrap() < king.file());
+            assert!(rights.short.unwrap() > king.file());
+            assert_eq!(
+                board.colored_pieces(Color::White, Piece::Rook).len(),
+                2,
+                "{}",
+                variant
+            );
+
+            // the Shredder FEN reads back as the same position
+            let fen = variant.fen(&board);
+            assert_eq!(Board::from_fen(&fen, true).unwrap(), board, "{}", fen);
+        }
+    }
+
+    #[test]
+    fn test_chess960_castling_onto_rook() {
+        // king on b1 with rooks on a1 and h1: castling long moves the king to
+        // c1 and the rook to d1
+        let mut board =
+            Board::from_fen("rk5r/pppppppp/8/8/8/8/PPPPPPPP/RK5R w HAha - 0 1", true).unwrap();
+        board.play("b1a1".parse().unwrap());
+
+        assert_eq!(
+            format!("{:#}", board),
+            "rk5r/pppppppp/8/8/8/8/PPPPPPPP/2KR3R b ha - 1 1"
+        );
+    }
+}
@@ -1,17 +1,25 @@
 use crate::{
     algorithm::{
+        book::OpeningBook,
         choose::GreedyChooser,
-        eval::{AlphaBetaNegamax, Evaluator},
+        eval::{AlphaBetaNegamax, Evaluator, Progress},
+        handicap::Handicap,
+        puzzle::PuzzleAttempt,
+        record::GameRecord,
         score::PawnDifferenceScore,
+        tablebase::SyzygyTablebase,
+        variant::Variant,
         ComputerPlayer,
     },
     game::{ChessBoard, Flip},
     opponent::OpponentMaker,
+    puzzle::{provide_puzzle_context, PuzzlePanel},
+    review::GameReview,
 };
-use cozy_chess::{Board, Color, GameStatus};
+use cozy_chess::{Board, Color, GameStatus, Move};
 use leptos::*;
 use leptos_meta::*;
-use std::rc::Rc;
+use std::sync::Arc;
 
 #[component]
 pub fn App(cx: Scope) -> impl IntoView {
@@ -33,14 +41,42 @@ fn Home(cx: Scope) -> impl IntoView {
     provide_context(cx, user_color);
     provide_context(cx, set_user_color);
 
-    let (opponent, set_opponent) = create_signal(
+    let (thinking, set_thinking) = create_signal(cx, None::<Progress>);
+    provide_context(cx, thinking);
+    provide_context(cx, set_thinking);
+
+    let (variant, set_variant) = create_signal(cx, Variant::Standard);
+    provide_context(cx, variant);
+    provide_context(cx, set_variant);
+
+    let (handicap, set_handicap) = create_signal(cx, Handicap::None);
+    provide_context(cx, handicap);
+    provide_context(cx, set_handicap);
+
+    provide_puzzle_context(cx);
+
+    // better move drawn on the board by the game review
+    let (hint, set_hint) = create_signal(cx, None::<Move>);
+    provide_context(cx, hint);
+    provide_context(cx, set_hint);
+
+    let mut computer_player = ComputerPlayer::new(
+        Arc::new(AlphaBetaNegamax::new(4)),
+        Arc::new(PawnDifferenceScore::default()),
+        Arc::new(GreedyChooser::default()),
+    )
+    .with_progress(Arc::new(move |progress| set_thinking.set(Some(*progress))));
+    computer_player.change_book(Some(Arc::new(OpeningBook::embedded())));
+    computer_player.change_tablebase(Some(Arc::new(SyzygyTablebase::bundled())));
+
+    let (record, set_record) = create_signal(
         cx,
-        ComputerPlayer::new(
-            Rc::new(AlphaBetaNegamax::new(4)),
-            Rc::new(PawnDifferenceScore::default()),
-            Rc::new(GreedyChooser::default()),
-        ),
+        GameRecord::new(Board::startpos(), computer_player.seed()),
     );
+    provide_context(cx, record);
+    provide_context(cx, set_record);
+
+    let (opponent, set_opponent) = create_signal(cx, computer_player);
     provide_context(cx, opponent);
     provide_context(cx, set_opponent);
 
@@ -59,6 +95,56 @@ pub fn LeftBar(cx: Scope) -> impl IntoView {
     let set_board = use_context::<WriteSignal<Board>>(cx).expect("should be board here");
     let user_color = use_context::<ReadSignal<Color>>(cx).expect("should be color here");
     let set_user_color = use_context::<WriteSignal<Color>>(cx).expect("should be color here");
+    let record = use_context::<ReadSignal<GameRecord>>(cx).expect("should be record here");
+    let set_record = use_context::<WriteSignal<GameRecord>>(cx).expect("should be record here");
+    let set_opponent =
+        use_context::<WriteSignal<ComputerPlayer>>(cx).expect("should be opponent here");
+    let thinking =
+        use_context::<ReadSignal<Option<Progress>>>(cx).expect("should be thinking here");
+    let set_thinking =
+        use_context::<WriteSignal<Option<Progress>>>(cx).expect("should be thinking here");
+    let variant = use_context::<ReadSignal<Variant>>(cx).expect("should be variant here");
+    let set_variant = use_context::<WriteSignal<Variant>>(cx).expect("should be variant here");
+    let handicap = use_context::<ReadSignal<Handicap>>(cx).expect("should be handicap here");
+    let set_handicap = use_context::<WriteSignal<Handicap>>(cx).expect("should be handicap here");
+    let set_puzzle =
+        use_context::<WriteSignal<Option<PuzzleAttempt>>>(cx).expect("should be puzzle here");
+    let set_hint = use_context::<WriteSignal<Option<Move>>>(cx).expect("should be hint here");
+
+    // replaying a seed with the same moves from the player replays the game.
+    // Odds always come out of the computer's army
+    let new_game = move |seed: u64| {
+        let variant = variant.get_untracked();
+        let handicap = handicap.get_untracked();
+        let start = handicap.apply(&variant.start(), user_color.get_untracked().flipped());
+
+        set_opponent.update(|player| {
+            player.stop();
+            player.reseed(seed);
+        });
+        set_thinking.set(None);
+        set_puzzle.set(None);
+        set_hint.set(None);
+        set_record.set(
+            GameRecord::new(start.clone(), seed)
+                .with_variant(variant)
+                .with_handicap(handicap),
+        );
+        set_board.set(start);
+        log!(
+            "Board reset to {} with {} and seed {}",
+            variant,
+            handicap,
+            seed
+        );
+    };
+
+    // a new Chess960 game gets a new start position too
+    let shuffle = move || {
+        if variant.get_untracked().is_chess960() {
+            set_variant.set(Variant::random_chess960(&mut rand::thread_rng()));
+        }
+    };
 
     view! {cx,
         <aside class="fixed h-full flex bg-page-bar lg:flex flex-shrink-0 flex-col w-56 transition-width duration-75">
@@ -66,10 +152,10 @@ pub fn LeftBar(cx: Scope) -> impl IntoView {
                 <ul>
                     <li class="hover:bg-page-dark">
                         <button class="text-page-text text-3xl font-bold w-full text-left my-4 ml-2 hover:text-white"
-                        on:click=move |_| {
-                                set_board.set(Board::startpos());
-                                log!("Board reset");
-                        }>
+                        on:click=move |_| cx.batch(|| {
+                            shuffle();
+                            new_game(rand::random());
+                        })>
                             "New Game"
                         </button>
                     </li>
@@ -79,8 +165,8 @@ pub fn LeftBar(cx: Scope) -> impl IntoView {
                             cx.batch(|| {
                                 set_user_color.update(|c| c.flip());
                                 log!("Colour changed to {}", user_color.get_untracked());
-                                set_board.set(Board::startpos());
-                                log!("Board reset");
+                                shuffle();
+                                new_game(rand::random());
                             })
                         }>
                             {move || match user_color.get() {
@@ -89,7 +175,116 @@ pub fn LeftBar(cx: Scope) -> impl IntoView {
                             }}
                         </button>
                     </li>
-
+                    <li class="text-page-text text-xl text-left mx-2 my-4">
+                        <label class="flex flex-col gap-1">
+                            "Seed"
+                            <input
+                                type="number"
+                                min="0"
+                                class="bg-page-dark border-2 border-page-dark rounded-md"
+                                prop:value=move || record.get().seed.to_string()
+                                on:change=move |ev| {
+                                    match event_target_value(&ev).parse::<u64>() {
+                                        Ok(seed) => cx.batch(|| new_game(seed)),
+                                        Err(_) => warn!("Ignoring invalid seed"),
+                                    }
+                                }
+                            />
+                        </label>
+                    </li>
+                    <li class="text-page-text text-xl text-left mx-2 my-4">
+                        <label class="flex flex-col gap-1">
+                            "Variant"
+                            <select
+                                class="bg-page-dark border-2 border-page-dark rounded-md"
+                                prop:value=move || match variant.get() {
+                                    Variant::Standard => "standard",
+                                    Variant::Chess960(_) => "chess960",
+                                }
+                                on:change=move |ev| {
+                                    let chosen = match event_target_value(&ev).as_str() {
+                                        "chess960" => Variant::random_chess960(&mut rand::thread_rng()),
+                                        _ => Variant::Standard,
+                                    };
+                                    cx.batch(|| {
+                                        set_variant.set(chosen);
+                                        new_game(rand::random());
+                                    })
+                                }
+                            >
+                                <option value="standard">"Standard"</option>
+                                <option value="chess960">"Chess960"</option>
+                            </select>
+                        </label>
+                    </li>
+                    <Show when=move || variant.get().is_chess960() fallback=|_| {}>
+                        <li class="text-page-text text-xl text-left mx-2 my-4">
+                            <label class="flex flex-col gap-1">
+                                "Position"
+                                <input
+                                    type="number"
+                                    min="0"
+                                    max="959"
+                                    class="bg-page-dark border-2 border-page-dark rounded-md"
+                                    prop:value=move || match variant.get() {
+                                        Variant::Chess960(number) => number.to_string(),
+                                        Variant::Standard => String::new(),
+                                    }
+                                    on:change=move |ev| {
+                                        match event_target_value(&ev).parse().ok().and_then(Variant::chess960) {
+                                            Some(chosen) => cx.batch(|| {
+                                                set_variant.set(chosen);
+                                                new_game(rand::random());
+                                            }),
+                                            None => warn!("Ignoring invalid Chess960 position"),
+                                        }
+                                    }
+                                />
+                            </label>
+                        </li>
+                    </Show>
+                    <li class="text-page-text text-xl text-left mx-2 my-4">
+                        <label class="flex flex-col gap-1">
+                            "Odds"
+                            <select
+                                class="bg-page-dark border-2 border-page-dark rounded-md"
+                                prop:value=move || {
+                                    Handicap::ALL
+                                        .iter()
+                                        .position(|odds| *odds == handicap.get())
+                                        .unwrap_or_default()
+                                        .to_string()
+                                }
+                                on:change=move |ev| {
+                                    let chosen = event_target_value(&ev)
+                                        .parse::<usize>()
+                                        .ok()
+                                        .and_then(|i| Handicap::ALL.get(i).copied())
+                                        .unwrap_or_default();
+                                    cx.batch(|| {
+                                        set_handicap.set(chosen);
+                                        new_game(rand::random());
+                                    })
+                                }
+                            >
+                                {Handicap::ALL
+                                    .iter()
+                                    .enumerate()
+                                    .map(|(i, odds)| view! { cx, <option value=i.to_string()>{odds.to_string()}</option> })
+                                    .collect::<Vec<_>>()}
+                            </select>
+                        </label>
+                    </li>
+                    <PuzzlePanel/>
+                    <li class="text-page-text text-xl text-left mx-2 my-4">
+                        "Engine"
+                        <div class="text-base break-words">
+                            {move || match thinking.get() {
+                                Some(progress) => progress.to_string(),
+                                None => "Waiting".to_string(),
+                            }}
+                        </div>
+                    </li>
                 </ul>
             </div>
         </aside>
@@ -102,6 +297,7 @@ fn MainContent(cx: Scope) -> impl IntoView {
         <div class="flex-1 grid grid-cols-2 bg-page-background lg:ml-56">
             <div class="mt-8 ml-16">
                 <ChessBoard/>
+                <GameReview/>
             </div>
             <div class="my-8 ml-8 mr-48 bg-page-bar rounded-[1rem]">
                 <OpponentMaker/>
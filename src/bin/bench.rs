@@ -0,0 +1,181 @@
+//! Runs every evaluator and score function over a suite of test positions.
+//!
+//! Usage: bench [--epd <file>] [--depth <n>] [--iterations <n>] [--threads <n>]
+//!
+//! Each position names its best moves in an EPD `bm` operation, and counts as
+//! solved when a greedy player would choose one of them. The built-in Win At
+//! Chess positions are used unless another file is given. Nodes are the
+//! counts each search reports as it finishes a depth, or the playouts for
+//! Monte Carlo tree search. Negamax searches at most 3 plies, as it has no
+//! pruning.
+//! `--threads` splits the root moves of the alpha-beta and principal
+//! variation searches between that many threads.
+
+use std::{
+    env, fs, process,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tailwind_csr_trunk::algorithm::{
+    bench::{parse_epd, run_position, to_san, EpdPosition, WIN_AT_CHESS},
+    eval::{
+        mcts::MonteCarloTreeSearch, AlphaBetaNegamax, Evaluator, NaiveEvaluator, Negamax,
+        PrincipalVariationSearch,
+    },
+    score::{CompositeScore, PawnDifferenceScore, ScoreFunction},
+};
+
+struct Args {
+    epd: Option<String>,
+    depth: usize,
+    iterations: usize,
+    threads: usize,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut args = env::args().skip(1);
+    let mut epd = None;
+    let mut depth = 4;
+    let mut iterations = 2_000;
+    let mut threads = 1;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--epd" => epd = Some(args.next().ok_or("--epd needs a file")?),
+            "--depth" => {
+                depth = args
+                    .next()
+                    .and_then(|n| n.parse().ok())
+                    .ok_or("--depth needs a number")?
+            }
+            "--iterations" => {
+                iterations = args
+                    .next()
+                    .and_then(|n| n.parse().ok())
+                    .ok_or("--iterations needs a number")?
+            }
+            "--threads" => {
+                threads = args
+                    .next()
+                    .and_then(|n| n.parse().ok())
+                    .ok_or("--threads needs a number")?
+            }
+            _ => return Err(format!("unexpected argument {}", arg)),
+        }
+    }
+
+    Ok(Args {
+        epd,
+        depth,
+        iterations,
+        threads,
+    })
+}
+
+fn run(args: Args) -> Result<(), String> {
+    let text = match &args.epd {
+        Some(path) => {
+            fs::read_to_string(path).map_err(|err| format!("could not read {}: {}", path, err))?
+        }
+        None => WIN_AT_CHESS.to_string(),
+    };
+
+    let positions = text
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(parse_epd)
+        .collect::<Vec<EpdPosition>>();
+
+    if positions.is_empty() {
+        return Err("no positions with a bm or am operation".to_string());
+    }
+
+    let evaluators: Vec<(&str, Box<dyn Evaluator>)> = vec![
+        ("1 Move Lookahead", Box::new(NaiveEvaluator)),
+        ("Negamax", Box::new(Negamax::new(args.depth.min(3)))),
+        (
+            "Negamax with Alpha-Beta Pruning",
+            Box::new(AlphaBetaNegamax::new(args.depth).with_threads(args.threads)),
+        ),
+        (
+            "Principal Variation Search",
+            Box::new(PrincipalVariationSearch::new(args.depth).with_threads(args.threads)),
+        ),
+        (
+            "Monte Carlo Tree Search",
+            Box::new(MonteCarloTreeSearch::new(args.iterations)),
+        ),
+    ];
+
+    let score_fns: Vec<(&str, Arc<dyn ScoreFunction>)> = vec![
+        (
+            "Pawn Difference Score",
+            Arc::new(PawnDifferenceScore::default()),
+        ),
+        ("Composite Score", Arc::new(CompositeScore::default())),
+    ];
+
+    let mut summary = vec![];
+
+    for (eval_name, evaluator) in &evaluators {
+        for (score_name, score_fn) in &score_fns {
+            println!("{} / {}", eval_name, score_name);
+
+            let (mut solved, mut nodes, mut time) = (0, 0, Duration::ZERO);
+
+            for position in &positions {
+                let start = Instant::now();
+                let result = run_position(evaluator.as_ref(), score_fn.clone(), position);
+                let elapsed = start.elapsed();
+
+                let chosen = match result.chosen {
+                    Some(mov) => to_san(&position.board, &mov),
+                    None => "-".to_string(),
+                };
+
+                println!(
+                    "  {:<10} {:<8} {:<6} {:>10} nodes {:>8.1} ms",
+                    position.id,
+                    chosen,
+                    match result.solved {
+                        true => "solved",
+                        false => "missed",
+                    },
+                    result.nodes,
+                    elapsed.as_secs_f64() * 1000.0
+                );
+
+                solved += result.solved as usize;
+                nodes += result.nodes;
+                time += elapsed;
+            }
+
+            summary.push((eval_name, score_name, solved, nodes, time));
+        }
+    }
+
+    println!();
+    for (eval_name, score_name, solved, nodes, time) in summary {
+        println!(
+            "{:<32} {:<22} {:>3}/{} solved {:>12} nodes {:>10.1} ms",
+            eval_name,
+            score_name,
+            solved,
+            positions.len(),
+            nodes,
+            time.as_secs_f64() * 1000.0
+        );
+    }
+
+    Ok(())
+}
+
+fn main() {
+    let result = parse_args().and_then(run);
+
+    if let Err(err) = result {
+        eprintln!("error: {}", err);
+        eprintln!("usage: bench [--epd <file>] [--depth <n>] [--iterations <n>] [--threads <n>]");
+        process::exit(1);
+    }
+}
@@ -0,0 +1,187 @@
+//! Estimates the rating gap between adjacent strength levels by playing them
+//! against each other.
+//!
+//! Usage: calibrate [--games <n>] [--max-plies <n>] [--threads <n>]
+//!                  [--seed <n>] [--syzygy <dir>]
+//!
+//! Each pair of neighbouring levels plays `n` games, swapping colours every
+//! game and starting from a random line of the built-in opening book. The
+//! gaps, chained up from the weakest level at 400, give the ratings stored in
+//! `strength::LEVELS`.
+//!
+//! Every game's players are seeded from `--seed`, which also picks the opening
+//! lines since White's player chooses from the book. The seed is random and
+//! printed when not given, so a run can be replayed exactly.
+//!
+//! `--threads` splits each search's root moves between that many threads. The
+//! levels' node limits are then shared by the threads, so games no longer
+//! replay exactly even with the same seed.
+//!
+//! `--syzygy` adds the Syzygy tables in a directory to the small ones built
+//! in, so both levels play endings perfectly and the gaps only measure the
+//! rest of the game.
+
+use cozy_chess::Board;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use std::{env, process, sync::Arc};
+use tailwind_csr_trunk::algorithm::{
+    book::OpeningBook,
+    score::PawnDifferenceScore,
+    strength::{elo_difference, play_game, GameResult, Strength, LEVELS},
+    tablebase::{SyzygyTablebase, Tablebase},
+    ComputerPlayer,
+};
+
+struct Args {
+    games: usize,
+    max_plies: usize,
+    threads: usize,
+    seed: u64,
+    syzygy: Option<String>,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut args = env::args().skip(1);
+    let mut games = 20;
+    let mut max_plies = 200;
+    let mut threads = 1;
+    let mut seed = rand::random();
+    let mut syzygy = None;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--games" => {
+                games = args
+                    .next()
+                    .and_then(|n| n.parse().ok())
+                    .ok_or("--games needs a number")?
+            }
+            "--max-plies" => {
+                max_plies = args
+                    .next()
+                    .and_then(|n| n.parse().ok())
+                    .ok_or("--max-plies needs a number")?
+            }
+            "--threads" => {
+                threads = args
+                    .next()
+                    .and_then(|n| n.parse().ok())
+                    .ok_or("--threads needs a number")?
+            }
+            "--seed" => {
+                seed = args
+                    .next()
+                    .and_then(|n| n.parse().ok())
+                    .ok_or("--seed needs a number")?
+            }
+            "--syzygy" => syzygy = Some(args.next().ok_or("--syzygy needs a directory")?),
+            _ => return Err(format!("unexpected argument {}", arg)),
+        }
+    }
+
+    Ok(Args {
+        games,
+        max_plies,
+        threads,
+        seed,
+        syzygy,
+    })
+}
+
+fn describe(strength: &Strength) -> String {
+    match strength.node_limit {
+        Some(limit) => format!("depth {}, {} nodes", strength.depth, limit),
+        None => format!("depth {}", strength.depth),
+    }
+}
+
+fn run(args: Args) -> Result<(), String> {
+    let book = Arc::new(OpeningBook::embedded());
+    let mut tablebase = SyzygyTablebase::bundled();
+    if let Some(dir) = &args.syzygy {
+        let files = tablebase
+            .add_directory(dir)
+            .map_err(|err| format!("can't read tables in {}: {}", dir, err))?;
+        println!(
+            "{} table files, up to {} pieces",
+            files,
+            tablebase.max_pieces()
+        );
+    }
+    let tablebase: Arc<dyn Tablebase> = Arc::new(tablebase);
+
+    let score_fn = Arc::new(PawnDifferenceScore::default());
+    let mut rating = LEVELS[0].rating as f64;
+
+    println!("seed {}", args.seed);
+    let mut seeds = ChaCha8Rng::seed_from_u64(args.seed);
+
+    println!("level {} ({}): {:.0}", 0, describe(&LEVELS[0]), rating);
+
+    for (i, pair) in LEVELS.windows(2).enumerate() {
+        let player = |strength: &Strength, seed: u64| {
+            let search = strength.search().with_threads(args.threads);
+            let mut player =
+                ComputerPlayer::new(Arc::new(search), score_fn.clone(), strength.chooser())
+                    .with_seed(seed);
+            player.change_book(Some(book.clone()));
+            player.change_tablebase(Some(tablebase.clone()));
+            player
+        };
+
+        // counted from the stronger level's point of view
+        let (mut wins, mut draws, mut losses) = (0, 0, 0);
+
+        for game in 0..args.games {
+            let stronger_is_white = game % 2 == 0;
+            let weaker = player(&pair[0], seeds.gen());
+            let stronger = player(&pair[1], seeds.gen());
+
+            let result = match stronger_is_white {
+                true => play_game(&stronger, &weaker, Board::startpos(), args.max_plies),
+                false => play_game(&weaker, &stronger, Board::startpos(), args.max_plies),
+            };
+
+            match (result, stronger_is_white) {
+                (GameResult::Draw, _) => draws += 1,
+                (GameResult::WhiteWin, true) | (GameResult::BlackWin, false) => wins += 1,
+                _ => losses += 1,
+            }
+        }
+
+        let (difference, error) = elo_difference(wins, draws, losses);
+        rating += difference;
+
+        println!(
+            "level {} ({}): {:.0}, {:+.0} ± {:.0} over level {} (+{} ={} -{})",
+            i + 1,
+            describe(&pair[1]),
+            rating,
+            difference,
+            error,
+            i,
+            wins,
+            draws,
+            losses
+        );
+    }
+
+    Ok(())
+}
+
+fn main() {
+    let args = parse_args().unwrap_or_else(|err| {
+        eprintln!("error: {}", err);
+        eprintln!(
+            "usage: calibrate [--games <n>] [--max-plies <n>] [--threads <n>] [--seed <n>] \
+             [--syzygy <dir>]"
+        );
+        process::exit(1);
+    });
+
+    if let Err(err) = run(args) {
+        eprintln!("error: {}", err);
+        process::exit(1);
+    }
+}
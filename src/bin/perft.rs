@@ -0,0 +1,80 @@
+//! Counts the positions reachable from a position, to check move generation.
+//!
+//! Usage: perft <depth> [fen] [--sorted]
+//!
+//! Prints the count below each legal move and the total, starting from the
+//! initial position unless a FEN is given. With `--sorted` the tree is walked
+//! with the search's move ordering instead, which stops at the first position
+//! where it drops or repeats a legal move.
+
+use cozy_chess::Board;
+use std::{env, process};
+use tailwind_csr_trunk::algorithm::perft::{divide, sorted_perft};
+
+struct Args {
+    depth: usize,
+    board: Board,
+    sorted: bool,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut depth = None;
+    let mut fen = None;
+    let mut sorted = false;
+
+    for arg in env::args().skip(1) {
+        match arg.as_str() {
+            "--sorted" => sorted = true,
+            _ if depth.is_none() => {
+                depth = Some(arg.parse().map_err(|_| format!("invalid depth {}", arg))?)
+            }
+            _ if fen.is_none() => fen = Some(arg),
+            _ => return Err(format!("unexpected argument {}", arg)),
+        }
+    }
+
+    let board = match fen {
+        Some(fen) => {
+            Board::from_fen(&fen, false).map_err(|err| format!("invalid FEN: {:?}", err))?
+        }
+        None => Board::startpos(),
+    };
+
+    Ok(Args {
+        depth: depth.ok_or("missing depth")?,
+        board,
+        sorted,
+    })
+}
+
+fn run(args: Args) -> Result<(), String> {
+    if args.sorted {
+        let nodes = sorted_perft(&args.board, args.depth).map_err(|err| err.to_string())?;
+        println!("{} nodes, every position ordered correctly", nodes);
+        return Ok(());
+    }
+
+    let moves = divide(&args.board, args.depth);
+
+    for (mov, nodes) in &moves {
+        println!("{}: {}", mov, nodes);
+    }
+
+    println!();
+    println!(
+        "{} nodes",
+        moves.iter().map(|(_, nodes)| nodes).sum::<u64>()
+    );
+
+    Ok(())
+}
+
+fn main() {
+    let result = parse_args().and_then(run);
+
+    if let Err(err) = result {
+        eprintln!("error: {}", err);
+        eprintln!("usage: perft <depth> [fen] [--sorted]");
+        process::exit(1);
+    }
+}
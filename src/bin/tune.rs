@@ -0,0 +1,105 @@
+//! Tunes the evaluation weights from positions labelled with game results.
+//!
+//! Usage: tune <positions> [--params <json>] [--output <json>] [--iterations <n>]
+//!
+//! The positions file holds one FEN or EPD line per position followed by the
+//! result, e.g. `... w - - 0 1 [1.0]` or `... w - - c9 "1/2-1/2";`. The tuned
+//! weights are written as JSON that can be loaded in the opponent settings.
+
+use std::{env, fs, process};
+use tailwind_csr_trunk::algorithm::{
+    params::EvalParams,
+    score::CompositeScore,
+    tune::{find_k, parse_labelled_position, Tuner},
+};
+
+struct Args {
+    positions: String,
+    params: Option<String>,
+    output: String,
+    iterations: usize,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut args = env::args().skip(1);
+    let mut positions = None;
+    let mut params = None;
+    let mut output = "tuned-params.json".to_string();
+    let mut iterations = 100;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--params" => params = Some(args.next().ok_or("--params needs a file")?),
+            "--output" => output = args.next().ok_or("--output needs a file")?,
+            "--iterations" => {
+                iterations = args
+                    .next()
+                    .and_then(|n| n.parse().ok())
+                    .ok_or("--iterations needs a number")?
+            }
+            _ if positions.is_none() => positions = Some(arg),
+            _ => return Err(format!("unexpected argument {}", arg)),
+        }
+    }
+
+    Ok(Args {
+        positions: positions.ok_or("missing positions file")?,
+        params,
+        output,
+        iterations,
+    })
+}
+
+fn run(args: Args) -> Result<(), String> {
+    let text = fs::read_to_string(&args.positions)
+        .map_err(|err| format!("could not read {}: {}", args.positions, err))?;
+
+    let positions = text
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(parse_labelled_position)
+        .collect::<Vec<_>>();
+
+    if positions.is_empty() {
+        return Err(format!("no labelled positions in {}", args.positions));
+    }
+
+    let params = match &args.params {
+        Some(path) => {
+            let json = fs::read_to_string(path)
+                .map_err(|err| format!("could not read {}: {}", path, err))?;
+            EvalParams::from_json(&json).map_err(|err| format!("invalid {}: {}", path, err))?
+        }
+        None => EvalParams::default(),
+    };
+
+    let make_score_fn = |params: &EvalParams| CompositeScore::new(params.clone());
+
+    let k = find_k(&make_score_fn(&params), &positions);
+    let tuner = Tuner::new(make_score_fn, k).with_max_iterations(args.iterations);
+
+    println!("Loaded {} positions", positions.len());
+    println!("Scaling constant k = {:.3}", k);
+    println!("Initial loss {:.6}", tuner.loss(&params, &positions));
+
+    let tuned = tuner.tune(params, &positions, |iteration, loss, _| {
+        println!("Iteration {:>3}: loss {:.6}", iteration + 1, loss);
+    });
+
+    fs::write(&args.output, tuned.to_json())
+        .map_err(|err| format!("could not write {}: {}", args.output, err))?;
+
+    println!("Wrote tuned parameters to {}", args.output);
+
+    Ok(())
+}
+
+fn main() {
+    let result = parse_args().and_then(run);
+
+    if let Err(err) = result {
+        eprintln!("error: {}", err);
+        eprintln!("usage: tune <positions> [--params <json>] [--output <json>] [--iterations <n>]");
+        process::exit(1);
+    }
+}
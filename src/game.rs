@@ -1,23 +1,17 @@
-use crate::algorithm::{
-    choose::GreedyChooser,
-    eval::{AlphaBetaNegamax, NaiveEvaluator, Negamax, Negascout},
-    score::{piece_value, PawnDifferenceScore},
-    ComputerPlayer,
+use crate::{
+    algorithm::{
+        choose::GreedyChooser,
+        eval::{AlphaBetaNegamax, NaiveEvaluator, Negamax, Negascout},
+        puzzle::{PuzzleAttempt, PuzzleStats},
+        record::GameRecord,
+        score::{piece_value, PawnDifferenceScore},
+        ComputerPlayer,
+    },
+    puzzle::check_puzzle_move,
 };
-use cozy_chess::{Board, Color, GameStatus, Piece, PieceMoves, Square};
+use cozy_chess::{Board, Color, File, GameStatus, Move, Piece, PieceMoves, Square};
 use leptos::*;
-use std::{collections::HashMap, hash::Hash};
-
-fn map_difference(a: HashMap<Piece, usize>, b: HashMap<Piece, usize>) -> HashMap<Piece, usize> {
-    let mut diff = HashMap::new();
-
-    for (piece, count) in a {
-        let count_b = b.get(&piece).unwrap_or(&0);
-        diff.insert(piece, count - *count_b);
-    }
-
-    diff
-}
+use std::hash::Hash;
 
 pub trait Flip {
     fn flip(&mut self);
@@ -57,16 +51,29 @@ impl MovePicker {
         }
     }
 
-    fn set_square(&mut self, square: Square) {
-        if self.from.is_some() {
-            if self.to.is_some() {
+    // castling is played by moving the king onto its own rook, which is the
+    // only way to tell it apart from a plain king move in Chess960. Clicking
+    // where the king ends up also works when no plain king move goes there,
+    // and clicking another of your own pieces picks that one up instead
+    fn set_square(&mut self, square: Square, board: &Board) {
+        match (self.from, self.to) {
+            (Some(from), None) => {
+                let targets = legal_targets(board, from);
+
+                if targets.contains(&square) {
+                    self.to = Some(square);
+                } else if let Some(rook) = castling_rook(board, from, square) {
+                    self.to = Some(rook);
+                } else if board.color_on(square) == Some(board.side_to_move()) {
+                    self.from = Some(square);
+                } else {
+                    self.to = Some(square);
+                }
+            }
+            _ => {
                 self.from = Some(square);
                 self.to = None;
-            } else {
-                self.to = Some(square);
             }
-        } else {
-            self.from = Some(square);
         }
     }
 
@@ -89,6 +96,34 @@ impl MovePicker {
     }
 }
 
+fn legal_targets(board: &Board, from: Square) -> Vec<Square> {
+    let mut targets = Vec::new();
+    board.generate_moves_for(from.bitboard(), |moves| {
+        targets.extend(moves.into_iter().map(|mov| mov.to));
+        false
+    });
+    targets
+}
+
+// the rook to castle with when the king at `from` is sent to `square`, the
+// king's destination on the C or G file
+fn castling_rook(board: &Board, from: Square, square: Square) -> Option<Square> {
+    let side = board.side_to_move();
+    if board.king(side) != from || square.rank() != from.rank() {
+        return None;
+    }
+
+    let rights = board.castle_rights(side);
+    let rook_file = match square.file() {
+        File::G => rights.short,
+        File::C => rights.long,
+        _ => None,
+    }?;
+
+    let rook = Square::new(rook_file, from.rank());
+    legal_targets(board, from).contains(&rook).then_some(rook)
+}
+
 fn piece_to_img_path(colour: Option<Color>, piece: Option<Piece>) -> String {
     let color = match colour {
         Some(Color::White) => "w",
@@ -133,23 +168,33 @@ fn filter_moves(
     false
 }
 
+// resolves on a later turn of the browser's event loop, once input has been
+// handled and the page repainted
+pub async fn next_tick() {
+    let promise = js_sys::Promise::new(&mut |resolve, _| {
+        if window().set_timeout_with_callback(&resolve).is_err() {
+            let _ = resolve.call0(&wasm_bindgen::JsValue::NULL);
+        }
+    });
+
+    let _ = wasm_bindgen_futures::JsFuture::from(promise).await;
+}
+
 #[component]
 pub fn ChessBoard(cx: Scope) -> impl IntoView {
     let board = use_context::<ReadSignal<Board>>(cx).expect("to have found board");
     let set_board = use_context::<WriteSignal<Board>>(cx).expect("to have found set_board");
     let user_color = use_context::<ReadSignal<Color>>(cx).expect("to have found user_color");
     let opponent = use_context::<ReadSignal<ComputerPlayer>>(cx).expect("to have found opponent");
+    let set_record = use_context::<WriteSignal<GameRecord>>(cx).expect("to have found set_record");
 
-    let all_pieces_white = HashMap::from([
-        (Piece::Pawn, 8),
-        (Piece::Knight, 2),
-        (Piece::Bishop, 2),
-        (Piece::Rook, 2),
-        (Piece::Queen, 1),
-        (Piece::King, 1),
-    ]);
-
-    let all_pieces_black = all_pieces_white.clone();
+    let record = use_context::<ReadSignal<GameRecord>>(cx).expect("to have found record");
+    let puzzle =
+        use_context::<ReadSignal<Option<PuzzleAttempt>>>(cx).expect("to have found puzzle");
+    let set_puzzle =
+        use_context::<WriteSignal<Option<PuzzleAttempt>>>(cx).expect("to have found set_puzzle");
+    let set_stats = use_context::<WriteSignal<PuzzleStats>>(cx).expect("to have found set_stats");
+    let set_hint = use_context::<WriteSignal<Option<Move>>>(cx).expect("to have found set_hint");
 
     let (picker, set_picker) = create_signal(cx, MovePicker::new());
 
@@ -173,83 +218,91 @@ pub fn ChessBoard(cx: Scope) -> impl IntoView {
             if (moves.get().len() == 1) & picker.get().to().is_some() {
                 let mov = moves.get()[0];
 
-                log!("User Playing {:?}", mov);
-
-                cx.batch(|| {
-                    set_board.update(|b| b.play(mov));
+                // a wrong puzzle move isn't played, the player tries again
+                if !check_puzzle_move(set_puzzle, set_stats, mov) {
+                    log!("User missed the puzzle with {:?}", mov);
                     set_picker.update(|p| p.clear());
-                });
-            }
-        }
-    });
+                    return;
+                }
 
-    create_effect(cx, move |_| {
-        if color.get() != user_color.get() {
-            if let Some(mov) = opponent.get_untracked().get_move(board.get_untracked()) {
-                log!("Opponent playing {:?}", mov);
+                log!("User Playing {:?}", mov);
 
                 cx.batch(|| {
+                    set_hint.set(None);
                     set_board.update(|b| b.play(mov));
+                    set_record.update(|r| r.push(mov));
                     set_picker.update(|p| p.clear());
                 });
             }
         }
     });
 
-    let white_captured = create_memo(cx, move |_| {
-        let board = board.get();
-        let mut white = HashMap::new();
+    let play_opponent_move = move |mov: Move| {
+        log!("Opponent playing {:?}", mov);
 
-        board.colors(Color::White).into_iter().for_each(|square| {
-            let piece = board.piece_on(square).expect("Should be piece here");
-            let count = white.entry(piece).or_insert(0);
-            *count += 1;
+        cx.batch(|| {
+            set_board.update(|b| b.play(mov));
+            set_record.update(|r| r.push(mov));
+            set_picker.update(|p| p.clear());
         });
+    };
 
-        let white = map_difference(all_pieces_white.clone(), white);
-        let mut white = white
-            .into_iter()
-            .flat_map(|(piece, count)| {
-                let mut v = Vec::new();
-                for _ in 0..count {
-                    v.push(piece);
-                }
-                v
-            })
-            .collect::<Vec<_>>();
+    create_effect(cx, move |_| {
+        if color.get() != user_color.get() {
+            // puzzles only play the solution's replies
+            if puzzle.get_untracked().is_some() {
+                let mut reply = None;
+                set_puzzle.update(|puzzle| reply = puzzle.as_mut().and_then(|p| p.reply()));
 
-        white.sort_by(|a, b| piece_value(*a).partial_cmp(&piece_value(*b)).unwrap());
+                if let Some(mov) = reply {
+                    play_opponent_move(mov);
+                }
+                return;
+            }
 
-        white
-    });
+            // the search gives way between depths, so the page can show its
+            // thinking and a new game can stop it
+            let player = opponent.get_untracked();
+            let searched = board.get_untracked();
 
-    let black_captured = create_memo(cx, move |_| {
-        let board = board.get();
-        let mut black = HashMap::new();
+            spawn_local(async move {
+                let Some(mov) = player.get_move_async(searched.clone(), next_tick).await else {
+                    return;
+                };
 
-        board.colors(Color::Black).into_iter().for_each(|square| {
-            let piece = board.piece_on(square).expect("Should be piece here");
-            let count = black.entry(piece).or_insert(0);
-            *count += 1;
-        });
+                // the user may have switched sides while it was thinking
+                if board.get_untracked() == searched
+                    && color.get_untracked() != user_color.get_untracked()
+                {
+                    play_opponent_move(mov);
 
-        let black = map_difference(all_pieces_black.clone(), black);
-        let mut black = black
-            .into_iter()
-            .flat_map(|(piece, count)| {
-                let mut v = Vec::new();
-                for _ in 0..count {
-                    v.push(piece);
+                    // think about the likely reply while the user decides.
+                    // stopped by the user's move, a new game or a jump
+                    player.ponder_async(board.get_untracked(), next_tick).await;
                 }
-                v
-            })
-            .collect::<Vec<_>>();
-
-        black.sort_by(|a, b| piece_value(*a).partial_cmp(&piece_value(*b)).unwrap());
+            });
+        }
+    });
 
-        black
+    // worked out from the moves played, so promotions and pieces missing
+    // from the start don't count as captures
+    let captured = create_memo(cx, move |_| {
+        let mut captured = record.get().captured();
+        for pieces in &mut captured {
+            pieces.sort_by_key(|piece| piece_value(*piece));
+        }
+        captured
     });
 
+    // material lead shown by the pieces a side has taken, if it is ahead
+    let lead = move |color: Color| {
+        let lead = record.get().material_lead(color);
+        match lead > 0 {
+            true => format!("+{}", lead),
+            false => String::new(),
+        }
+    };
+
     let needs_promotion = create_memo(cx, move |_| {
         if color.get() != user_color.get() {
             return false;
@@ -262,10 +315,7 @@ pub fn ChessBoard(cx: Scope) -> impl IntoView {
         <div class="flex justify-left mx-auto h-8">
         {
             move || {
-                let pieces = match user_color.get() {
-                    Color::White => white_captured.get(),
-                    Color::Black => black_captured.get(),
-                };
+                let pieces = captured.get()[user_color.get() as usize].clone();
 
                 pieces.into_iter().map(|piece| {
                     view! { cx,
@@ -278,6 +328,9 @@ pub fn ChessBoard(cx: Scope) -> impl IntoView {
                 }).collect::<Vec<_>>()
             }
         }
+        <span class="text-page-text text-xl font-bold my-auto ml-2">
+            {move || lead(user_color.get().flipped())}
+        </span>
         </div>
         <div>
             <div>
@@ -324,10 +377,7 @@ pub fn ChessBoard(cx: Scope) -> impl IntoView {
         <div class="flex justify-left mx-auto h-8">
         {
             move || {
-                let pieces = match user_color.get().flipped() {
-                    Color::White => white_captured.get(),
-                    Color::Black => black_captured.get(),
-                };
+                let pieces = captured.get()[user_color.get().flipped() as usize].clone();
 
                 pieces.into_iter().map(|piece| {
                     view! { cx,
@@ -340,6 +390,9 @@ pub fn ChessBoard(cx: Scope) -> impl IntoView {
                 }).collect::<Vec<_>>()
             }
         }
+        <span class="text-page-text text-xl font-bold my-auto ml-2">
+            {move || lead(user_color.get())}
+        </span>
     </div>
     }
 }
@@ -352,6 +405,8 @@ fn Square(
     picker: ReadSignal<MovePicker>,
     set_picker: WriteSignal<MovePicker>,
 ) -> impl IntoView {
+    let hint = use_context::<ReadSignal<Option<Move>>>(cx).expect("to have found hint");
+
     let color = if (square.rank() as usize + square.file() as usize) % 2 == 0 {
         "bg-chess-green"
     } else {
@@ -365,6 +420,8 @@ fn Square(
                     "shadow-square-inner shadow-green-500"
                 } else if picker.get().to() == Some(square) {
                     "shadow-square-inner shadow-yellow-500"
+                } else if hint.get().is_some_and(|mov| mov.from == square || mov.to == square) {
+                    "shadow-square-inner shadow-blue-500"
                 } else {
                     ""
                 };
@@ -374,7 +431,7 @@ fn Square(
                     format!("overflow-hidden select-none aspect-square {} {}", color, highlight)
                 }
             }
-            on:click=move |_| { set_picker.update(|p| p.set_square(square)) }
+            on:click=move |_| { set_picker.update(|p| p.set_square(square, &board.get_untracked())) }
         >
             <Show when=move || { board.get().piece_on(square).is_some() } fallback=|_| {}>
                 <img
@@ -389,3 +446,52 @@ fn Square(
         </div>
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn pick(board: &Board, squares: &[&str]) -> MovePicker {
+        let mut picker = MovePicker::new();
+        for square in squares {
+            picker.set_square(square.parse().unwrap(), board);
+        }
+        picker
+    }
+
+    #[test]
+    fn test_move_picker_castling() {
+        let board = Board::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1", false).unwrap();
+
+        // onto the rook, or to where the king ends up
+        assert_eq!(pick(&board, &["e1", "h1"]).to(), Some(Square::H1));
+        assert_eq!(pick(&board, &["e1", "g1"]).to(), Some(Square::H1));
+        assert_eq!(pick(&board, &["e1", "c1"]).to(), Some(Square::A1));
+
+        // king on b1 with rooks on a1 and h1, so c1 is both a plain king move
+        // and where castling long puts the king
+        let board =
+            Board::from_fen("rk5r/pppppppp/8/8/8/8/PPPPPPPP/RK5R w HAha - 0 1", true).unwrap();
+        assert_eq!(pick(&board, &["b1", "c1"]).to(), Some(Square::C1));
+        assert_eq!(pick(&board, &["b1", "a1"]).to(), Some(Square::A1));
+        assert_eq!(pick(&board, &["b1", "g1"]).to(), Some(Square::H1));
+    }
+
+    #[test]
+    fn test_move_picker_reselects_own_piece() {
+        let board = Board::startpos();
+
+        let picker = pick(&board, &["g1", "b1"]);
+        assert_eq!((picker.from(), picker.to()), (Some(Square::B1), None));
+
+        let picker = pick(&board, &["g1", "f3"]);
+        assert_eq!(
+            (picker.from(), picker.to()),
+            (Some(Square::G1), Some(Square::F3))
+        );
+
+        // a finished pick starts again
+        let picker = pick(&board, &["g1", "f3", "e2"]);
+        assert_eq!((picker.from(), picker.to()), (Some(Square::E2), None));
+    }
+}
@@ -1,10 +1,12 @@
-mod algorithm;
 mod app;
 mod game;
 mod opponent;
+mod puzzle;
+mod review;
 
 use app::App;
 use leptos::*;
+use tailwind_csr_trunk::algorithm;
 
 pub fn main() {
     _ = console_log::init_with_level(log::Level::Debug);
@@ -1,7 +1,22 @@
-use std::rc::Rc;
+use std::{sync::Arc, time::Duration};
 
-use crate::algorithm::{choose::*, eval::*, score::*, ComputerPlayer};
+use crate::algorithm::{
+    book::OpeningBook,
+    choose::*,
+    difficulty::Difficulty,
+    eval::{mcts::MonteCarloTreeSearch, *},
+    params::EvalParams,
+    score::{
+        nnue::{Network, NnueScore},
+        *,
+    },
+    strength::{Strength, BLUNDER_LOSS, LEVELS},
+    ComputerPlayer,
+};
+use js_sys::{encode_uri_component, Uint8Array};
 use leptos::*;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::HtmlInputElement;
 
 #[component]
 pub fn OpponentMaker(cx: Scope) -> impl IntoView {
@@ -10,14 +25,144 @@ pub fn OpponentMaker(cx: Scope) -> impl IntoView {
     // need to add every eval, score_fn, chooser here
 
     let (current_eval, set_current_eval) = create_signal(cx, "1 Move Lookahead");
+    let (search_depth, set_search_depth) = create_signal(cx, 1);
+    let (node_limit, set_node_limit) = create_signal(cx, None);
+
+    create_effect(cx, move |first_run: Option<()>| {
+        let evaluator = make_evaluator(current_eval.get(), search_depth.get(), node_limit.get());
+
+        // the app picks its own engine to start with
+        if first_run.is_some() {
+            set_opponent.update(|player| player.change_algorithm(evaluator));
+        }
+    });
     let (current_score, set_current_score) = create_signal(cx, "Pawn Difference Score");
+    let (current_book, set_current_book) = create_signal(cx, "Built-in Book");
+    let (current_difficulty, set_current_difficulty) = create_signal(cx, "");
+    let (blunder_rate, set_blunder_rate) = create_signal(cx, 0.0);
+    let (rating, set_rating) = create_signal(cx, 1200);
+
+    // blunders are picked by how much they lose, which only centipawn scores
+    // can tell
+    let blunder_slider_enabled =
+        move || make_evaluator(current_eval.get(), 1, None).scores_centipawns();
+
+    create_effect(cx, move |_| {
+        let blunder_rate = blunder_rate.get();
+
+        let chooser: Arc<dyn Chooser> = match current_difficulty.get() {
+            // the rating's blunder rate goes through the blunder slider
+            "Approximate Rating" => Arc::new(SoftmaxChooser::new(
+                Strength::for_rating(rating.get()).temperature,
+            )),
+            name => Difficulty::ALL
+                .into_iter()
+                .find(|difficulty| difficulty.name() == name)
+                .map_or_else(
+                    || Arc::new(GreedyChooser) as Arc<dyn Chooser>,
+                    |difficulty| difficulty.chooser(),
+                ),
+        };
+
+        let chooser: Arc<dyn Chooser> = match blunder_rate > 0.0 && blunder_slider_enabled() {
+            true => Arc::new(BlunderChooser::new(blunder_rate, BLUNDER_LOSS).with_chooser(chooser)),
+            false => chooser,
+        };
+
+        set_opponent.update(|player| player.change_chooser(chooser));
+        log!("Opponent blunder rate set to {:.0}%", blunder_rate * 100.0);
+    });
+    let (params, set_params) = create_signal(cx, EvalParams::default());
+    let (enabled_terms, set_enabled_terms) = create_signal(
+        cx,
+        terms::all_terms()
+            .iter()
+            .map(|term| term.name())
+            .collect::<Vec<_>>(),
+    );
+
+    let (mix_weights, set_mix_weights) = create_signal(
+        cx,
+        vec![("Pawn Difference Score", 1.0), ("Composite Score", 0.0)],
+    );
+
+    create_effect(cx, move |_| {
+        let params = params.get();
+        let enabled_terms = enabled_terms.get();
+        let mix_weights = mix_weights.get();
+
+        let score_fn: Arc<dyn ScoreFunction> = match current_score.get_untracked() {
+            "Pawn Difference Score" => Arc::new(PawnDifferenceScore::new(params)),
+            "Composite Score" => Arc::new(composite_score(params, &enabled_terms)),
+            "Weighted Sum" => Arc::new(weighted_sum_score(params, &enabled_terms, &mix_weights)),
+            _ => return,
+        };
+
+        set_opponent.update(|player| player.change_score_fn(score_fn));
+        log!("Opponent evaluation parameters updated");
+    });
+
+    // the network replaces the hand-written evaluation, so the parameter
+    // editor doesn't apply to it
+    let upload_network = move |ev| {
+        let input = event_target::<HtmlInputElement>(&ev);
+        let Some(file) = input.files().and_then(|files| files.get(0)) else {
+            return;
+        };
+
+        spawn_local(async move {
+            let bytes = match JsFuture::from(file.array_buffer()).await {
+                Ok(buffer) => Uint8Array::new(&buffer).to_vec(),
+                Err(_) => {
+                    error!("Could not read {}", file.name());
+                    return;
+                }
+            };
+
+            match Network::from_bytes(&bytes) {
+                Ok(network) => {
+                    log!("Loaded network {}", file.name());
+                    let score_fn = Arc::new(NnueScore::new(Arc::new(network)));
+                    set_opponent.update(|player| player.change_score_fn(score_fn));
+                    set_current_score.set("Neural Network");
+                }
+                Err(err) => error!("Could not load {}: {}", file.name(), err),
+            }
+        });
+    };
+
+    let upload_book = move |ev| {
+        let input = event_target::<HtmlInputElement>(&ev);
+        let Some(file) = input.files().and_then(|files| files.get(0)) else {
+            return;
+        };
+
+        spawn_local(async move {
+            let bytes = match JsFuture::from(file.array_buffer()).await {
+                Ok(buffer) => Uint8Array::new(&buffer).to_vec(),
+                Err(_) => {
+                    error!("Could not read {}", file.name());
+                    return;
+                }
+            };
+
+            match OpeningBook::from_bytes(&bytes) {
+                Ok(book) => {
+                    log!("Loaded opening book {}", file.name());
+                    set_opponent.update(|player| player.change_book(Some(Arc::new(book))));
+                    set_current_book.set("Custom Book");
+                }
+                Err(err) => error!("Could not load {}: {}", file.name(), err),
+            }
+        });
+    };
 
     view! { cx,
         <div class="m-4 h-full">
             <div class="text-4xl font-bold text-page-text mb-4">
                 "Opponent Settings"
             </div>
-            <div class="grid grid-cols-3 h-full gap-4 grid-flow-col">
+            <div class="grid grid-cols-4 h-full gap-4 grid-flow-col">
                 <div class="grid grid-rows-6 gap-4">
                     <div>
                         <button class=move || {
@@ -31,7 +176,7 @@ pub fn OpponentMaker(cx: Scope) -> impl IntoView {
                             })
                         }
                         on:click=move |_| {
-                            set_opponent.update(|player| player.change_algorithm(Rc::new(NaiveEvaluator::default())));
+                            set_search_depth.set(1);
                             set_current_eval.set("1 Move Lookahead");
                             log!("Opponent set to NaiveEvaluator");
                         }>
@@ -51,7 +196,7 @@ pub fn OpponentMaker(cx: Scope) -> impl IntoView {
                             })
                         }
                         on:click=move |_| {
-                            set_opponent.update(|player| player.change_algorithm(Rc::new(Negamax::new(3))));
+                            set_search_depth.set(3);
                             set_current_eval.set("Negamax");
                             log!("Opponent set to Negamax");
                         }>
@@ -71,16 +216,74 @@ pub fn OpponentMaker(cx: Scope) -> impl IntoView {
                         })
                     }
                     on:click=move |_| {
-                        set_opponent.update(|player| player.change_algorithm(Rc::new(AlphaBetaNegamax::new(7))));
+                        set_search_depth.set(7);
                         set_current_eval.set("Negamax with Alpha-Beta Pruning");
                         log!("Opponent set to AlphaBetaNegamax");
                     }>
                     "Negamax with Alpha-Beta Pruning"
                     </button>
                     </div>
+
+                    <div>
+                        <button class=move || {
+                            let highlight = current_eval.get() == "Principal Variation Search";
+                            format!("{} {} text-3xl border-2 border-page-dark rounded-md w-full h-full hover:text-white hover:bg-page-dark", match highlight {
+                                true => "text-page-textinverse",
+                                false => "text-page-text",
+                            }, match highlight {
+                                true => "bg-page-text",
+                                false => "",
+                            })
+                        }
+                        on:click=move |_| {
+                            set_search_depth.set(7);
+                            set_current_eval.set("Principal Variation Search");
+                            log!("Opponent set to PrincipalVariationSearch");
+                        }>
+                            "Principal Variation Search"
+                        </button>
+                    </div>
+
+                    <div>
+                        <button class=move || {
+                            let highlight = current_eval.get() == "Monte Carlo Tree Search";
+                            format!("{} {} text-3xl border-2 border-page-dark rounded-md w-full h-full hover:text-white hover:bg-page-dark", match highlight {
+                                true => "text-page-textinverse",
+                                false => "text-page-text",
+                            }, match highlight {
+                                true => "bg-page-text",
+                                false => "",
+                            })
+                        }
+                        on:click=move |_| {
+                            set_current_eval.set("Monte Carlo Tree Search");
+                            log!("Opponent set to MonteCarloTreeSearch");
+                        }>
+                            "Monte Carlo Tree Search"
+                        </button>
+                    </div>
+
+                    <label class="flex flex-col justify-center text-page-text text-xl">
+                        "Node Limit"
+                        <select
+                            class="bg-page-dark border-2 border-page-dark rounded-md"
+                            prop:value=move || node_limit.get().map(|limit| limit.to_string()).unwrap_or_default()
+                            on:change=move |ev| {
+                                let limit = event_target_value(&ev).parse::<usize>().ok();
+                                set_node_limit.set(limit);
+                                log!("Opponent node limit set to {:?}", limit);
+                            }
+                        >
+                            <option value="">"None"</option>
+                            <option value="1000">"1,000"</option>
+                            <option value="10000">"10,000"</option>
+                            <option value="100000">"100,000"</option>
+                            <option value="1000000">"1,000,000"</option>
+                        </select>
+                    </label>
                 </div>
 
-                <div class="grid grid-rows-6 gap-4">
+                <div class="grid grid-rows-9 gap-4">
                     <div>
                         <button class=move || {
                             let highlight = current_score.get() == "Pawn Difference Score";
@@ -93,24 +296,445 @@ pub fn OpponentMaker(cx: Scope) -> impl IntoView {
                             })
                         }
                         on:click=move |_| {
-                            set_opponent.update(|player| player.change_score_fn(Rc::new(PawnDifferenceScore::default())));
+                            set_opponent.update(|player| player.change_score_fn(Arc::new(PawnDifferenceScore::new(params.get_untracked()))));
                             set_current_score.set("Pawn Difference Score");
                             log!("Opponent set to PawnDifferenceScore");
                         }>
                         "Pawn Difference Score"
                         </button>
                     </div>
+
+                    <div>
+                        <button class=move || {
+                            let highlight = current_score.get() == "Composite Score";
+                            format!("{} {} text-3xl border-2 border-page-dark rounded-md w-full h-full hover:text-white hover:bg-page-dark", match highlight {
+                                true => "text-page-textinverse",
+                                false => "text-page-text",
+                            }, match highlight {
+                                true => "bg-page-text",
+                                false => "",
+                            })
+                        }
+                        on:click=move |_| {
+                            set_opponent.update(|player| player.change_score_fn(Arc::new(composite_score(params.get_untracked(), &enabled_terms.get_untracked()))));
+                            set_current_score.set("Composite Score");
+                            log!("Opponent set to CompositeScore");
+                        }>
+                        "Composite Score"
+                        </button>
+                    </div>
+
+                    <div class="flex flex-col justify-center gap-1 text-page-text text-xl">
+                        {terms::all_terms()
+                            .into_iter()
+                            .map(|term| {
+                                let name = term.name();
+                                view! { cx,
+                                    <label class="flex justify-between items-center">
+                                        {name}
+                                        <input
+                                            type="checkbox"
+                                            prop:checked=move || enabled_terms.get().contains(&name)
+                                            on:change=move |ev| {
+                                                let checked = event_target_checked(&ev);
+                                                set_enabled_terms.update(|enabled| {
+                                                    enabled.retain(|enabled_name| *enabled_name != name);
+                                                    if checked {
+                                                        enabled.push(name);
+                                                    }
+                                                });
+                                            }
+                                        />
+                                    </label>
+                                }
+                            })
+                            .collect::<Vec<_>>()}
+                    </div>
+
+                    <div>
+                        <button class=move || {
+                            let highlight = current_score.get() == "Weighted Sum";
+                            format!("{} {} text-3xl border-2 border-page-dark rounded-md w-full h-full hover:text-white hover:bg-page-dark", match highlight {
+                                true => "text-page-textinverse",
+                                false => "text-page-text",
+                            }, match highlight {
+                                true => "bg-page-text",
+                                false => "",
+                            })
+                        }
+                        on:click=move |_| {
+                            set_opponent.update(|player| player.change_score_fn(Arc::new(weighted_sum_score(params.get_untracked(), &enabled_terms.get_untracked(), &mix_weights.get_untracked()))));
+                            set_current_score.set("Weighted Sum");
+                            log!("Opponent set to WeightedSumScore");
+                        }>
+                        "Weighted Sum"
+                        </button>
+                    </div>
+
+                    <div class="flex flex-col justify-center gap-1 text-page-text text-xl">
+                        {mix_weights
+                            .get_untracked()
+                            .into_iter()
+                            .enumerate()
+                            .map(|(i, (name, _))| {
+                                let weight = move || mix_weights.get()[i].1;
+                                view! { cx,
+                                    <label class="flex justify-between items-center gap-2">
+                                        {name}
+                                        <input
+                                            type="range"
+                                            min="0"
+                                            max="2"
+                                            step="0.1"
+                                            prop:value=weight
+                                            on:input=move |ev| {
+                                                match event_target_value(&ev).parse::<f64>() {
+                                                    Ok(value) => set_mix_weights.update(|weights| weights[i].1 = value),
+                                                    Err(_) => warn!("Ignoring invalid {} weight", name),
+                                                }
+                                            }
+                                        />
+                                        {move || format!("{:.1}", weight())}
+                                    </label>
+                                }
+                            })
+                            .collect::<Vec<_>>()}
+                    </div>
+
+                    <div>
+                        <label class=move || {
+                            let highlight = current_score.get() == "Neural Network";
+                            format!("{} {} flex items-center justify-center cursor-pointer text-3xl border-2 border-page-dark rounded-md w-full h-full hover:text-white hover:bg-page-dark", match highlight {
+                                true => "text-page-textinverse",
+                                false => "text-page-text",
+                            }, match highlight {
+                                true => "bg-page-text",
+                                false => "",
+                            })
+                        }>
+                            "Upload Network (.nnue)"
+                            <input type="file" accept=".nnue" class="hidden" on:change=upload_network/>
+                        </label>
+                    </div>
+
+                    <div class="row-span-3 overflow-y-auto">
+                        <EvalParamsEditor params=params set_params=set_params/>
+                    </div>
                 </div>
 
+                <div class="grid grid-rows-6 gap-4">
+                    <div>
+                        <button class=move || {
+                            let highlight = current_book.get() == "Built-in Book";
+                            format!("{} {} text-3xl border-2 border-page-dark rounded-md w-full h-full hover:text-white hover:bg-page-dark", match highlight {
+                                true => "text-page-textinverse",
+                                false => "text-page-text",
+                            }, match highlight {
+                                true => "bg-page-text",
+                                false => "",
+                            })
+                        }
+                        on:click=move |_| {
+                            set_opponent.update(|player| player.change_book(Some(Arc::new(OpeningBook::embedded()))));
+                            set_current_book.set("Built-in Book");
+                            log!("Opponent set to built-in opening book");
+                        }>
+                            "Built-in Book"
+                        </button>
+                    </div>
 
+                    <div>
+                        <button class=move || {
+                            let highlight = current_book.get() == "No Book";
+                            format!("{} {} text-3xl border-2 border-page-dark rounded-md w-full h-full hover:text-white hover:bg-page-dark", match highlight {
+                                true => "text-page-textinverse",
+                                false => "text-page-text",
+                            }, match highlight {
+                                true => "bg-page-text",
+                                false => "",
+                            })
+                        }
+                        on:click=move |_| {
+                            set_opponent.update(|player| player.change_book(None));
+                            set_current_book.set("No Book");
+                            log!("Opponent set to play without an opening book");
+                        }>
+                            "No Book"
+                        </button>
+                    </div>
 
+                    <div>
+                        <label class=move || {
+                            let highlight = current_book.get() == "Custom Book";
+                            format!("{} {} flex items-center justify-center cursor-pointer text-3xl border-2 border-page-dark rounded-md w-full h-full hover:text-white hover:bg-page-dark", match highlight {
+                                true => "text-page-textinverse",
+                                false => "text-page-text",
+                            }, match highlight {
+                                true => "bg-page-text",
+                                false => "",
+                            })
+                        }>
+                            "Upload Book (.bin)"
+                            <input type="file" accept=".bin" class="hidden" on:change=upload_book/>
+                        </label>
+                    </div>
+                </div>
 
+                <div class="grid grid-rows-7 gap-4">
+                    {Difficulty::ALL
+                        .into_iter()
+                        .map(|difficulty| {
+                            view! { cx,
+                                <div>
+                                    <button class=move || {
+                                        let highlight = current_difficulty.get() == difficulty.name();
+                                        format!("{} {} text-3xl border-2 border-page-dark rounded-md w-full h-full hover:text-white hover:bg-page-dark", match highlight {
+                                            true => "text-page-textinverse",
+                                            false => "text-page-text",
+                                        }, match highlight {
+                                            true => "bg-page-text",
+                                            false => "",
+                                        })
+                                    }
+                                    on:click=move |_| {
+                                        // the engine and chooser follow from these in the effects above,
+                                        // without anything a rating left behind
+                                        set_search_depth.set(difficulty.depth());
+                                        set_node_limit.set(None);
+                                        set_blunder_rate.set(0.0);
+                                        set_current_eval.set("Principal Variation Search");
+                                        set_current_difficulty.set(difficulty.name());
+                                        log!("Opponent set to {} difficulty", difficulty.name());
+                                    }>
+                                        {difficulty.name()}
+                                    </button>
+                                </div>
+                            }
+                        })
+                        .collect::<Vec<_>>()}
 
+                    <label class=move || {
+                        let highlight = current_difficulty.get() == "Approximate Rating";
+                        format!("{} {} flex flex-col justify-center text-xl border-2 border-page-dark rounded-md", match highlight {
+                            true => "text-page-textinverse",
+                            false => "text-page-text",
+                        }, match highlight {
+                            true => "bg-page-text",
+                            false => "",
+                        })
+                    }>
+                        {move || format!("Approximate Rating: {}", rating.get())}
+                        <input
+                            type="range"
+                            min=LEVELS[0].rating
+                            max=LEVELS[LEVELS.len() - 1].rating
+                            step="50"
+                            prop:value=move || rating.get()
+                            on:input=move |ev| {
+                                let Ok(value) = event_target_value(&ev).parse::<u32>() else {
+                                    warn!("Ignoring invalid rating");
+                                    return;
+                                };
 
+                                let strength = Strength::for_rating(value);
+                                set_search_depth.set(strength.depth);
+                                set_node_limit.set(strength.node_limit);
+                                set_current_eval.set("Principal Variation Search");
+                                set_rating.set(value);
+                                set_blunder_rate.set(strength.blunder_rate);
+                                set_current_difficulty.set("Approximate Rating");
+                                log!("Opponent set to play at about {}", value);
+                            }
+                        />
+                    </label>
 
+                    <label class="flex flex-col justify-center text-page-text text-xl">
+                        {move || match blunder_slider_enabled() {
+                            true => format!("Blunder Rate: {:.0}%", blunder_rate.get() * 100.0),
+                            false => format!("Blunder Rate: not with {}", current_eval.get()),
+                        }}
+                        <input
+                            type="range"
+                            min="0"
+                            max="1"
+                            step="0.05"
+                            prop:disabled=move || !blunder_slider_enabled()
+                            prop:value=move || blunder_rate.get()
+                            on:input=move |ev| {
+                                match event_target_value(&ev).parse::<f64>() {
+                                    Ok(value) => set_blunder_rate.set(value),
+                                    Err(_) => warn!("Ignoring invalid blunder rate"),
+                                }
+                            }
+                        />
+                    </label>
+                </div>
             </div>
         </div>
+    }
+}
+
+// the web build searches on the page's only thread, where a deep search
+// without a node limit takes minutes, so it stops deepening after this many
+const WEB_NODE_LIMIT: usize = 200_000;
+
+/// Names of the engines `make_evaluator` builds, as the buttons show them.
+pub const EVALUATORS: [&str; 5] = [
+    "1 Move Lookahead",
+    "Negamax",
+    "Negamax with Alpha-Beta Pruning",
+    "Principal Variation Search",
+    "Monte Carlo Tree Search",
+];
+
+pub fn make_evaluator(name: &str, depth: usize, node_limit: Option<usize>) -> Arc<dyn Evaluator> {
+    let deep_node_limit = match cfg!(target_arch = "wasm32") {
+        true => node_limit.or(Some(WEB_NODE_LIMIT)),
+        false => node_limit,
+    };
 
+    match name {
+        "Negamax" => Arc::new(Negamax::new(depth).with_node_limit(node_limit)),
+        "Negamax with Alpha-Beta Pruning" => {
+            Arc::new(AlphaBetaNegamax::new(depth).with_node_limit(deep_node_limit))
+        }
+        "Principal Variation Search" => {
+            Arc::new(PrincipalVariationSearch::new(depth).with_node_limit(deep_node_limit))
+        }
+        // the node limit counts playouts, without one it thinks for a while
+        "Monte Carlo Tree Search" => match node_limit {
+            Some(iterations) => Arc::new(MonteCarloTreeSearch::new(iterations)),
+            None => Arc::new(
+                MonteCarloTreeSearch::new(usize::MAX).with_time_limit(Some(Duration::from_secs(2))),
+            ),
+        },
+        // one ply never gets near a node limit
+        _ => Arc::new(NaiveEvaluator),
+    }
+}
+
+fn composite_score(params: EvalParams, enabled_terms: &[&'static str]) -> CompositeScore {
+    let terms = terms::all_terms()
+        .into_iter()
+        .filter(|term| enabled_terms.contains(&term.name()))
+        .collect();
+
+    CompositeScore::new(params).with_terms(terms)
+}
+
+// the sliders mix the other score functions by name
+fn weighted_sum_score(
+    params: EvalParams,
+    enabled_terms: &[&'static str],
+    mix_weights: &[(&'static str, f64)],
+) -> WeightedSumScore {
+    mix_weights
+        .iter()
+        .filter(|(_, weight)| *weight != 0.0)
+        .fold(WeightedSumScore::default(), |sum, (name, weight)| {
+            let score_fn: Arc<dyn ScoreFunction> = match *name {
+                "Composite Score" => Arc::new(composite_score(params.clone(), enabled_terms)),
+                _ => Arc::new(PawnDifferenceScore::new(params.clone())),
+            };
 
+            sum.with_component(*weight, score_fn)
+        })
+}
+
+// "rook_open_file" is shown as "Rook open file"
+fn weight_label(name: &str) -> String {
+    let label = name.replace('_', " ");
+    let mut chars = label.chars();
+
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => label,
+    }
+}
+
+#[component]
+fn EvalParamsEditor(
+    cx: Scope,
+    params: ReadSignal<EvalParams>,
+    set_params: WriteSignal<EvalParams>,
+) -> impl IntoView {
+    let upload_params = move |ev| {
+        let input = event_target::<HtmlInputElement>(&ev);
+        let Some(file) = input.files().and_then(|files| files.get(0)) else {
+            return;
+        };
+
+        spawn_local(async move {
+            let json = match JsFuture::from(file.text()).await {
+                Ok(text) => text.as_string().unwrap_or_default(),
+                Err(_) => {
+                    error!("Could not read {}", file.name());
+                    return;
+                }
+            };
+
+            match EvalParams::from_json(&json) {
+                Ok(loaded) => {
+                    log!("Loaded evaluation parameters from {}", file.name());
+                    set_params.set(loaded);
+                }
+                Err(err) => error!("Could not load {}: {}", file.name(), err),
+            }
+        });
+    };
+
+    let download_href = move || {
+        let json = params.get().to_json();
+        format!(
+            "data:application/json;charset=utf-8,{}",
+            String::from(encode_uri_component(&json))
+        )
+    };
+
+    view! { cx,
+        <div class="flex flex-col gap-2 text-page-text text-xl">
+            {EvalParams::weight_names()
+                .into_iter()
+                .map(|name| {
+                    view! { cx,
+                        <label class="flex justify-between items-center">
+                            {weight_label(name)}
+                            <input
+                                type="number"
+                                class="w-24 bg-page-dark border-2 border-page-dark rounded-md text-right"
+                                prop:value=move || params.get().weight(name).unwrap_or_default()
+                                on:change=move |ev| {
+                                    match event_target_value(&ev).parse::<i32>() {
+                                        Ok(value) => set_params.update(|params| {
+                                            params.set_weight(name, value);
+                                        }),
+                                        Err(_) => warn!("Ignoring invalid {} value", name),
+                                    }
+                                }
+                            />
+                        </label>
+                    }
+                })
+                .collect::<Vec<_>>()}
+            <div class="grid grid-cols-2 gap-2 mt-2">
+                <a
+                    class="border-2 border-page-dark rounded-md hover:text-white hover:bg-page-dark"
+                    href=download_href
+                    download="eval-params.json"
+                >
+                    "Save JSON"
+                </a>
+                <label class="cursor-pointer border-2 border-page-dark rounded-md hover:text-white hover:bg-page-dark">
+                    "Load JSON"
+                    <input type="file" accept=".json" class="hidden" on:change=upload_params/>
+                </label>
+            </div>
+            <button
+                class="border-2 border-page-dark rounded-md hover:text-white hover:bg-page-dark"
+                on:click=move |_| set_params.set(EvalParams::default())
+            >
+                "Reset Values"
+            </button>
+        </div>
     }
 }
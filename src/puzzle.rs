@@ -0,0 +1,156 @@
+use crate::algorithm::{
+    puzzle::{parse_puzzles, pick_puzzle, PuzzleAttempt, PuzzleStats, PuzzleStep, BUNDLED_PUZZLES},
+    record::GameRecord,
+    ComputerPlayer,
+};
+use cozy_chess::{Board, Color, Move};
+use leptos::*;
+
+const STATS_KEY: &str = "puzzle-stats";
+
+fn load_stats() -> PuzzleStats {
+    window()
+        .local_storage()
+        .ok()
+        .flatten()
+        .and_then(|storage| storage.get_item(STATS_KEY).ok().flatten())
+        .and_then(|json| PuzzleStats::from_json(&json).ok())
+        .unwrap_or_default()
+}
+
+fn save_stats(stats: &PuzzleStats) {
+    let storage = window().local_storage().ok().flatten();
+
+    match storage.map(|storage| storage.set_item(STATS_KEY, &stats.to_json())) {
+        Some(Ok(())) => {}
+        _ => warn!("Couldn't save puzzle stats"),
+    }
+}
+
+/// Signals for the puzzle being played and the player's stats. Stats are
+/// loaded from local storage and saved back whenever they change.
+pub fn provide_puzzle_context(cx: Scope) {
+    let (puzzle, set_puzzle) = create_signal(cx, None::<PuzzleAttempt>);
+    provide_context(cx, puzzle);
+    provide_context(cx, set_puzzle);
+
+    let (stats, set_stats) = create_signal(cx, load_stats());
+    provide_context(cx, stats);
+    provide_context(cx, set_stats);
+
+    create_effect(cx, move |_| save_stats(&stats.get()));
+}
+
+/// Checks the player's move against the puzzle, if one is being played, and
+/// scores the attempt the first time it is failed or when it is solved.
+/// Returns whether the move should be played.
+pub fn check_puzzle_move(
+    set_puzzle: WriteSignal<Option<PuzzleAttempt>>,
+    set_stats: WriteSignal<PuzzleStats>,
+    mov: Move,
+) -> bool {
+    let mut result = None;
+
+    set_puzzle.update(|puzzle| {
+        if let Some(puzzle) = puzzle {
+            let failed = puzzle.failed();
+            result = Some((puzzle.try_move(mov), failed, puzzle.puzzle.rating));
+        }
+    });
+
+    match result {
+        None => true,
+        Some((PuzzleStep::Wrong, failed, rating)) => {
+            if !failed {
+                set_stats.update(|stats| stats.record(rating, false));
+            }
+            false
+        }
+        Some((PuzzleStep::Solved, failed, rating)) => {
+            if !failed {
+                set_stats.update(|stats| stats.record(rating, true));
+            }
+            true
+        }
+        Some((PuzzleStep::Correct, _, _)) => true,
+    }
+}
+
+#[component]
+pub fn PuzzlePanel(cx: Scope) -> impl IntoView {
+    let set_board = use_context::<WriteSignal<Board>>(cx).expect("should be board here");
+    let set_user_color = use_context::<WriteSignal<Color>>(cx).expect("should be color here");
+    let set_record = use_context::<WriteSignal<GameRecord>>(cx).expect("should be record here");
+    let set_opponent =
+        use_context::<WriteSignal<ComputerPlayer>>(cx).expect("should be opponent here");
+    let puzzle =
+        use_context::<ReadSignal<Option<PuzzleAttempt>>>(cx).expect("should be puzzle here");
+    let set_puzzle =
+        use_context::<WriteSignal<Option<PuzzleAttempt>>>(cx).expect("should be puzzle here");
+    let stats = use_context::<ReadSignal<PuzzleStats>>(cx).expect("should be stats here");
+
+    let puzzles = parse_puzzles(BUNDLED_PUZZLES);
+
+    // the opponent's first move is played by the board once the puzzle is set
+    let next_puzzle = move || {
+        let Some(next) = pick_puzzle(
+            &puzzles,
+            stats.get_untracked().rating,
+            &mut rand::thread_rng(),
+        ) else {
+            warn!("No puzzles to play");
+            return;
+        };
+
+        set_opponent.update(|player| player.stop());
+        set_user_color.set(next.player());
+        set_record.set(GameRecord::new(next.start.clone(), 0));
+        set_board.set(next.start.clone());
+        set_puzzle.set(Some(PuzzleAttempt::new(next.clone())));
+        log!("Puzzle {} rated {}", next.id, next.rating);
+    };
+
+    let status = move || {
+        let Some(puzzle) = puzzle.get() else {
+            return "Tactics at your rating".to_string();
+        };
+
+        match puzzle.last_step() {
+            None => format!(
+                "Find the best move for {}",
+                match puzzle.puzzle.player() {
+                    Color::White => "White",
+                    Color::Black => "Black",
+                }
+            ),
+            Some(PuzzleStep::Correct) => "Correct, keep going".to_string(),
+            Some(PuzzleStep::Wrong) => "Not quite, try again".to_string(),
+            Some(PuzzleStep::Solved) => match puzzle.failed() {
+                true => "Solved, after a miss".to_string(),
+                false => "Solved!".to_string(),
+            },
+        }
+    };
+
+    view! {cx,
+        <li class="hover:bg-page-dark">
+            <button class="text-page-text text-3xl font-bold w-full text-left my-4 ml-2 hover:text-white"
+            on:click=move |_| cx.batch(&next_puzzle)>
+                {move || match puzzle.get() {
+                    Some(_) => "Next Puzzle",
+                    None => "Puzzles",
+                }}
+            </button>
+        </li>
+        <li class="text-page-text text-xl text-left mx-2 my-4">
+            "Puzzle"
+            <div class="text-base break-words">{status}</div>
+            <div class="text-base">
+                {move || format!("Rating {}", stats.get().rating)}
+            </div>
+            <div class="text-base">
+                {move || format!("Streak {} (best {})", stats.get().streak, stats.get().best_streak)}
+            </div>
+        </li>
+    }
+}
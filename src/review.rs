@@ -0,0 +1,263 @@
+use crate::{
+    algorithm::{
+        record::GameRecord,
+        review::{review_game_async, MoveClass, MoveReview},
+        ComputerPlayer,
+    },
+    game::next_tick,
+    opponent::{make_evaluator, EVALUATORS},
+};
+use cozy_chess::{Board, Color, Move};
+use leptos::*;
+
+// size of the evaluation graph, and the evaluation at its top and bottom
+const GRAPH_WIDTH: f64 = 400.0;
+const GRAPH_HEIGHT: f64 = 120.0;
+const GRAPH_LIMIT: f64 = 1000.0;
+
+/// A finished review, with the game it was made from so positions can still
+/// be shown after the board has moved on.
+#[derive(Clone)]
+struct Review {
+    record: GameRecord,
+    user_color: Color,
+    moves: Vec<MoveReview>,
+}
+
+impl Review {
+    // the number the move at `ply` has in the game's notation, which counts
+    // on from the start position's
+    fn move_number(&self, ply: usize) -> usize {
+        let start = &self.record.start;
+        let black_first = (start.side_to_move() == Color::Black) as usize;
+        start.fullmove_number() as usize + (ply + black_first) / 2
+    }
+}
+
+fn class_color(class: MoveClass) -> &'static str {
+    match class {
+        MoveClass::Best => "text-green-500",
+        MoveClass::Good => "text-page-text",
+        MoveClass::Inaccuracy => "text-yellow-500",
+        MoveClass::Mistake => "text-orange-500",
+        MoveClass::Blunder => "text-red-500",
+    }
+}
+
+fn graph_point(review: &MoveReview, plies: usize) -> (f64, f64) {
+    let x = GRAPH_WIDTH * (review.ply + 1) as f64 / plies.max(1) as f64;
+    let eval = (review.eval.as_centipawns() as f64).clamp(-GRAPH_LIMIT, GRAPH_LIMIT);
+    let y = GRAPH_HEIGHT / 2.0 * (1.0 - eval / GRAPH_LIMIT);
+
+    (x, y)
+}
+
+#[component]
+pub fn GameReview(cx: Scope) -> impl IntoView {
+    let record = use_context::<ReadSignal<GameRecord>>(cx).expect("should be record here");
+    let set_record = use_context::<WriteSignal<GameRecord>>(cx).expect("should be record here");
+    let set_board = use_context::<WriteSignal<Board>>(cx).expect("should be board here");
+    let user_color = use_context::<ReadSignal<Color>>(cx).expect("should be color here");
+    let opponent = use_context::<ReadSignal<ComputerPlayer>>(cx).expect("should be opponent here");
+    let set_hint = use_context::<WriteSignal<Option<Move>>>(cx).expect("should be hint here");
+
+    let (depth, set_depth) = create_signal(cx, 3);
+    let (evaluator_name, set_evaluator_name) = create_signal(cx, "Negamax with Alpha-Beta Pruning");
+    let (review, set_review) = create_signal(cx, None::<Review>);
+    let (reviewing, set_reviewing) = create_signal(cx, false);
+
+    // only engines that score in centipawns can say how much a move lost
+    let review_evaluators = EVALUATORS
+        .into_iter()
+        .filter(|name| make_evaluator(name, 1, None).scores_centipawns())
+        .collect::<Vec<_>>();
+
+    // searches one position at a time, letting the page repaint in between
+    let run_review = move |_| {
+        if reviewing.get_untracked() {
+            return;
+        }
+
+        let record = record.get_untracked();
+        let user_color = user_color.get_untracked();
+        let evaluator = make_evaluator(evaluator_name.get_untracked(), depth.get_untracked(), None);
+        let score_fn = opponent.with_untracked(|player| player.score_fn());
+
+        set_reviewing.set(true);
+        spawn_local(async move {
+            let moves = review_game_async(&record, evaluator.as_ref(), score_fn, next_tick).await;
+
+            log!("Reviewed {} moves", moves.len());
+            cx.batch(|| {
+                set_review.set(Some(Review {
+                    record,
+                    user_color,
+                    moves,
+                }));
+                set_reviewing.set(false);
+            });
+        });
+    };
+
+    let move_number = move |ply: usize| {
+        review.with(|review| {
+            review
+                .as_ref()
+                .map(|review| review.move_number(ply))
+                .unwrap_or_default()
+        })
+    };
+
+    // back to the position before the move, with the better move drawn. Only
+    // the user's moves can be picked, so the computer doesn't start thinking
+    let jump_to = move |ply: usize, best: Move| {
+        let Some(review) = review.get_untracked() else {
+            return;
+        };
+
+        let mut record = review.record.clone();
+        record.moves.truncate(ply);
+
+        // the position being pondered is gone
+        opponent.with_untracked(|player| player.stop());
+
+        cx.batch(|| {
+            set_board.set(record.board());
+            set_record.set(record);
+            set_hint.set(Some(best));
+        });
+    };
+
+    let user_moves = move || {
+        review
+            .get()
+            .map(|review| {
+                review
+                    .moves
+                    .into_iter()
+                    .filter(|mov| mov.mover == review.user_color)
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default()
+    };
+
+    let summary = move || {
+        let moves = user_moves();
+        MoveClass::ALL
+            .into_iter()
+            .map(|class| {
+                let count = moves.iter().filter(|mov| mov.class == class).count();
+                format!("{} {}", count, class)
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+
+    let graph_line = move || {
+        let Some(review) = review.get() else {
+            return String::new();
+        };
+        let plies = review.moves.len();
+
+        std::iter::once((0.0, GRAPH_HEIGHT / 2.0))
+            .chain(review.moves.iter().map(|mov| graph_point(mov, plies)))
+            .map(|(x, y)| format!("{:.1},{:.1}", x, y))
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+
+    view! {cx,
+        <div class="my-4 text-page-text text-left">
+            <div class="flex gap-4 items-center">
+                <button class="text-page-text text-xl font-bold hover:text-white"
+                prop:disabled=move || reviewing.get()
+                on:click=run_review>
+                    {move || match reviewing.get() {
+                        true => "Reviewing...",
+                        false => "Review Game",
+                    }}
+                </button>
+                <select
+                    class="bg-page-dark border-2 border-page-dark rounded-md"
+                    prop:value=move || evaluator_name.get()
+                    on:change=move |ev| {
+                        let chosen = event_target_value(&ev);
+                        match EVALUATORS.into_iter().find(|name| *name == chosen) {
+                            Some(name) => set_evaluator_name.set(name),
+                            None => warn!("Ignoring unknown review engine"),
+                        }
+                    }
+                >
+                    {review_evaluators
+                        .into_iter()
+                        .map(|name| view! { cx, <option value=name>{name}</option> })
+                        .collect::<Vec<_>>()}
+                </select>
+                <label class="flex gap-2 items-center">
+                    "Depth"
+                    <input
+                        type="number"
+                        min="1"
+                        max="5"
+                        class="w-16 bg-page-dark border-2 border-page-dark rounded-md"
+                        prop:value=move || depth.get().to_string()
+                        on:change=move |ev| {
+                            match event_target_value(&ev).parse::<usize>() {
+                                Ok(value) if (1..=5).contains(&value) => set_depth.set(value),
+                                _ => warn!("Ignoring invalid review depth"),
+                            }
+                        }
+                    />
+                </label>
+            </div>
+            <Show when=move || review.get().is_some() fallback=|_| {}>
+                <svg
+                    class="my-2 bg-page-bar rounded-md"
+                    width=GRAPH_WIDTH
+                    height=GRAPH_HEIGHT
+                    viewBox=format!("0 0 {} {}", GRAPH_WIDTH, GRAPH_HEIGHT)
+                >
+                    <line x1="0" y1={GRAPH_HEIGHT / 2.0} x2=GRAPH_WIDTH y2={GRAPH_HEIGHT / 2.0} stroke="#555555"/>
+                    <polyline points=graph_line fill="none" stroke="#DFDFDE" stroke-width="2"/>
+                    {move || {
+                        let plies = review.get().map(|review| review.moves.len()).unwrap_or_default();
+                        user_moves()
+                            .into_iter()
+                            .map(|mov| {
+                                let (x, y) = graph_point(&mov, plies);
+                                let (ply, best) = (mov.ply, mov.best);
+                                view! { cx,
+                                    <circle
+                                        cx=x
+                                        cy=y
+                                        r="4"
+                                        class=format!("cursor-pointer fill-current {}", class_color(mov.class))
+                                        on:click=move |_| jump_to(ply, best)
+                                    >
+                                        <title>{format!("{} {}: {}", move_number(ply), mov.played, mov.class)}</title>
+                                    </circle>
+                                }
+                            })
+                            .collect::<Vec<_>>()
+                    }}
+                </svg>
+                <div class="text-base">{summary}</div>
+                <ol class="text-base">
+                    {move || user_moves()
+                        .into_iter()
+                        .filter(|mov| mov.class > MoveClass::Good)
+                        .map(|mov| {
+                            let (ply, best) = (mov.ply, mov.best);
+                            view! { cx,
+                                <li class="cursor-pointer hover:text-white" on:click=move |_| jump_to(ply, best)>
+                                    <span class=class_color(mov.class)>{mov.class.to_string()}</span>
+                                    {format!(" on move {}: {} played, {} was better", move_number(ply), mov.played, mov.best)}
+                                </li>
+                            }
+                        })
+                        .collect::<Vec<_>>()}
+                </ol>
+            </Show>
+        </div>
+    }
+}
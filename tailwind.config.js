@@ -30,6 +30,11 @@ module.exports = {
         maxHeight: {
             'piece': '128px',
         },
+        gridTemplateRows: {
+            '7': 'repeat(7, minmax(0, 1fr))',
+            '8': 'repeat(8, minmax(0, 1fr))',
+            '9': 'repeat(9, minmax(0, 1fr))',
+        },
         boxShadow: {
             'square-inner': 'inset 0 0 0 4px rgb(0 0 0 / 0.1)',
         },